@@ -1,20 +1,26 @@
-use std::collections::HashMap;
+use std::time::Duration;
 
 use pyo3::{IntoPyObject, PyErr, Python, types::PyAnyMethods};
-use serde::Deserialize;
+use pythonize::Depythonizer;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::{
     api::{
-        APIMethod, APIMethodParams, APIMethodResult, APIRegister, ExternalCommandInput,
-        utils::try_parse,
+        APIMethodParams, APIMethodResult, APIMethodSpec, APIRegister, ApiError,
+        ExternalCommandInput, PyCallable,
+        utils::{try_parse, try_parse_or_default},
     },
     commands::{
-        command_dispatcher::{CommandFunction, CommandRequest},
-        insert_into_tree,
+        command_dispatcher::{CommandFunction, CommandInfo, CommandMeta, CommandRequest},
+        insert_into_tree, parse_key,
     },
-    engine::document::DocType,
-    input::{Token, keymaps::KeymapProvider},
+    engine::{
+        document::DocType,
+        messages::{Message, MessageLevel},
+    },
+    input::{Token, input_engine::ModeType, keymaps::KeybindScope},
 };
 
 pub struct CommandAPI {}
@@ -29,97 +35,237 @@ impl CommandAPI {
     }
 
     pub fn test(state: &mut APIMethodParams) -> APIMethodResult {
-        println!("HELLO FROM THE API");
+        state
+            .engine
+            .notify(Message::new(MessageLevel::Info, "Hello from the API"));
         Ok(None)
     }
 
     pub fn register_command(state: &mut APIMethodParams) -> APIMethodResult {
         let command = parse_register_params(&state.params)?;
-
-        match command.doc_type {
-            Some(doc_type) => {
-                state.command_dispatch.register_for_doc(
-                    doc_type,
-                    command.id.as_str(),
-                    command.function,
-                );
-            }
-            None => {
-                state
-                    .command_dispatch
-                    .register_global(command.id.as_str(), command.function);
-            }
-        }
-
+        state.command_dispatch.register(
+            command.id.as_str(),
+            command.doc_type,
+            command.function,
+            command.meta,
+        );
         Ok(None)
     }
 
+    pub fn list(state: &mut APIMethodParams) -> APIMethodResult {
+        Ok(Some(serde_json::to_value(state.command_dispatch.list())?))
+    }
+
     pub fn register_keybind(state: &mut APIMethodParams) -> APIMethodResult {
         let command = try_parse::<RegisterKeybind>(&state.params)?;
 
-        // let tree = build_keymap_tree(&command.keys, Token::Command(CommandRequest { id: command.command_id.unwrap().clone(), args: command.params }))?;
-
-        let mut map = state.engine.keymap().clone();
-
-        if command.command_id.is_some() {
-            insert_into_tree(
-                &mut map,
-                &command.keys,
-                Token::Command({
-                    CommandRequest {
-                        id: command.command_id.unwrap().clone(),
-                        args: command.params.unwrap_or_default(),
-                    }
-                }),
-            )?;
-        }
+        let Some(command_id) = command.command_id else {
+            return Ok(None);
+        };
+        let token = Token::Command(CommandRequest {
+            id: command_id,
+            args: command.params.unwrap_or_default(),
+        });
+        let scope = keybind_scope(command.scope, command.mode)?;
+        state
+            .input_engine
+            .edit_keymap(state.engine, &scope, |map| {
+                insert_into_tree(map, &command.keys, token)
+            })??;
+        Ok(None)
+    }
 
-        state.engine.set_keymap_cache(Some(map));
+    pub fn unregister_keybind(state: &mut APIMethodParams) -> APIMethodResult {
+        let params = try_parse::<UnregisterKeybind>(&state.params)?;
+        let keys = params
+            .keys
+            .iter()
+            .map(|key| parse_key(key))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| ApiError::invalid_field("keys", e))?;
+        let scope = keybind_scope(params.scope, params.mode)?;
+        state
+            .input_engine
+            .edit_keymap(state.engine, &scope, |map| map.remove(&keys))?
+            .ok_or_else(|| {
+                ApiError::not_found(format!("`{}` is not bound", params.keys.join(" ")))
+            })?;
+        Ok(None)
+    }
 
+    pub fn list_keybinds(state: &mut APIMethodParams) -> APIMethodResult {
+        let params = try_parse_or_default::<ListKeybinds>(&state.params)?;
+        let mut keybinds = vec![];
+        for (scope, keymap) in state.input_engine.keymaps(state.engine) {
+            if params.scope.as_ref().is_some_and(|only| *only != scope) {
+                continue;
+            }
+            let mut bindings: Vec<KeybindInfo> = keymap
+                .bindings()
+                .into_iter()
+                .map(|(keys, token)| KeybindInfo {
+                    keys: keys.iter().map(|key| key.to_string()).collect(),
+                    scope: scope.clone(),
+                    command: match token {
+                        Token::Command(cmd) => Some(cmd.clone()),
+                        _ => None,
+                    },
+                    builtin: token.builtin_name(),
+                })
+                .collect();
+            bindings.sort_by(|a, b| a.keys.cmp(&b.keys));
+            keybinds.extend(bindings);
+        }
+        Ok(Some(serde_json::to_value(keybinds)?))
+    }
+
+    pub fn set_timeout(state: &mut APIMethodParams) -> APIMethodResult {
+        let params = try_parse::<SetTimeoutParams>(&state.params)?;
+        state.input_engine.timeout = Duration::from_millis(params.ms);
         Ok(None)
     }
 }
 
 impl APIRegister for CommandAPI {
     fn register_methods(api: &mut super::API) {
-        let mut methods: HashMap<&str, APIMethod> = HashMap::new();
-        methods.insert("command.run", Self::run_command);
-        methods.insert("command.register", Self::register_command);
-        methods.insert("command.test", Self::test);
-        methods.insert("keybind.register", Self::register_keybind);
-        api.register_api(methods);
+        api.register_api(vec![
+            APIMethodSpec::new(
+                "command.run",
+                "Runs a command and returns its result.",
+                Self::run_command,
+            )
+            .params::<RunCommandParams>()
+            .returns::<Value>(),
+            APIMethodSpec::new(
+                "command.register",
+                "Registers a Python function as a command, optionally for one document type.",
+                Self::register_command,
+            )
+            .params::<RegisterCommandParams>(),
+            APIMethodSpec::new(
+                "command.list",
+                "Lists global and per-document commands with their metadata.",
+                Self::list,
+            )
+            .returns::<Vec<CommandInfo>>(),
+            APIMethodSpec::new("command.test", "Shows a test message.", Self::test),
+            APIMethodSpec::new(
+                "keybind.register",
+                "Binds a key sequence to a command.",
+                Self::register_keybind,
+            )
+            .params::<RegisterKeybind>(),
+            APIMethodSpec::new(
+                "keybind.unregister",
+                "Removes the binding of a key sequence from one keymap.",
+                Self::unregister_keybind,
+            )
+            .params::<UnregisterKeybind>(),
+            APIMethodSpec::new(
+                "keybind.list",
+                "Lists every key binding, or those in one keymap, with the scope it is in.",
+                Self::list_keybinds,
+            )
+            .optional_params::<ListKeybinds>()
+            .returns::<Vec<KeybindInfo>>(),
+            APIMethodSpec::new(
+                "keybind.set_timeout",
+                "Sets how long a key sequence waits for its next key.",
+                Self::set_timeout,
+            )
+            .params::<SetTimeoutParams>(),
+        ]);
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 struct RunCommandParams {
     command: CommandRequest,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 struct RegisterKeybind {
     keys: Vec<String>,
     command_id: Option<String>,
     params: Option<Vec<Value>>,
+    /// Only bind the keys in this mode: `normal`, `insert`, `visual` or `command`.
+    /// Without a mode they work in every mode, but not for keys typed as text in
+    /// insert mode or on the command line. Short for the `mode` scope.
+    mode: Option<ModeType>,
+    /// The keymap to bind the keys in, instead of `mode`; global if neither is given.
+    scope: Option<KeybindScope>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+struct UnregisterKeybind {
+    keys: Vec<String>,
+    mode: Option<ModeType>,
+    /// The keymap the keys were bound in, instead of `mode`; global if neither is given.
+    scope: Option<KeybindScope>,
 }
 
+#[derive(Deserialize, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+struct ListKeybinds {
+    /// Only list the bindings in this keymap.
+    scope: Option<KeybindScope>,
+}
+
+/// A key binding as listed by `keybind.list`.
+#[derive(Serialize, JsonSchema)]
+struct KeybindInfo {
+    keys: Vec<String>,
+    scope: KeybindScope,
+    /// The command the keys run, if they are bound to one.
+    command: Option<CommandRequest>,
+    /// What the keys do otherwise, e.g. `motion w`.
+    builtin: Option<String>,
+}
+
+/// The keymap a binding request targets: its `scope`, or the keymap of its `mode`.
+/// Giving both is refused, since `mode` would be ignored.
+fn keybind_scope(
+    scope: Option<KeybindScope>,
+    mode: Option<ModeType>,
+) -> Result<KeybindScope, ApiError> {
+    match (scope, mode) {
+        (Some(_), Some(_)) => Err(ApiError::invalid_params(
+            "give either `scope` or `mode`, not both",
+        )),
+        (Some(scope), None) => Ok(scope),
+        (None, mode) => Ok(mode.map_or(KeybindScope::Global, KeybindScope::Mode)),
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+struct SetTimeoutParams {
+    /// Milliseconds, 1000 by default.
+    ms: u64,
+}
+
+#[derive(JsonSchema)]
 struct RegisterCommandParams {
     id: String,
     doc_type: Option<DocType>,
+    #[schemars(with = "PyCallable")]
     function: CommandFunction,
+    #[schemars(flatten)]
+    meta: CommandMeta,
 }
 fn parse_register_params(
     input: &Option<ExternalCommandInput>,
-) -> Result<RegisterCommandParams, String> {
+) -> Result<RegisterCommandParams, ApiError> {
     match input {
         Some(input) => match input {
             ExternalCommandInput::Python(obj) => Python::attach(|py| {
                 let bound_obj = obj.bind(py);
 
                 let res = if bound_obj.is_callable() {
-                    bound_obj.call0().map_err(|e| e.to_string())?
+                    bound_obj.call0()?
                 } else {
                     bound_obj.clone()
                 };
@@ -127,9 +273,8 @@ fn parse_register_params(
                 // Extract fields directly from Python object
                 let id: String = res
                     .get_item("id")
-                    .map_err(|e| e.to_string())?
-                    .extract()
-                    .map_err(|e: PyErr| e.to_string())?;
+                    .and_then(|id| id.extract())
+                    .map_err(|e: PyErr| ApiError::invalid_field("id", e))?;
 
                 let doc_type_str: Option<String> = match res.get_item("doc_type") {
                     Ok(item) => item.extract().ok(),
@@ -138,22 +283,26 @@ fn parse_register_params(
                 let doc_type: Option<DocType> = doc_type_str
                     .map(|s| serde_json::from_value(serde_json::Value::String(s)))
                     .transpose()
-                    .map_err(|e| format!("Invalid doctype: {}", e))?;
+                    .map_err(|e| ApiError::invalid_field("doc_type", e))?;
 
                 let function = res
                     .get_item("function")
-                    .map_err(|e| e.to_string())?
+                    .map_err(|e| ApiError::invalid_field("function", e))?
                     .into_pyobject(py);
+                // the remaining keys are optional metadata; `function` is skipped as unknown
+                let meta: CommandMeta =
+                    serde_path_to_error::deserialize(&mut Depythonizer::from_object(&res))?;
                 Ok(RegisterCommandParams {
                     id,
                     doc_type,
                     function: CommandFunction::Python(function.unwrap().unbind()),
+                    meta,
                 })
             }),
-            ExternalCommandInput::JSON(value) => {
-                Err("JSON input not supported for CommandParams with function".to_string())
-            }
+            ExternalCommandInput::JSON(value) => Err(ApiError::invalid_params(
+                "JSON input not supported for CommandParams with function",
+            )),
         },
-        None => Err("missing input parameters".to_string()),
+        None => Err(ApiError::invalid_params("missing input parameters")),
     }
 }
@@ -0,0 +1,78 @@
+use std::{fs, path::Path};
+
+use crate::{
+    api::{APIMethodParams, APIMethodResult, APIMethodSpec, APIRegister},
+    input::command_line::EX_ALIASES,
+};
+
+pub struct CommandLineAPI {}
+
+impl CommandLineAPI {
+    /// Completes the word before the cursor, or moves on to the next candidate.
+    pub fn complete(state: &mut APIMethodParams) -> APIMethodResult {
+        let line = &mut state.input_engine.command_line;
+        if line.is_completing() {
+            line.next_completion();
+            return Ok(None);
+        }
+        let (start, word, is_command) = line.completion_word();
+        let candidates = if is_command {
+            let doc_type = state.engine.get_current_window().1.doc_type.clone();
+            let mut ids: Vec<String> = state
+                .command_dispatch
+                .list()
+                .into_iter()
+                .filter(|cmd| cmd.doc_type.as_ref().is_none_or(|t| t == &doc_type))
+                .map(|cmd| cmd.id)
+                .chain(EX_ALIASES.iter().map(|(alias, _)| alias.to_string()))
+                .filter(|id| id.starts_with(&word))
+                .collect();
+            ids.sort();
+            ids.dedup();
+            ids
+        } else {
+            complete_path(&word)
+        };
+        state
+            .input_engine
+            .command_line
+            .start_completion(start, candidates);
+        Ok(None)
+    }
+}
+
+/// Entries of the directory named by `word` that start with its last component;
+/// directories end in `/` so completion can continue into them.
+fn complete_path(word: &str) -> Vec<String> {
+    let (dir, prefix) = match word.rsplit_once('/') {
+        Some((dir, prefix)) => (format!("{}/", dir), prefix),
+        None => (String::new(), word),
+    };
+    let search = if dir.is_empty() { "." } else { dir.as_str() };
+    let Ok(entries) = fs::read_dir(Path::new(search)) else {
+        return vec![];
+    };
+    let mut paths: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{}{}{}", dir, name, slash))
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+impl APIRegister for CommandLineAPI {
+    fn register_methods(api: &mut super::API) {
+        api.register_api(vec![APIMethodSpec::new(
+            "cmdline.complete",
+            "Completes the command id or file path before the command-line cursor.",
+            Self::complete,
+        )]);
+    }
+}
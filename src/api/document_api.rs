@@ -1,10 +1,18 @@
-use std::collections::HashMap;
+use std::path::PathBuf;
 
-use serde::Deserialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    api::{APIMethod, APIMethodParams, APIMethodResult, APIRegister, utils::try_parse},
-    input::input_engine::{Mode, ModeType},
+    api::{
+        APIMethodParams, APIMethodResult, APIMethodSpec, APIRegister, ApiError,
+        utils::{try_parse, try_parse_or_default},
+    },
+    engine::{
+        EngineEvent, WindowId,
+        document::{DocId, DocType, DocumentData},
+    },
+    input::input_engine::ModeType,
 };
 
 pub struct DocumentAPI {}
@@ -12,21 +20,148 @@ pub struct DocumentAPI {}
 impl DocumentAPI {
     pub fn change_mode(state: &mut APIMethodParams) -> APIMethodResult {
         let imode = try_parse::<ChangeModeParams>(&state.params)?;
-        state.input_engine.mode = Mode::new(imode.mode);
+        state.input_engine.set_mode(imode.mode, state.engine);
+        Ok(None)
+    }
+
+    pub fn save(state: &mut APIMethodParams) -> APIMethodResult {
+        let params = try_parse_or_default::<SaveParams>(&state.params)?;
+        let doc_id = params
+            .doc_id
+            .unwrap_or_else(|| state.engine.get_current_window().1.id.clone());
+        let doc = state
+            .engine
+            .docs
+            .get_mut(&doc_id)
+            .ok_or_else(|| ApiError::not_found(format!("Document `{}` not found", doc_id)))?;
+        let path = doc.save(params.path.map(PathBuf::from))?;
+        state.engine.emit(&EngineEvent::DocumentSaved(doc_id));
+        Ok(Some(serde_json::to_value(SaveResult { path })?))
+    }
+
+    pub fn open(state: &mut APIMethodParams) -> APIMethodResult {
+        let params = try_parse::<OpenParams>(&state.params)?;
+        let win_id = params
+            .win_id
+            .unwrap_or_else(|| state.engine.active_window.clone());
+        let doc_id = state.engine.open_document(&params.path)?;
+        state.engine.show_document(&win_id, &doc_id)?;
+        Ok(Some(serde_json::to_value(DocIdResult { doc_id })?))
+    }
+
+    pub fn sort(state: &mut APIMethodParams) -> APIMethodResult {
+        let params = try_parse_or_default::<SortParams>(&state.params)?;
+        let (_, doc) = state.engine.get_current_window();
+        match &mut doc.data {
+            DocumentData::SpreadSheet(data) => {
+                let col = params.column.unwrap_or(data.selected_cell.1);
+                data.sort_rows(col, params.descending);
+            }
+            DocumentData::Text(data) => data.sort_lines(params.descending),
+            _ => {
+                return Err(ApiError::WrongDocumentType {
+                    expected: DocType::SpreadSheet,
+                    found: doc.doc_type.clone(),
+                });
+            }
+        }
+        let range = ((0, 0), doc.data.extent());
+        let doc_id = doc.id.clone();
+        state
+            .engine
+            .emit(&EngineEvent::DocumentChanged(doc_id, range));
+        Ok(None)
+    }
+
+    pub fn close(state: &mut APIMethodParams) -> APIMethodResult {
+        let params = try_parse_or_default::<DocIdParams>(&state.params)?;
+        let doc_id = params
+            .doc_id
+            .unwrap_or_else(|| state.engine.get_current_window().1.id.clone());
+        state.engine.close_document(&doc_id)?;
         Ok(None)
     }
 }
 
 impl APIRegister for DocumentAPI {
     fn register_methods(api: &mut super::API) {
-        let mut methods: HashMap<&str, APIMethod> = HashMap::new();
-        methods.insert("doc.changeMode", Self::change_mode);
-        api.register_api(methods);
+        api.register_api(vec![
+            APIMethodSpec::new(
+                "doc.changeMode",
+                "Switches the input mode.",
+                Self::change_mode,
+            )
+            .params::<ChangeModeParams>(),
+            APIMethodSpec::new(
+                "doc.save",
+                "Writes a document, by default the current one, to its path or to `path`.",
+                Self::save,
+            )
+            .optional_params::<SaveParams>()
+            .returns::<SaveResult>(),
+            APIMethodSpec::new(
+                "doc.open",
+                "Opens a file in a window, by default the current one.",
+                Self::open,
+            )
+            .params::<OpenParams>()
+            .returns::<DocIdResult>(),
+            APIMethodSpec::new(
+                "doc.sort",
+                "Sorts the current sheet's rows by a column, or the current text's lines.",
+                Self::sort,
+            )
+            .optional_params::<SortParams>(),
+            APIMethodSpec::new(
+                "doc.close",
+                "Closes a document, by default the current one, and the windows showing it.",
+                Self::close,
+            )
+            .optional_params::<DocIdParams>(),
+        ]);
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 struct ChangeModeParams {
     mode: ModeType,
 }
+
+#[derive(Deserialize, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+struct SaveParams {
+    doc_id: Option<DocId>,
+    path: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct SaveResult {
+    path: PathBuf,
+}
+
+#[derive(Deserialize, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+struct DocIdParams {
+    doc_id: Option<DocId>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+struct OpenParams {
+    path: PathBuf,
+    win_id: Option<WindowId>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct DocIdResult {
+    doc_id: DocId,
+}
+
+#[derive(Deserialize, Default, JsonSchema)]
+#[serde(rename_all = "snake_case", default)]
+struct SortParams {
+    /// Column to sort a sheet by, by default the selected cell's.
+    column: Option<usize>,
+    descending: bool,
+}
@@ -0,0 +1,938 @@
+use std::collections::BTreeMap;
+
+use csv::ReaderBuilder;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::{
+    api::{
+        APIMethodParams, APIMethodResult, APIMethodSpec, APIRegister, ApiError,
+        utils::{try_parse, try_parse_or_default},
+    },
+    engine::{
+        Edit, EngineEvent, WindowId,
+        document::{DocId, DocRange, DocType, DocumentData},
+        documents::{spreadsheet::SpreadSheetDocumentData, text::TextDocumentData},
+        motion::{Motion, Operator, Pos, cell_motion, line_len, text_motion},
+        registers::{Register, RegisterKind, Registers},
+        selection::{Selection, SelectionSize, VisualKind},
+        text_object::{ObjectRange, TextObject, cell_object, text_object},
+    },
+    input::input_engine::ModeType,
+};
+
+/// How far `>` and `<` shift a line.
+const SHIFT_WIDTH: isize = 4;
+
+pub struct EditorAPI {}
+
+impl EditorAPI {
+    /// Moves the cursor by a motion, or applies an operator to the text it moves over.
+    pub fn operator(state: &mut APIMethodParams) -> APIMethodResult {
+        let params = try_parse::<OperatorParams>(&state.params)?;
+        let target = match (params.modifier.as_deref(), params.motion.as_deref()) {
+            (_, None) => Target::Selection,
+            (None, Some(motion)) => Target::Motion(Motion::parse(motion, params.char)?),
+            (Some(modifier @ ("i" | "a")), Some(object)) => {
+                Target::Object(TextObject::parse(object)?, modifier == "a")
+            }
+            (Some(other), _) => {
+                return Err(ApiError::invalid_field(
+                    "modifier",
+                    format!("unknown modifier `{}`, expected `i` or `a`", other),
+                ));
+            }
+        };
+        let operator = params
+            .operator
+            .as_deref()
+            .map(Operator::parse)
+            .transpose()?;
+        let count = params.count.unwrap_or(0) as usize;
+        if operator.is_some() {
+            Registers::check_writable(params.register)?;
+        }
+
+        let (win, doc) = state.engine.get_current_window();
+        let (win_id, doc_id) = (win.id.clone(), doc.id.clone());
+        let cursor = (win.cursor_row, win.cursor_col);
+        let edit = match (&mut doc.data, operator, target) {
+            (DocumentData::Text(data), None, Target::Motion(motion)) => {
+                let Some(target) =
+                    text_motion(&data.data, clamp(data, cursor), motion, count, false)
+                else {
+                    return Ok(None);
+                };
+                let col = match motion {
+                    Motion::FirstLine | Motion::LastLine => data.first_non_blank(target.0),
+                    _ => target.1,
+                };
+                Outcome::moved((target.0, col))
+            }
+            (DocumentData::Text(data), Some(operator), Target::Motion(motion)) => {
+                let Some(edit) = apply(data, clamp(data, cursor), operator, motion, count) else {
+                    return Ok(None);
+                };
+                edit
+            }
+            (DocumentData::Text(data), Some(operator), Target::Object(object, around)) => {
+                let cursor = clamp(data, cursor);
+                match text_object(&data.data, cursor, object, around, count)? {
+                    Some(ObjectRange::Chars(start, end)) if start != end => {
+                        apply_chars(data, operator, start, end)
+                    }
+                    Some(ObjectRange::Lines(first, last)) => {
+                        apply_lines(data, cursor, operator, first, last)
+                    }
+                    _ => return Ok(None),
+                }
+            }
+            (DocumentData::SpreadSheet(data), None, Target::Motion(motion)) => {
+                let target = cell_motion(data.selected_cell, data.extent(), motion, count)?;
+                let selection_changed = data.selected_cell != target;
+                data.selected_cell = target;
+                let mut edit = Outcome::moved(target);
+                edit.selection_changed = selection_changed;
+                edit
+            }
+            // `yy` and `dd` take whole rows of a sheet
+            (DocumentData::SpreadSheet(data), Some(operator), Target::Motion(Motion::Line)) => {
+                let (rows, cols) = data.extent();
+                let row = data.selected_cell.0;
+                let last = (row + count.max(1) - 1).min(rows.saturating_sub(1));
+                apply_cells(data, operator, (row, 0), (last, cols.saturating_sub(1)))?
+            }
+            (DocumentData::SpreadSheet(data), Some(operator), Target::Object(object, around)) => {
+                let (from, to) = cell_object(data, data.selected_cell, object, around)?;
+                apply_cells(data, operator, from, to)?
+            }
+            (DocumentData::Text(data), Some(operator), Target::Selection) => {
+                let (selection, cursor) = match (data.selected, params.selection) {
+                    (Some(selection), _) => (selection, clamp(data, cursor)),
+                    (None, Some(size)) => {
+                        let (selection, to) = size.select(clamp(data, cursor));
+                        (selection, clamp(data, to))
+                    }
+                    (None, None) => return Err(no_selection()),
+                };
+                let (from, to) = selection.corners(cursor);
+                match selection.kind {
+                    VisualKind::Char => apply_chars(data, operator, from, (to.0, to.1 + 1)),
+                    VisualKind::Line => apply_lines(data, cursor, operator, from.0, to.0),
+                    VisualKind::Block => apply_block(data, operator, from, to),
+                }
+            }
+            (DocumentData::SpreadSheet(data), Some(operator), Target::Selection) => {
+                let (selection, cursor) = match (data.selected, params.selection) {
+                    (Some(selection), _) => (selection, data.selected_cell),
+                    (None, Some(size)) => {
+                        let (selection, (row, col)) = size.select(data.selected_cell);
+                        let (rows, cols) = data.extent();
+                        let to = (row.min(rows.max(1) - 1), col.min(cols.max(1) - 1));
+                        (selection, to)
+                    }
+                    (None, None) => return Err(no_selection()),
+                };
+                let (from, to) = selection.corners(cursor);
+                let mut edit = apply_cells(data, operator, from, to)?;
+                if data.selected_cell != from {
+                    data.selected_cell = from;
+                    edit.cursor = from;
+                    edit.selection_changed = true;
+                }
+                edit
+            }
+            (_, None, Target::Selection) => {
+                return Err(ApiError::invalid_field(
+                    "motion",
+                    "a motion is needed unless an operator acts on the visual selection",
+                ));
+            }
+            (_, None, Target::Object(..)) => {
+                return Err(ApiError::invalid_field(
+                    "modifier",
+                    "a text object needs an operator",
+                ));
+            }
+            _ => {
+                return Err(ApiError::WrongDocumentType {
+                    expected: DocType::Text,
+                    found: doc.doc_type.clone(),
+                });
+            }
+        };
+        let change = operator == Some(Operator::Change);
+        finish(state, win_id, doc_id, cursor, edit, params.register)?;
+        if change {
+            state.input_engine.set_mode(ModeType::Input, state.engine);
+        } else if let (Target::Selection, ModeType::Visualize) =
+            (target, &state.input_engine.mode.mode)
+        {
+            state.input_engine.set_mode(ModeType::Normal, state.engine);
+        }
+        Ok(None)
+    }
+
+    /// Starts visual mode with the selection anchored at the cursor, switches the kind
+    /// of selection, or leaves visual mode when the kind is the current one or missing.
+    pub fn visual(state: &mut APIMethodParams) -> APIMethodResult {
+        let params = try_parse_or_default::<VisualParams>(&state.params)?;
+        let (win, doc) = state.engine.get_current_window();
+        let doc_id = doc.id.clone();
+        let cursor = (win.cursor_row, win.cursor_col);
+        let current = doc.data.selection_mut().and_then(|s| s.map(|s| s.kind));
+        let kind = match (params.kind, current) {
+            (Some(kind), Some(current)) if kind == current => None,
+            (kind, _) => kind,
+        };
+        let Some(kind) = kind else {
+            state.input_engine.set_mode(ModeType::Normal, state.engine);
+            return Ok(None);
+        };
+        let anchor = match &doc.data {
+            DocumentData::Text(data) => clamp(data, cursor),
+            DocumentData::SpreadSheet(data) => data.selected_cell,
+            _ => {
+                return Err(ApiError::WrongDocumentType {
+                    expected: DocType::Text,
+                    found: doc.doc_type.clone(),
+                });
+            }
+        };
+        // a sheet selects cells in blocks whichever key started it
+        let kind = match doc.data {
+            DocumentData::SpreadSheet(_) => VisualKind::Block,
+            _ => kind,
+        };
+        if let Some(selection) = doc.data.selection_mut() {
+            match selection {
+                Some(selection) => selection.kind = kind,
+                None => *selection = Some(Selection::new(anchor, kind)),
+            }
+        }
+        state.engine.emit(&EngineEvent::SelectionChanged(doc_id));
+        if !matches!(state.input_engine.mode.mode, ModeType::Visualize) {
+            state
+                .input_engine
+                .set_mode(ModeType::Visualize, state.engine);
+        }
+        Ok(None)
+    }
+
+    /// Puts a register's text after the cursor, or a block of cells at the selected cell.
+    pub fn put(state: &mut APIMethodParams) -> APIMethodResult {
+        let params = try_parse_or_default::<PutParams>(&state.params)?;
+        let register = state
+            .engine
+            .registers
+            .get(params.register)?
+            .ok_or_else(|| {
+                ApiError::invalid_field(
+                    "register",
+                    format!("register `{}` is empty", params.register.unwrap_or('"')),
+                )
+            })?
+            .repeated(params.count.unwrap_or(1) as usize);
+        let register = if params.transpose {
+            register.transposed()
+        } else {
+            register
+        };
+
+        let (win, doc) = state.engine.get_current_window();
+        let (win_id, doc_id) = (win.id.clone(), doc.id.clone());
+        let cursor = (win.cursor_row, win.cursor_col);
+        let edit = match &mut doc.data {
+            DocumentData::Text(data) => {
+                put_text(data, clamp(data, cursor), &register, params.before)
+            }
+            DocumentData::SpreadSheet(data) => {
+                put_cells(data, data.selected_cell, &register.cells())
+            }
+            _ => {
+                return Err(ApiError::WrongDocumentType {
+                    expected: DocType::Text,
+                    found: doc.doc_type.clone(),
+                });
+            }
+        };
+        finish(state, win_id, doc_id, cursor, edit, None)?;
+        Ok(None)
+    }
+
+    /// Inserts pasted text at the cursor in one edit, or on a spreadsheet spreads rows
+    /// of tab- or comma-separated values over the cells from the selected one.
+    pub fn paste(state: &mut APIMethodParams) -> APIMethodResult {
+        let params = try_parse::<PasteParams>(&state.params)?;
+        // terminals send line breaks in a paste as carriage returns
+        let text = params.text.replace("\r\n", "\n").replace('\r', "\n");
+        let typing = matches!(state.input_engine.mode.mode, ModeType::Input);
+
+        let (win, doc) = state.engine.get_current_window();
+        let (win_id, doc_id) = (win.id.clone(), doc.id.clone());
+        let cursor = (win.cursor_row, win.cursor_col);
+        let mut undo_stack = std::mem::take(&mut doc.undo_stack);
+        let (edit, undo) = match &mut doc.data {
+            DocumentData::Text(data) => {
+                let lines: Vec<String> = text.split('\n').map(str::to_string).collect();
+                let end = data.insert_text(cursor, &lines);
+                // typing goes on after the text, otherwise the cursor rests on its end
+                let mut edit = match typing {
+                    true => Outcome::moved(end),
+                    false => Outcome::moved(clamp(data, (end.0, end.1.saturating_sub(1)))),
+                };
+                edit.changed = Some((cursor, end));
+                let undo = Edit::Inserted {
+                    start: cursor,
+                    end,
+                    cursor,
+                };
+                (edit, undo)
+            }
+            DocumentData::SpreadSheet(data) => {
+                let cells = split_cells(&text).map_err(|e| ApiError::invalid_field("text", e))?;
+                let at = data.selected_cell;
+                let before = cells
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(row, values)| {
+                        (0..values.len()).map(move |col| (at.0 + row, at.1 + col))
+                    })
+                    .map(|cell| (cell, data.range_text(cell, cell).concat()))
+                    .collect();
+                (put_cells(data, at, &cells), Edit::Cells(before))
+            }
+            _ => {
+                return Err(ApiError::WrongDocumentType {
+                    expected: DocType::Text,
+                    found: doc.doc_type.clone(),
+                });
+            }
+        };
+        undo_stack.push(undo);
+        finish(state, win_id, doc_id.clone(), cursor, edit, None)?;
+        keep_undo(state, &doc_id, undo_stack);
+        Ok(None)
+    }
+
+    /// Reverts the last change on the current document's undo stack.
+    pub fn undo(state: &mut APIMethodParams) -> APIMethodResult {
+        let (win, doc) = state.engine.get_current_window();
+        let (win_id, doc_id) = (win.id.clone(), doc.id.clone());
+        let cursor = (win.cursor_row, win.cursor_col);
+        let mut undo_stack = std::mem::take(&mut doc.undo_stack);
+        let undo = undo_stack.pop().ok_or_else(|| ApiError::Failed {
+            message: "nothing to undo".to_string(),
+        })?;
+        let edit = match (&mut doc.data, undo) {
+            (DocumentData::Text(data), Edit::Inserted { start, end, cursor }) => {
+                data.delete_range(start, end);
+                let mut edit = Outcome::moved(cursor);
+                edit.changed = Some((start, end));
+                edit
+            }
+            (DocumentData::SpreadSheet(data), Edit::Cells(before)) => {
+                for ((row, col), raw) in &before {
+                    data.set_raw(*row, *col, raw);
+                }
+                let mut edit = Outcome::moved(cursor);
+                let first = before.iter().map(|(cell, _)| *cell).min().unwrap_or(cursor);
+                let last = before.iter().map(|(cell, _)| *cell).max().unwrap_or(cursor);
+                edit.changed = Some((first, (last.0 + 1, last.1 + 1)));
+                edit
+            }
+            _ => {
+                return Err(ApiError::Failed {
+                    message: "the undo entry does not fit the document".to_string(),
+                });
+            }
+        };
+        finish(state, win_id, doc_id.clone(), cursor, edit, None)?;
+        keep_undo(state, &doc_id, undo_stack);
+        Ok(None)
+    }
+
+    /// Repeats the last change, typing its insert session again if it started one.
+    pub fn repeat(state: &mut APIMethodParams) -> APIMethodResult {
+        let params = try_parse_or_default::<RepeatParams>(&state.params)?;
+        let change = state
+            .input_engine
+            .last_change
+            .as_mut()
+            .ok_or_else(|| ApiError::Failed {
+                message: "no change to repeat".to_string(),
+            })?;
+        // like vim, a count given to `.` replaces the change's for later repeats too
+        if let Some(count) = params.count {
+            change.set_count(count);
+        }
+        let change = change.clone();
+        state.command_dispatch.dispatch(
+            &change.command,
+            state.engine,
+            state.input_engine,
+            state.ui,
+        )?;
+        state.input_engine.play_macro(change.typed)?;
+        Ok(None)
+    }
+
+    pub fn registers(state: &mut APIMethodParams) -> APIMethodResult {
+        Ok(Some(serde_json::to_value(state.engine.registers.list())?))
+    }
+
+    /// Fills a register, e.g. for a plugin to offer text to put.
+    pub fn set_register(state: &mut APIMethodParams) -> APIMethodResult {
+        let params = try_parse::<SetRegisterParams>(&state.params)?;
+        if params.name == '+' {
+            state.ui.set_clipboard(&params.register.text.join("\n"))?;
+        }
+        state.engine.registers.set(params.name, params.register)?;
+        Ok(None)
+    }
+}
+
+/// Moves the window's cursor, stores what was yanked and emits events for an outcome.
+fn finish(
+    state: &mut APIMethodParams,
+    win_id: WindowId,
+    doc_id: DocId,
+    before: Pos,
+    edit: Outcome,
+    register: Option<char>,
+) -> Result<(), ApiError> {
+    if let Some(win) = state.engine.windows.get_mut(&win_id) {
+        win.cursor_row = edit.cursor.0;
+        win.cursor_col = edit.cursor.1;
+    }
+    if let Some(yanked) = edit.yanked {
+        if register == Some('+') {
+            state.ui.set_clipboard(&yanked.text.join("\n"))?;
+        }
+        state.engine.registers.store(register, yanked)?;
+    }
+    if let Some(range) = edit.changed {
+        state
+            .engine
+            .emit(&EngineEvent::DocumentChanged(doc_id.clone(), range));
+    }
+    if edit.cursor != before {
+        state.engine.emit(&EngineEvent::CursorMoved(
+            win_id,
+            edit.cursor.0,
+            edit.cursor.1,
+        ));
+    }
+    if edit.selection_changed {
+        state.engine.emit(&EngineEvent::SelectionChanged(doc_id));
+    }
+    Ok(())
+}
+
+/// Puts back an undo stack set aside while [`finish`] reported a paste or undo, as
+/// reporting a change empties it.
+fn keep_undo(state: &mut APIMethodParams, doc_id: &DocId, undo_stack: Vec<Edit>) {
+    if let Some(doc) = state.engine.docs.get_mut(doc_id) {
+        doc.undo_stack = undo_stack;
+    }
+}
+
+/// What the operator acts on: the text a motion moves over, a text object, inner or
+/// around, or the visual selection.
+#[derive(Clone, Copy)]
+enum Target {
+    Motion(Motion),
+    Object(TextObject, bool),
+    Selection,
+}
+
+fn no_selection() -> ApiError {
+    ApiError::invalid_field("motion", "a motion or a visual selection is needed")
+}
+
+/// What an operator or motion did, applied to the window and engine once the document
+/// is no longer borrowed.
+struct Outcome {
+    cursor: Pos,
+    changed: Option<DocRange>,
+    yanked: Option<Register>,
+    selection_changed: bool,
+}
+impl Outcome {
+    fn moved(cursor: Pos) -> Self {
+        Self {
+            cursor,
+            changed: None,
+            yanked: None,
+            selection_changed: false,
+        }
+    }
+}
+
+/// Keeps a normal mode cursor on a character of an existing line.
+fn clamp(data: &TextDocumentData, (row, col): Pos) -> Pos {
+    let row = row.min(data.data.len().saturating_sub(1));
+    (row, col.min(line_len(&data.data, row).saturating_sub(1)))
+}
+
+fn apply(
+    data: &mut TextDocumentData,
+    cursor: Pos,
+    operator: Operator,
+    motion: Motion,
+    count: usize,
+) -> Option<Outcome> {
+    // like vim, `cw` on a word only changes up to its end
+    let on_word = data
+        .data
+        .get(cursor.0)
+        .and_then(|line| line.chars().nth(cursor.1))
+        .is_some_and(|c| !c.is_whitespace());
+    let motion = match motion {
+        Motion::WordForward if operator == Operator::Change && on_word => Motion::WordEnd,
+        motion => motion,
+    };
+    let target = text_motion(&data.data, cursor, motion, count, true)?;
+    if motion.is_linewise() || matches!(operator, Operator::Indent | Operator::Outdent) {
+        let (first, last) = (cursor.0.min(target.0), cursor.0.max(target.0));
+        return Some(apply_lines(data, cursor, operator, first, last));
+    }
+
+    let (start, mut end) = (cursor.min(target), cursor.max(target));
+    if motion.is_inclusive() {
+        end.1 += 1;
+    } else if end.0 > start.0 && (end.1 == 0 || motion == Motion::WordForward) {
+        // an exclusive motion into the next line stops at the end of the previous one,
+        // unless it moved over text there
+        let blank_before = data.data[end.0]
+            .chars()
+            .take(end.1)
+            .all(char::is_whitespace);
+        if blank_before {
+            end = (end.0 - 1, line_len(&data.data, end.0 - 1));
+        }
+    }
+    // `$` on an empty line covers nothing, even though it is inclusive
+    end.1 = end.1.min(line_len(&data.data, end.0));
+    if start == end {
+        return None;
+    }
+    Some(apply_chars(data, operator, start, end))
+}
+
+/// Applies an operator to the text from `start` up to `end`.
+fn apply_chars(data: &mut TextDocumentData, operator: Operator, start: Pos, end: Pos) -> Outcome {
+    let yanked = Some(Register::new(
+        data.text_range(start, end),
+        RegisterKind::Chars,
+    ));
+    let changed = match operator {
+        Operator::Yank => None,
+        Operator::Indent | Operator::Outdent => {
+            data.indent_lines(start.0, end.0, shift_width(operator));
+            Some(((start.0, 0), (end.0 + 1, 0)))
+        }
+        _ => {
+            data.delete_range(start, end);
+            Some((start, end))
+        }
+    };
+    let cursor = match operator {
+        Operator::Change => start,
+        _ => clamp(data, start),
+    };
+    Outcome {
+        cursor,
+        changed,
+        yanked,
+        selection_changed: false,
+    }
+}
+
+/// Applies an operator to the columns `from.1..=to.1` of the lines `from.0..=to.0`;
+/// the register keeps them as a block.
+fn apply_block(data: &mut TextDocumentData, operator: Operator, from: Pos, to: Pos) -> Outcome {
+    let end = to.1 + 1;
+    let block: Vec<String> = (from.0..=to.0)
+        .map(|row| data.text_range((row, from.1), (row, end)).concat())
+        .collect();
+    let changed = match operator {
+        Operator::Yank => None,
+        Operator::Indent | Operator::Outdent => {
+            data.indent_lines(from.0, to.0, shift_width(operator));
+            Some(((from.0, 0), (to.0 + 1, 0)))
+        }
+        Operator::Delete | Operator::Change => {
+            for row in from.0..=to.0 {
+                data.delete_range((row, from.1), (row, end));
+            }
+            Some(((from.0, 0), (to.0 + 1, 0)))
+        }
+    };
+    let cursor = match operator {
+        Operator::Change => from,
+        _ => clamp(data, from),
+    };
+    Outcome {
+        cursor,
+        changed,
+        yanked: Some(Register::new(block, RegisterKind::Block)),
+        selection_changed: false,
+    }
+}
+
+/// Applies an operator to whole lines `first..=last`.
+fn apply_lines(
+    data: &mut TextDocumentData,
+    cursor: Pos,
+    operator: Operator,
+    first: usize,
+    last: usize,
+) -> Outcome {
+    let last = last.min(data.data.len().saturating_sub(1));
+    let lines = Register::new(data.line_range(first, last), RegisterKind::Lines);
+    let mut edit = Outcome::moved((first, 0));
+    edit.changed = Some(((first, 0), (last + 1, 0)));
+    match operator {
+        Operator::Yank => {
+            edit.cursor = clamp(data, (first, cursor.1));
+            edit.changed = None;
+            edit.yanked = Some(lines);
+        }
+        Operator::Delete => {
+            data.delete_lines(first, last);
+            let row = first.min(data.data.len() - 1);
+            edit.cursor = (row, data.first_non_blank(row));
+            edit.yanked = Some(lines);
+        }
+        Operator::Change => {
+            if first < data.data.len() {
+                data.data.splice(first..=last, [String::new()]);
+            }
+            edit.yanked = Some(lines);
+        }
+        Operator::Indent | Operator::Outdent => {
+            data.indent_lines(first, last, shift_width(operator));
+            edit.cursor = (first, data.first_non_blank(first));
+        }
+    }
+    edit
+}
+
+/// Puts text after the cursor, or lines below its line; `before` puts it at the cursor
+/// or above instead.
+fn put_text(
+    data: &mut TextDocumentData,
+    cursor: Pos,
+    register: &Register,
+    before: bool,
+) -> Outcome {
+    if data.data.is_empty() {
+        data.data.push(String::new());
+    }
+    if register.kind == RegisterKind::Block {
+        return put_block(data, cursor, register, before);
+    }
+    if register.kind != RegisterKind::Chars {
+        let row = if before { cursor.0 } else { cursor.0 + 1 };
+        let height = register.text.len();
+        data.data.splice(row..row, register.text.iter().cloned());
+        let mut edit = Outcome::moved((row, data.first_non_blank(row)));
+        edit.changed = Some(((row, 0), (row + height, 0)));
+        return edit;
+    }
+    let after = !before && line_len(&data.data, cursor.0) > 0;
+    let start = if after {
+        (cursor.0, cursor.1 + 1)
+    } else {
+        cursor
+    };
+    let end = data.insert_text(start, &register.text);
+    // the cursor ends on the last character put, or at the start of several lines
+    let cursor = if end.0 == start.0 {
+        (end.0, end.1.saturating_sub(1))
+    } else {
+        start
+    };
+    let mut edit = Outcome::moved(cursor);
+    edit.changed = Some((start, end));
+    edit
+}
+
+/// Puts each line of a block on its own line from the cursor down, all starting in
+/// the same column, padding short lines with spaces.
+fn put_block(
+    data: &mut TextDocumentData,
+    cursor: Pos,
+    register: &Register,
+    before: bool,
+) -> Outcome {
+    let col = if before || line_len(&data.data, cursor.0) == 0 {
+        cursor.1
+    } else {
+        cursor.1 + 1
+    };
+    for (i, piece) in register.text.iter().enumerate() {
+        let row = cursor.0 + i;
+        if row >= data.data.len() {
+            data.data.push(String::new());
+        }
+        let len = line_len(&data.data, row);
+        if len < col {
+            data.data[row].push_str(&" ".repeat(col - len));
+        }
+        data.insert_text((row, col), std::slice::from_ref(piece));
+    }
+    let mut edit = Outcome::moved((cursor.0, col));
+    edit.changed = Some(((cursor.0, 0), (cursor.0 + register.text.len(), 0)));
+    edit
+}
+
+/// Puts a block of cells with its top-left corner at `cell`, overwriting what is there.
+fn put_cells(data: &mut SpreadSheetDocumentData, cell: Pos, cells: &[Vec<String>]) -> Outcome {
+    let width = cells.iter().map(Vec::len).max().unwrap_or(0);
+    for (row, values) in cells.iter().enumerate() {
+        for (col, value) in values.iter().enumerate() {
+            data.set_raw(cell.0 + row, cell.1 + col, value);
+        }
+    }
+    let mut edit = Outcome::moved(cell);
+    edit.changed = Some((cell, (cell.0 + cells.len(), cell.1 + width)));
+    edit
+}
+
+/// Splits pasted text into rows of cells: at tabs if it has any, as copied from
+/// another spreadsheet, and otherwise as CSV.
+fn split_cells(text: &str) -> Result<Vec<Vec<String>>, String> {
+    // a copied range usually ends its last row with a line break
+    let text = text.strip_suffix('\n').unwrap_or(text);
+    if text.contains('\t') {
+        return Ok(text
+            .split('\n')
+            .map(|line| line.split('\t').map(str::to_string).collect())
+            .collect());
+    }
+    ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes())
+        .records()
+        .map(|record| {
+            let record = record.map_err(|e| e.to_string())?;
+            Ok(record.iter().map(str::to_string).collect())
+        })
+        .collect()
+}
+
+fn shift_width(operator: Operator) -> isize {
+    match operator {
+        Operator::Outdent => -SHIFT_WIDTH,
+        _ => SHIFT_WIDTH,
+    }
+}
+
+/// Applies an operator to the cells between two inclusive corners; deleting empties
+/// them, and the contents go to the register a row per line.
+fn apply_cells(
+    data: &mut SpreadSheetDocumentData,
+    operator: Operator,
+    from: Pos,
+    to: Pos,
+) -> Result<Outcome, ApiError> {
+    let yanked = Some(Register::new(
+        data.range_text(from, to),
+        RegisterKind::Cells,
+    ));
+    let changed = match operator {
+        Operator::Yank => None,
+        Operator::Delete | Operator::Change => {
+            data.clear_range(from, to);
+            Some((from, (to.0 + 1, to.1 + 1)))
+        }
+        Operator::Indent | Operator::Outdent => {
+            return Err(ApiError::invalid_field(
+                "operator",
+                "cells cannot be shifted",
+            ));
+        }
+    };
+    let selection_changed = data.selected_cell != from;
+    data.selected_cell = from;
+    Ok(Outcome {
+        cursor: from,
+        changed,
+        yanked,
+        selection_changed,
+    })
+}
+
+impl APIRegister for EditorAPI {
+    fn register_methods(api: &mut super::API) {
+        api.register_api(vec![
+            APIMethodSpec::new(
+                "editor.operator",
+                "Moves the cursor by a normal mode motion, or applies an operator over it.",
+                Self::operator,
+            )
+            .params::<OperatorParams>(),
+            APIMethodSpec::new(
+                "editor.put",
+                "Puts a register after the cursor, or at the selected cell of a sheet.",
+                Self::put,
+            )
+            .optional_params::<PutParams>(),
+            APIMethodSpec::new(
+                "editor.visual",
+                "Starts, switches or ends visual mode with a selection anchored at the cursor.",
+                Self::visual,
+            )
+            .optional_params::<VisualParams>(),
+            APIMethodSpec::new(
+                "editor.paste",
+                "Inserts pasted text at the cursor, or spreads it over the cells of a sheet.",
+                Self::paste,
+            )
+            .params::<PasteParams>(),
+            APIMethodSpec::new(
+                "editor.undo",
+                "Reverts the last change on the current document's undo stack, like `u`.",
+                Self::undo,
+            ),
+            APIMethodSpec::new(
+                "editor.repeat",
+                "Repeats the last change made in normal mode, like `.`.",
+                Self::repeat,
+            )
+            .optional_params::<RepeatParams>(),
+            APIMethodSpec::new(
+                "registers.list",
+                "Returns every register that holds something, by name.",
+                Self::registers,
+            )
+            .returns::<BTreeMap<char, Register>>(),
+            APIMethodSpec::new(
+                "registers.set",
+                "Fills a register; `+` also copies to the system clipboard.",
+                Self::set_register,
+            )
+            .params::<SetRegisterParams>(),
+        ]);
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+struct PasteParams {
+    /// The pasted text; on a spreadsheet, lines are rows and tabs or commas part cells.
+    text: String,
+}
+
+#[derive(Deserialize, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+struct RepeatParams {
+    /// Replaces the count the change was made with.
+    count: Option<u32>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+struct OperatorParams {
+    /// `d`, `c`, `y`, `>` or `<`; without one the cursor just moves.
+    operator: Option<String>,
+    /// How many times to repeat the motion, or the line `gg` and `G` go to.
+    count: Option<u32>,
+    /// A motion key such as `w`, `$`, `gg` or `f`, or `line` for a doubled operator.
+    /// Left out, the operator acts on the visual selection.
+    motion: Option<String>,
+    /// The character `f`, `t`, `F` and `T` search for.
+    char: Option<char>,
+    /// `i` or `a` to make `motion` a text object: inner or around `w`, a quote,
+    /// a bracket, `p`aragraph, or on a spreadsheet the `r`ow, `c`olumn or data region `p`.
+    modifier: Option<String>,
+    /// The register to yank into: `a` to `z`, `A` to `Z` to append, or `+`.
+    register: Option<char>,
+    /// Without a motion or visual selection, acts on a selection this size from the
+    /// cursor, as `.` repeats an operator used in visual mode.
+    selection: Option<SelectionSize>,
+}
+
+#[derive(Deserialize, Default, JsonSchema)]
+#[serde(rename_all = "snake_case", default)]
+struct VisualParams {
+    /// `char`, `line` or `block`; left out, visual mode ends.
+    kind: Option<VisualKind>,
+}
+
+#[derive(Deserialize, Default, JsonSchema)]
+#[serde(rename_all = "snake_case", default)]
+struct PutParams {
+    /// The register to put, by default the unnamed one.
+    register: Option<char>,
+    /// Put at the cursor or above its line, like `P`, instead of after it.
+    before: bool,
+    count: Option<u32>,
+    /// Swap the rows and columns of a block of cells.
+    transpose: bool,
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+struct SetRegisterParams {
+    name: char,
+    register: Register,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(cells: &[&[&str]]) -> Vec<Vec<String>> {
+        cells
+            .iter()
+            .map(|row| row.iter().map(|c| c.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn split_cells_prefers_tabs() {
+        assert_eq!(
+            split_cells("1\t2\n3\t4\n"),
+            Ok(rows(&[&["1", "2"], &["3", "4"]]))
+        );
+        assert_eq!(split_cells("a,b\t\tc"), Ok(rows(&[&["a,b", "", "c"]])));
+    }
+
+    #[test]
+    fn split_cells_reads_csv_without_tabs() {
+        assert_eq!(split_cells("plain"), Ok(rows(&[&["plain"]])));
+        assert_eq!(
+            split_cells("x,\"y, z\"\nw\n"),
+            Ok(rows(&[&["x", "y, z"], &["w"]]))
+        );
+        assert_eq!(split_cells("\"a\nb\",é"), Ok(rows(&[&["a\nb", "é"]])));
+        assert_eq!(split_cells(""), Ok(vec![]));
+    }
+
+    fn text(lines: &[&str]) -> TextDocumentData {
+        TextDocumentData {
+            data: lines.iter().map(|l| l.to_string()).collect(),
+            selected: None,
+        }
+    }
+
+    #[test]
+    fn delete_to_line_end_of_an_empty_line_does_nothing() {
+        let mut data = text(&["", "b"]);
+        let edit = apply(&mut data, (0, 0), Operator::Delete, Motion::LineEnd, 1);
+        assert!(edit.is_none());
+        assert_eq!(data.data, ["", "b"]);
+    }
+
+    #[test]
+    fn delete_to_line_end_counts_characters() {
+        let mut data = text(&["héllo", "b"]);
+        let edit = apply(&mut data, (0, 1), Operator::Delete, Motion::LineEnd, 1).unwrap();
+        assert_eq!(edit.yanked.unwrap().text, ["éllo"]);
+        assert_eq!(data.data, ["h", "b"]);
+    }
+}
@@ -1,18 +1,20 @@
 use crate::{
     api::{
-        APIMethod, APIMethodParams, APIMethodResult, APIRegister, ExternalCommandInput,
+        APIMethodParams, APIMethodResult, APIMethodSpec, APIRegister, ApiError,
+        ExternalCommandInput,
         utils::{self, try_parse},
     },
     engine::{
         EngineEvent, SplitDirection, WindowState,
+        document::{Document, DocumentData},
         layout::{LayoutNode, SplitDir},
         popup::{PopupPosition, PopupWindow, RelativeTo},
     },
     render::helpers::BorderStyle,
 };
-use serde::Deserialize;
-use serde_json::json;
-use std::collections::HashMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
 
 pub struct EngineAPI {}
 
@@ -29,8 +31,9 @@ impl EngineAPI {
                 ratio,
             } => {
                 if state.engine.layout.is_none() {
-                    return Err("Invalid Layout".to_string());
+                    return Err("Invalid Layout".into());
                 }
+                state.engine.unzoom();
                 let (win_id, mut win) = WindowState::new(doc);
                 win.border_style = border;
                 state.engine.windows.insert(win_id.clone(), win);
@@ -78,8 +81,7 @@ impl EngineAPI {
                     }
                     state
                         .engine
-                        .events
-                        .push(EngineEvent::WindowCreate(win_id.clone()));
+                        .emit(&EngineEvent::WindowCreate(win_id.clone()));
                 }
             }
             CreateWindowParams::Floating {
@@ -101,9 +103,20 @@ impl EngineAPI {
                 window.border_style = border;
 
                 if matches!(relative, RelativeTo::Win(_)) && win.is_none() {
-                    return Err("relative = win required a window id \"win\"".to_string());
+                    return Err(ApiError::invalid_params(
+                        "relative = win required a window id \"win\"",
+                    ));
                 }
 
+                // one popup is shown at a time, so an open one is closed rather than lost
+                if let Some(PopupWindow {
+                    layout: LayoutNode::Leaf(old),
+                    ..
+                }) = &state.engine.popups
+                {
+                    let old = old.clone();
+                    state.engine.close_window(&old)?;
+                }
                 state.engine.windows.insert(win_id.clone(), window);
                 let relative = if let Some(win) = win {
                     RelativeTo::Win(win)
@@ -125,8 +138,7 @@ impl EngineAPI {
                 }
                 state
                     .engine
-                    .events
-                    .push(EngineEvent::WindowCreate(win_id.clone()));
+                    .emit(&EngineEvent::WindowCreate(win_id.clone()));
             }
         }
         Ok(None)
@@ -138,34 +150,12 @@ impl EngineAPI {
     }
     pub fn get_window(state: &mut APIMethodParams) -> APIMethodResult {
         let win_id = utils::try_parse::<WindowIdParams>(&state.params)?.win_id;
-        let (win, doc) = state.engine.get_window(&win_id);
-        Ok(Some(json!({
-            "window": win,
-            "document": doc
-        })))
+        let (window, document) = state.engine.get_window(&win_id);
+        Ok(Some(serde_json::to_value(WindowInfo { window, document })?))
     }
     pub fn close_window(state: &mut APIMethodParams) -> APIMethodResult {
         let win_id = utils::try_parse::<WindowIdParams>(&state.params)?.win_id;
-        if let Some(old_layout) = std::mem::take(&mut state.engine.layout) {
-            let new_layout = old_layout
-                .remove_window(&win_id)
-                .ok_or_else(|| format!("Window `{}` not found in layout", &win_id))?;
-
-            state.engine.layout = Some(new_layout);
-        }
-        state.engine.windows.remove(&win_id);
-
-        if state.engine.active_window == win_id {
-            state.engine.active_window = state
-                .engine
-                .windows
-                .keys()
-                .next()
-                .cloned()
-                .ok_or_else(|| "No windows left after closing window".to_string())?;
-        }
-
-        state.engine.emit(&EngineEvent::WindowClose(win_id.clone()));
+        state.engine.close_window(&win_id)?;
         Ok(None)
     }
     pub fn move_window(state: &mut APIMethodParams) -> APIMethodResult {
@@ -176,44 +166,370 @@ impl EngineAPI {
             if let Some(id) = neighbor {
                 state.engine.active_window = id.clone();
                 state.engine.emit(&EngineEvent::LayoutChange);
-                return Ok(Some(json!({
-                    "win_id":id
-                })));
+                return Ok(Some(serde_json::to_value(WindowIdParams { win_id: id })?));
             };
             return Ok(None);
         }
-        Err("No Valid Layout".to_string())
+        Err("No Valid Layout".into())
+    }
+    /// Grows or shrinks a window by moving its edge on one side.
+    pub fn resize_window(state: &mut APIMethodParams) -> APIMethodResult {
+        let params = try_parse::<WindowResizeParams>(&state.params)?;
+        let win_id = params.win_id.unwrap_or(state.engine.active_window.clone());
+        let screen = state.engine.screen_rect();
+        state.engine.unzoom();
+        let layout = state.engine.layout.as_mut().ok_or("No Valid Layout")?;
+        let cells = params.cells.unwrap_or(1) as isize;
+        if layout.resize(&screen, &win_id, &params.dir, cells) {
+            state.engine.emit(&EngineEvent::LayoutChange);
+        }
+        Ok(None)
+    }
+    pub fn equalize_windows(state: &mut APIMethodParams) -> APIMethodResult {
+        state.engine.unzoom();
+        let layout = state.engine.layout.as_mut().ok_or("No Valid Layout")?;
+        layout.equalize();
+        state.engine.emit(&EngineEvent::LayoutChange);
+        Ok(None)
+    }
+    /// Exchanges a window with its neighbour in a direction, returning the neighbour.
+    pub fn swap_window(state: &mut APIMethodParams) -> APIMethodResult {
+        let params = try_parse::<WindowSwapParams>(&state.params)?;
+        let win_id = params.win_id.unwrap_or(state.engine.active_window.clone());
+        state.engine.unzoom();
+        let layout = state.engine.layout.as_mut().ok_or("No Valid Layout")?;
+        let Some(neighbor) = layout.get_neighbor(win_id.clone(), params.dir) else {
+            return Ok(None);
+        };
+        layout.swap(&win_id, &neighbor);
+        state.engine.emit(&EngineEvent::LayoutChange);
+        Ok(Some(serde_json::to_value(WindowIdParams {
+            win_id: neighbor,
+        })?))
+    }
+    pub fn rotate_windows(state: &mut APIMethodParams) -> APIMethodResult {
+        let params = utils::try_parse_or_default::<WindowRotateParams>(&state.params)?;
+        let win_id = params.win_id.unwrap_or(state.engine.active_window.clone());
+        state.engine.unzoom();
+        let layout = state.engine.layout.as_mut().ok_or("No Valid Layout")?;
+        layout.rotate(&win_id, params.reverse);
+        state.engine.emit(&EngineEvent::LayoutChange);
+        Ok(None)
+    }
+    pub fn zoom_window(state: &mut APIMethodParams) -> APIMethodResult {
+        let params = utils::try_parse_or_default::<WindowZoomParams>(&state.params)?;
+        let win_id = params.win_id.unwrap_or(state.engine.active_window.clone());
+        state.engine.toggle_zoom(&win_id)?;
+        Ok(None)
+    }
+    pub fn get_cursor(state: &mut APIMethodParams) -> APIMethodResult {
+        let win_id = utils::try_parse::<WindowIdParams>(&state.params)?.win_id;
+        let (win, doc) = state.engine.try_get_window(&win_id)?;
+        let (row, col) = match &doc.data {
+            DocumentData::SpreadSheet(data) => data.selected_cell,
+            _ => (win.cursor_row, win.cursor_col),
+        };
+        Ok(Some(serde_json::to_value(CursorResult { row, col })?))
+    }
+    pub fn set_cursor(state: &mut APIMethodParams) -> APIMethodResult {
+        let params = utils::try_parse::<WindowCursorParams>(&state.params)?;
+        let (win, doc) = state.engine.try_get_window(&params.win_id)?;
+        doc.data
+            .validate_position(params.row, params.col)
+            .map_err(ApiError::invalid_params)?;
+        win.cursor_row = params.row;
+        win.cursor_col = params.col;
+        let doc_id = doc.id.clone();
+        let mut selection_changed = false;
+        if let DocumentData::SpreadSheet(data) = &mut doc.data {
+            selection_changed = data.selected_cell != (params.row, params.col);
+            data.selected_cell = (params.row, params.col);
+        }
+        state.engine.emit(&EngineEvent::CursorMoved(
+            params.win_id.clone(),
+            params.row,
+            params.col,
+        ));
+        if selection_changed {
+            state.engine.emit(&EngineEvent::SelectionChanged(doc_id));
+        }
+        Ok(None)
+    }
+    pub fn get_view(state: &mut APIMethodParams) -> APIMethodResult {
+        let win_id = utils::try_parse::<WindowIdParams>(&state.params)?.win_id;
+        let (win, _) = state.engine.try_get_window(&win_id)?;
+        Ok(Some(serde_json::to_value(ViewResult {
+            scroll_rows: win.scroll_rows,
+            scroll_cols: win.scroll_cols,
+        })?))
+    }
+    pub fn set_view(state: &mut APIMethodParams) -> APIMethodResult {
+        let params = utils::try_parse::<WindowViewParams>(&state.params)?;
+        let (win, doc) = state.engine.try_get_window(&params.win_id)?;
+        let (rows, cols) = doc.data.extent();
+        if params.scroll_rows >= rows.max(1) || params.scroll_cols >= cols.max(1) {
+            return Err(ApiError::invalid_params(format!(
+                "View ({}, {}) is outside the document ({} rows, {} columns)",
+                params.scroll_rows, params.scroll_cols, rows, cols
+            )));
+        }
+        win.scroll_rows = params.scroll_rows;
+        win.scroll_cols = params.scroll_cols;
+        Ok(None)
+    }
+    pub fn get_option(state: &mut APIMethodParams) -> APIMethodResult {
+        let params = utils::try_parse::<WindowOptionParams>(&state.params)?;
+        let (win, _) = state.engine.try_get_window(&params.win_id)?;
+        let value = match params.option.as_str() {
+            "border_style" => json!(win.border_style),
+            other => {
+                return Err(ApiError::not_found(format!(
+                    "Unknown window option `{}`",
+                    other
+                )));
+            }
+        };
+        Ok(Some(value))
+    }
+    pub fn set_option(state: &mut APIMethodParams) -> APIMethodResult {
+        let params = utils::try_parse::<WindowOptionParams>(&state.params)?;
+        let (win, _) = state.engine.try_get_window(&params.win_id)?;
+        let value = params.value.unwrap_or_default();
+        match params.option.as_str() {
+            "border_style" => {
+                win.border_style = serde_path_to_error::deserialize(value)?;
+            }
+            other => {
+                return Err(ApiError::not_found(format!(
+                    "Unknown window option `{}`",
+                    other
+                )));
+            }
+        }
+        Ok(None)
+    }
+    pub fn get_dimensions(state: &mut APIMethodParams) -> APIMethodResult {
+        let win_id = utils::try_parse::<WindowIdParams>(&state.params)?.win_id;
+        let rect = state.engine.get_window_rect(&win_id)?;
+        Ok(Some(serde_json::to_value(DimensionsResult {
+            row: rect.y,
+            col: rect.x,
+            width: rect.width,
+            height: rect.height,
+        })?))
     }
     pub fn kill(state: &mut APIMethodParams) -> APIMethodResult {
-        println!("quit?");
-        state.engine.should_quit = true;
+        state.engine.request_quit();
         Ok(None)
     }
 }
 
 impl APIRegister for EngineAPI {
     fn register_methods(api: &mut super::API) {
-        let mut methods: HashMap<&str, APIMethod> = HashMap::new();
-        methods.insert("window.create", Self::create_window);
-        methods.insert("window.get_current", Self::get_current_window);
-        methods.insert("window.get_window", Self::get_window);
-        methods.insert("window.close", Self::close_window);
-        methods.insert("window.move", Self::move_window);
-        methods.insert("kill", Self::kill);
-        api.register_api(methods);
+        api.register_api(vec![
+            APIMethodSpec::new(
+                "window.create",
+                "Opens a split or floating window on a document.",
+                Self::create_window,
+            )
+            .params::<CreateWindowParams>(),
+            APIMethodSpec::new(
+                "window.get_current",
+                "Returns the focused window and its document.",
+                Self::get_current_window,
+            )
+            .returns::<WindowInfo>(),
+            APIMethodSpec::new(
+                "window.get_window",
+                "Returns a window and its document.",
+                Self::get_window,
+            )
+            .params::<WindowIdParams>()
+            .returns::<WindowInfo>(),
+            APIMethodSpec::new("window.close", "Closes a window.", Self::close_window)
+                .params::<WindowIdParams>(),
+            APIMethodSpec::new(
+                "window.move",
+                "Focuses the neighbouring window in a direction, returning it if there is one.",
+                Self::move_window,
+            )
+            .params::<WindowMoveParams>()
+            .returns::<Option<WindowIdParams>>(),
+            APIMethodSpec::new(
+                "window.resize",
+                "Moves a window's edge on one side by some rows or columns, out or in.",
+                Self::resize_window,
+            )
+            .params::<WindowResizeParams>(),
+            APIMethodSpec::new(
+                "window.equalize",
+                "Gives the windows in each row and column of the layout equal space.",
+                Self::equalize_windows,
+            ),
+            APIMethodSpec::new(
+                "window.swap",
+                "Exchanges a window with its neighbour in a direction, returning it if any.",
+                Self::swap_window,
+            )
+            .params::<WindowSwapParams>()
+            .returns::<Option<WindowIdParams>>(),
+            APIMethodSpec::new(
+                "window.rotate",
+                "Moves each window in a window's row or column one place along.",
+                Self::rotate_windows,
+            )
+            .optional_params::<WindowRotateParams>(),
+            APIMethodSpec::new(
+                "window.zoom",
+                "Zooms a window to fill the screen, or restores the layout if one is zoomed.",
+                Self::zoom_window,
+            )
+            .optional_params::<WindowZoomParams>(),
+            APIMethodSpec::new(
+                "window.get_cursor",
+                "Returns the cursor, or the selected cell of a spreadsheet.",
+                Self::get_cursor,
+            )
+            .params::<WindowIdParams>()
+            .returns::<CursorResult>(),
+            APIMethodSpec::new(
+                "window.set_cursor",
+                "Moves the cursor, or selects a cell of a spreadsheet.",
+                Self::set_cursor,
+            )
+            .params::<WindowCursorParams>(),
+            APIMethodSpec::new(
+                "window.get_view",
+                "Returns the first row and column shown in a window.",
+                Self::get_view,
+            )
+            .params::<WindowIdParams>()
+            .returns::<ViewResult>(),
+            APIMethodSpec::new(
+                "window.set_view",
+                "Scrolls a window so the given row and column come first.",
+                Self::set_view,
+            )
+            .params::<WindowViewParams>(),
+            APIMethodSpec::new(
+                "window.get_option",
+                "Reads a window option such as `border_style`.",
+                Self::get_option,
+            )
+            .params::<WindowOptionParams>()
+            .returns::<Value>(),
+            APIMethodSpec::new(
+                "window.set_option",
+                "Sets a window option such as `border_style`.",
+                Self::set_option,
+            )
+            .params::<WindowOptionParams>(),
+            APIMethodSpec::new(
+                "window.get_dimensions",
+                "Returns where a window is drawn on screen.",
+                Self::get_dimensions,
+            )
+            .params::<WindowIdParams>()
+            .returns::<DimensionsResult>(),
+            APIMethodSpec::new(
+                "kill",
+                "Asks to quit; `before_quit` handlers may refuse.",
+                Self::kill,
+            ),
+        ]);
     }
 }
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct WindowMoveParams {
     dir: SplitDirection,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
+struct WindowResizeParams {
+    /// The window, by default the focused one.
+    win_id: Option<String>,
+    /// The side whose edge moves.
+    dir: SplitDirection,
+    /// How far the edge moves out, 1 by default; negative moves it in.
+    cells: Option<i32>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct WindowSwapParams {
+    /// The window, by default the focused one.
+    win_id: Option<String>,
+    dir: SplitDirection,
+}
+
+#[derive(Deserialize, Default, JsonSchema)]
+#[serde(default)]
+struct WindowRotateParams {
+    /// A window in the row or column, by default the focused one.
+    win_id: Option<String>,
+    /// Move the windows up or left instead.
+    reverse: bool,
+}
+
+#[derive(Deserialize, Default, JsonSchema)]
+struct WindowZoomParams {
+    /// The window to zoom, by default the focused one.
+    win_id: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
 struct WindowIdParams {
     win_id: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, JsonSchema)]
+struct WindowInfo<'a> {
+    window: &'a WindowState,
+    document: &'a Document,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct CursorResult {
+    row: usize,
+    col: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct ViewResult {
+    scroll_rows: usize,
+    scroll_cols: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct DimensionsResult {
+    row: usize,
+    col: usize,
+    width: usize,
+    height: usize,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct WindowCursorParams {
+    win_id: String,
+    row: usize,
+    col: usize,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct WindowViewParams {
+    win_id: String,
+    scroll_rows: usize,
+    scroll_cols: usize,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct WindowOptionParams {
+    win_id: String,
+    option: String,
+    #[serde(default)]
+    value: Option<Value>,
+}
+
+#[derive(Deserialize, JsonSchema)]
 enum CreateWindowParams {
     Split {
         doc: String,
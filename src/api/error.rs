@@ -0,0 +1,215 @@
+use std::fmt;
+
+use pyo3::{
+    Bound, PyErr, Python, create_exception,
+    exceptions::PyRuntimeError,
+    types::{PyAnyMethods, PyType},
+};
+use serde::Serialize;
+
+use crate::engine::document::DocType;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum ApiError {
+    UnknownMethod {
+        method: String,
+    },
+    InvalidParams {
+        /// Where in the params deserialisation failed, e.g. `command.args[0]`.
+        path: String,
+        message: String,
+    },
+    NotFound {
+        message: String,
+    },
+    WrongDocumentType {
+        expected: DocType,
+        found: DocType,
+    },
+    Python {
+        message: String,
+    },
+    Io {
+        message: String,
+    },
+    Failed {
+        message: String,
+    },
+}
+
+impl ApiError {
+    pub fn invalid_params(message: impl Into<String>) -> Self {
+        Self::InvalidParams {
+            path: String::new(),
+            message: message.into(),
+        }
+    }
+    pub fn invalid_field(path: &str, message: impl fmt::Display) -> Self {
+        Self::InvalidParams {
+            path: path.to_string(),
+            message: message.to_string(),
+        }
+    }
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::NotFound {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownMethod { method } => write!(f, "Unknown API method `{}`", method),
+            Self::InvalidParams { path, message } if path.is_empty() || path == "." => {
+                write!(f, "Invalid params: {}", message)
+            }
+            Self::InvalidParams { path, message } => {
+                write!(f, "Invalid params at `{}`: {}", path, message)
+            }
+            Self::WrongDocumentType { expected, found } => {
+                write!(f, "Expected a {:?} document, found {:?}", expected, found)
+            }
+            Self::NotFound { message }
+            | Self::Python { message }
+            | Self::Io { message }
+            | Self::Failed { message } => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl From<String> for ApiError {
+    fn from(message: String) -> Self {
+        Self::Failed { message }
+    }
+}
+
+impl From<&str> for ApiError {
+    fn from(message: &str) -> Self {
+        Self::Failed {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<std::io::Error> for ApiError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io {
+            message: err.to_string(),
+        }
+    }
+}
+
+impl From<PyErr> for ApiError {
+    fn from(err: PyErr) -> Self {
+        Self::Python {
+            message: err.to_string(),
+        }
+    }
+}
+
+impl From<serde_json::Error> for ApiError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::invalid_params(err.to_string())
+    }
+}
+
+impl<E: fmt::Display> From<serde_path_to_error::Error<E>> for ApiError {
+    fn from(err: serde_path_to_error::Error<E>) -> Self {
+        Self::InvalidParams {
+            path: err.path().to_string(),
+            message: err.inner().to_string(),
+        }
+    }
+}
+
+create_exception!(
+    neocel,
+    NeocelError,
+    PyRuntimeError,
+    "Base class of every error raised by `api.call`."
+);
+create_exception!(
+    neocel,
+    UnknownMethodError,
+    NeocelError,
+    "No API method has the given name."
+);
+create_exception!(
+    neocel,
+    InvalidParamsError,
+    NeocelError,
+    "The params do not match what the method expects."
+);
+create_exception!(
+    neocel,
+    NotFoundError,
+    NeocelError,
+    "A window, document, command or subscription does not exist."
+);
+create_exception!(
+    neocel,
+    WrongDocumentTypeError,
+    NeocelError,
+    "The method does not support the document's type."
+);
+create_exception!(
+    neocel,
+    PluginError,
+    NeocelError,
+    "A Python command or event handler raised."
+);
+create_exception!(
+    neocel,
+    NeocelIOError,
+    NeocelError,
+    "Reading or writing a file failed."
+);
+
+/// The exception classes of the `neocel` module, base class first.
+pub fn python_exceptions(py: Python<'_>) -> [(&'static str, Bound<'_, PyType>); 7] {
+    [
+        ("NeocelError", py.get_type::<NeocelError>()),
+        ("UnknownMethodError", py.get_type::<UnknownMethodError>()),
+        ("InvalidParamsError", py.get_type::<InvalidParamsError>()),
+        ("NotFoundError", py.get_type::<NotFoundError>()),
+        (
+            "WrongDocumentTypeError",
+            py.get_type::<WrongDocumentTypeError>(),
+        ),
+        ("PluginError", py.get_type::<PluginError>()),
+        ("NeocelIOError", py.get_type::<NeocelIOError>()),
+    ]
+}
+
+impl From<ApiError> for PyErr {
+    fn from(err: ApiError) -> Self {
+        let message = err.to_string();
+        match err {
+            ApiError::UnknownMethod { .. } => UnknownMethodError::new_err(message),
+            ApiError::InvalidParams { .. } => InvalidParamsError::new_err(message),
+            ApiError::NotFound { .. } => NotFoundError::new_err(message),
+            ApiError::WrongDocumentType { .. } => WrongDocumentTypeError::new_err(message),
+            ApiError::Python { .. } => PluginError::new_err(message),
+            ApiError::Io { .. } => NeocelIOError::new_err(message),
+            ApiError::Failed { .. } => NeocelError::new_err(message),
+        }
+    }
+}
+
+/// Makes the exception classes importable from Python as `neocel.NotFoundError` etc.
+pub fn register_python_module(py: Python) -> Result<(), ApiError> {
+    let modules = py.import("sys")?.getattr("modules")?;
+    if modules.contains("neocel")? {
+        return Ok(());
+    }
+    let module = pyo3::types::PyModule::new(py, "neocel")?;
+    for (name, class) in python_exceptions(py) {
+        module.setattr(name, class)?;
+    }
+    modules.set_item("neocel", module)?;
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use pyo3::{PyErr, Python, types::PyAnyMethods};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        APIMethodParams, APIMethodResult, APIMethodSpec, APIRegister, ApiError,
+        ExternalCommandInput, PyCallable, utils::try_parse,
+    },
+    commands::command_dispatcher::{CommandFunction, SubscriptionId},
+    engine::EngineEventKind,
+};
+
+pub struct EventAPI {}
+
+impl EventAPI {
+    pub fn subscribe(state: &mut APIMethodParams) -> APIMethodResult {
+        let params = parse_subscribe_params(&state.params)?;
+        let id = state
+            .command_dispatch
+            .subscribe(params.event, params.function);
+        Ok(Some(serde_json::to_value(SubscriptionParams {
+            subscription_id: id,
+        })?))
+    }
+
+    pub fn unsubscribe(state: &mut APIMethodParams) -> APIMethodResult {
+        let id = try_parse::<SubscriptionParams>(&state.params)?.subscription_id;
+        if !state.command_dispatch.unsubscribe(&id) {
+            return Err(ApiError::not_found(format!(
+                "Subscription `{}` not found",
+                id
+            )));
+        }
+        Ok(None)
+    }
+}
+
+impl APIRegister for EventAPI {
+    fn register_methods(api: &mut super::API) {
+        api.register_api(vec![
+            APIMethodSpec::new(
+                "events.subscribe",
+                "Calls `callback(api, event)` whenever an event of the given kind is emitted.",
+                Self::subscribe,
+            )
+            .params::<SubscribeParams>()
+            .returns::<SubscriptionParams>(),
+            APIMethodSpec::new(
+                "events.unsubscribe",
+                "Removes a subscription.",
+                Self::unsubscribe,
+            )
+            .params::<SubscriptionParams>(),
+        ]);
+    }
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+struct SubscriptionParams {
+    subscription_id: SubscriptionId,
+}
+
+#[derive(JsonSchema)]
+struct SubscribeParams {
+    event: EngineEventKind,
+    #[schemars(rename = "callback", with = "PyCallable")]
+    function: CommandFunction,
+}
+fn parse_subscribe_params(
+    input: &Option<ExternalCommandInput>,
+) -> Result<SubscribeParams, ApiError> {
+    match input {
+        Some(ExternalCommandInput::Python(obj)) => Python::attach(|py| {
+            let res = obj.bind(py);
+
+            let event: String = res
+                .get_item("event")
+                .map_err(|e| ApiError::invalid_field("event", e))?
+                .extract()
+                .map_err(|e: PyErr| ApiError::invalid_field("event", e))?;
+            let event: EngineEventKind = serde_json::from_value(serde_json::Value::String(event))
+                .map_err(|e| ApiError::invalid_field("event", e))?;
+
+            let callback = res
+                .get_item("callback")
+                .map_err(|e| ApiError::invalid_field("callback", e))?;
+            if !callback.is_callable() {
+                return Err(ApiError::invalid_field("callback", "must be callable"));
+            }
+            Ok(SubscribeParams {
+                event,
+                function: CommandFunction::Python(callback.unbind()),
+            })
+        }),
+        Some(ExternalCommandInput::JSON(_)) => Err(ApiError::invalid_params(
+            "JSON input not supported for SubscribeParams with callback",
+        )),
+        None => Err(ApiError::invalid_params("missing input parameters")),
+    }
+}
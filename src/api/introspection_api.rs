@@ -0,0 +1,24 @@
+use crate::api::{
+    API, APIDescription, APIMethodParams, APIMethodResult, APIMethodSpec, APIRegister,
+};
+
+pub struct IntrospectionAPI {}
+
+impl IntrospectionAPI {
+    pub fn list(_state: &mut APIMethodParams) -> APIMethodResult {
+        Ok(Some(serde_json::to_value(API::new().describe())?))
+    }
+}
+
+impl APIRegister for IntrospectionAPI {
+    fn register_methods(api: &mut super::API) {
+        api.register_api(vec![
+            APIMethodSpec::new(
+                "api.list",
+                "Lists every API method with JSON schemas for its params and result.",
+                Self::list,
+            )
+            .returns::<APIDescription>(),
+        ]);
+    }
+}
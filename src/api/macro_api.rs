@@ -0,0 +1,113 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::{
+    api::{
+        APIMethodParams, APIMethodResult, APIMethodSpec, APIRegister, ApiError, utils::try_parse,
+    },
+    engine::registers::{Register, RegisterKind, Registers},
+    input::macros::{Recording, keys_to_text, text_to_keys},
+};
+
+pub struct MacroAPI {}
+
+impl MacroAPI {
+    /// Starts recording the keys typed from now on, for `macro.stop` to store.
+    pub fn record(state: &mut APIMethodParams) -> APIMethodResult {
+        let params = try_parse::<RecordParams>(&state.params)?;
+        if let Some(recording) = &state.input_engine.recording {
+            return Err(ApiError::Failed {
+                message: format!("already recording into `{}`", recording.register),
+            });
+        }
+        Registers::check_writable(Some(params.register))?;
+        state.input_engine.recording = Some(Recording {
+            register: params.register,
+            keys: vec![],
+        });
+        Ok(None)
+    }
+
+    /// Stores the keys recorded since `macro.record` in its register as text.
+    pub fn stop(state: &mut APIMethodParams) -> APIMethodResult {
+        let recording = state
+            .input_engine
+            .recording
+            .take()
+            .ok_or_else(|| ApiError::Failed {
+                message: "not recording a macro".to_string(),
+            })?;
+        let text = keys_to_text(&recording.keys);
+        if recording.register == '+' {
+            state.ui.set_clipboard(&text)?;
+        }
+        let register = Register::new(vec![text], RegisterKind::Chars);
+        state
+            .engine
+            .registers
+            .record(recording.register, register)?;
+        Ok(None)
+    }
+
+    /// Feeds the keys in a register as if typed, `count` times.
+    pub fn play(state: &mut APIMethodParams) -> APIMethodResult {
+        let params = try_parse::<PlayParams>(&state.params)?;
+        let name = match params.register {
+            '@' => state.input_engine.last_macro.ok_or_else(|| {
+                ApiError::invalid_field("register", "no macro has been played yet")
+            })?,
+            name => name,
+        };
+        let register = state.engine.registers.get(Some(name))?.ok_or_else(|| {
+            ApiError::invalid_field("register", format!("register `{}` is empty", name))
+        })?;
+        let keys = text_to_keys(&register.text.join("\n"))
+            .map_err(|e| ApiError::invalid_field("register", e))?;
+        // each play counts towards the limit, so a huge count stops there
+        for _ in 0..params.count.unwrap_or(1) {
+            state.input_engine.play_macro(keys.clone())?;
+        }
+        state.input_engine.last_macro = Some(name);
+        Ok(None)
+    }
+}
+
+impl APIRegister for MacroAPI {
+    fn register_methods(api: &mut super::API) {
+        api.register_api(vec![
+            APIMethodSpec::new(
+                "macro.record",
+                "Starts recording typed keys into a register.",
+                Self::record,
+            )
+            .params::<RecordParams>(),
+            APIMethodSpec::new(
+                "macro.stop",
+                "Stops recording and stores the keys in the register as text.",
+                Self::stop,
+            ),
+            APIMethodSpec::new(
+                "macro.play",
+                "Feeds the keys in a register as if typed.",
+                Self::play,
+            )
+            .params::<PlayParams>(),
+        ]);
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+struct RecordParams {
+    /// `a` to `z`, `A` to `Z` to append to the register, `"` or `+`.
+    register: char,
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+struct PlayParams {
+    /// Any register holding a macro's text, or `@` for the one played last.
+    register: char,
+    /// How many times to play it.
+    count: Option<u32>,
+}
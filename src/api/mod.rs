@@ -1,14 +1,27 @@
 pub mod command_api;
+pub mod command_line_api;
 pub mod config;
 pub mod document_api;
+pub mod editor_api;
 pub mod engine_api;
+pub mod error;
+pub mod event_api;
+pub mod introspection_api;
+pub mod macro_api;
+pub mod palette_api;
+pub mod rpc;
+pub mod stubs;
 pub mod text_document_api;
+pub mod ui_api;
 pub mod utils;
-use std::collections::HashMap;
+use std::{borrow::Cow, collections::HashMap};
 
 use pyo3::{Py, PyAny};
-use serde_json::Value;
+use schemars::{JsonSchema, Schema, SchemaGenerator, generate::SchemaSettings, json_schema};
+use serde::Serialize;
+use serde_json::{Map, Value};
 
+pub use crate::api::error::ApiError;
 use crate::{
     commands::command_dispatcher::CommandDispatcher, engine::Engine,
     input::input_engine::InputEngine, render::UI,
@@ -26,14 +39,85 @@ pub struct APIMethodParams<'a> {
     command_dispatch: &'a mut CommandDispatcher,
     params: Option<ExternalCommandInput>,
 }
-pub type APIMethodResult = Result<Option<Value>, String>;
+pub type APIMethodResult = Result<Option<Value>, ApiError>;
 pub type APIMethod = for<'a, 'b> fn(&'b mut APIMethodParams<'a>) -> APIMethodResult;
+type SchemaFn = fn(&mut SchemaGenerator) -> Schema;
+
+/// An API method together with what `api.list` and the generated stubs say about it.
+/// Schemas are only built when the API is described, not on every dispatch.
+pub struct APIMethodSpec {
+    name: &'static str,
+    description: &'static str,
+    func: APIMethod,
+    params: Option<SchemaFn>,
+    params_optional: bool,
+    returns: Option<SchemaFn>,
+}
+impl APIMethodSpec {
+    pub fn new(name: &'static str, description: &'static str, func: APIMethod) -> Self {
+        Self {
+            name,
+            description,
+            func,
+            params: None,
+            params_optional: false,
+            returns: None,
+        }
+    }
+    /// The params the method reads with `try_parse`.
+    pub fn params<T: JsonSchema>(mut self) -> Self {
+        self.params = Some(SchemaGenerator::subschema_for::<T>);
+        self
+    }
+    /// The params the method reads with `try_parse_or_default`, so they may be left out.
+    pub fn optional_params<T: JsonSchema>(mut self) -> Self {
+        self.params_optional = true;
+        self.params::<T>()
+    }
+    pub fn returns<T: JsonSchema>(mut self) -> Self {
+        self.returns = Some(SchemaGenerator::subschema_for::<T>);
+        self
+    }
+}
+
+/// Schema of a param that must be a Python callable, which serde cannot describe.
+pub struct PyCallable;
+impl JsonSchema for PyCallable {
+    fn inline_schema() -> bool {
+        true
+    }
+    fn schema_name() -> Cow<'static, str> {
+        "PyCallable".into()
+    }
+    fn json_schema(_generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({ "x-python-type": "callable" })
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct APIMethodInfo {
+    pub name: String,
+    pub description: String,
+    /// JSON schema of the params, absent if the method takes none.
+    pub params: Option<Value>,
+    pub params_optional: bool,
+    /// JSON schema of the result, absent if the method returns nothing.
+    pub returns: Option<Value>,
+}
+#[derive(Serialize, JsonSchema)]
+pub struct APIDescription {
+    pub methods: Vec<APIMethodInfo>,
+    /// Schemas referenced from `methods` as `#/$defs/<name>`.
+    #[serde(rename = "$defs")]
+    pub defs: Map<String, Value>,
+}
+
 pub struct API {
-    commands: HashMap<String, APIMethod>,
+    commands: HashMap<String, APIMethodSpec>,
 }
 
 pub type APICaller<'a> =
-    &'a mut dyn FnMut(String, Option<ExternalCommandInput>) -> Result<Option<Value>, String>;
+    &'a mut dyn FnMut(String, Option<ExternalCommandInput>) -> Result<Option<Value>, ApiError>;
 
 impl API {
     pub fn new() -> Self {
@@ -42,16 +126,41 @@ impl API {
         };
         engine_api::EngineAPI::register_methods(&mut s);
         command_api::CommandAPI::register_methods(&mut s);
+        command_line_api::CommandLineAPI::register_methods(&mut s);
         document_api::DocumentAPI::register_methods(&mut s);
         text_document_api::TextDocumentAPI::register_methods(&mut s);
+        event_api::EventAPI::register_methods(&mut s);
+        introspection_api::IntrospectionAPI::register_methods(&mut s);
+        palette_api::PaletteAPI::register_methods(&mut s);
+        ui_api::UIAPI::register_methods(&mut s);
+        editor_api::EditorAPI::register_methods(&mut s);
+        macro_api::MacroAPI::register_methods(&mut s);
         s
     }
-    pub fn register_api(&mut self, methods: HashMap<&str, APIMethod>) {
-        let t: HashMap<String, APIMethod> = methods
+    pub fn register_api(&mut self, methods: Vec<APIMethodSpec>) {
+        self.commands
+            .extend(methods.into_iter().map(|m| (m.name.to_string(), m)));
+    }
+
+    /// Describes every registered method, sorted by name.
+    pub fn describe(&self) -> APIDescription {
+        let mut generator = SchemaSettings::draft2020_12().into_generator();
+        let mut specs: Vec<&APIMethodSpec> = self.commands.values().collect();
+        specs.sort_by_key(|spec| spec.name);
+        let methods = specs
             .into_iter()
-            .map(|(k, v)| (k.to_string(), v))
+            .map(|spec| APIMethodInfo {
+                name: spec.name.to_string(),
+                description: spec.description.to_string(),
+                params: spec.params.map(|f| f(&mut generator).to_value()),
+                params_optional: spec.params_optional,
+                returns: spec.returns.map(|f| f(&mut generator).to_value()),
+            })
             .collect();
-        self.commands.extend(t);
+        APIDescription {
+            methods,
+            defs: generator.take_definitions(true),
+        }
     }
 
     pub fn run_command<F>(
@@ -61,14 +170,14 @@ impl API {
         ui: &mut UI,
         command_dispatch: &mut CommandDispatcher,
         mut callback: F,
-    ) -> Result<Option<Value>, String>
+    ) -> Result<Option<Value>, ApiError>
     where
-        F: FnMut(APICaller) -> Result<Option<Value>, String>,
+        F: FnMut(APICaller) -> Result<Option<Value>, ApiError>,
     {
         let mut callable = |command_name: String,
                             params: Option<ExternalCommandInput>|
-         -> Result<Option<Value>, String> {
-            if let Some(func) = self.commands.get(&command_name) {
+         -> Result<Option<Value>, ApiError> {
+            if let Some(spec) = self.commands.get(&command_name) {
                 let mut tuple_args = APIMethodParams {
                     engine,
                     input_engine,
@@ -76,10 +185,11 @@ impl API {
                     command_dispatch,
                     params,
                 };
-                func(&mut tuple_args)
+                (spec.func)(&mut tuple_args)
             } else {
-                println!("could not find command");
-                Ok(None)
+                Err(ApiError::UnknownMethod {
+                    method: command_name,
+                })
             }
         };
 
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use serde_json::json;
+
+use crate::{
+    api::{
+        APIMethodParams, APIMethodResult, APIMethodSpec, APIRegister, ApiError,
+        ExternalCommandInput, engine_api::EngineAPI,
+    },
+    commands::command_dispatcher::CommandRequest,
+    engine::{
+        document::{DocType, Document, DocumentData},
+        documents::palette::{PaletteDocumentData, PaletteEntry},
+    },
+    input::{Token, input_engine::ModeType, keymaps::KeymapProvider},
+};
+
+const PALETTE_WIDTH: usize = 72;
+const PALETTE_HEIGHT: usize = 16;
+
+pub struct PaletteAPI {}
+
+impl PaletteAPI {
+    /// Opens the palette over the current window, listing the commands available there.
+    pub fn open(state: &mut APIMethodParams) -> APIMethodResult {
+        let origin = state.engine.active_window.clone();
+        let (win, doc) = state.engine.try_get_window(&origin)?;
+        if doc.doc_type == DocType::Palette {
+            return Ok(None);
+        }
+        let doc_type = doc.doc_type.clone();
+
+        let mut keys: HashMap<String, Vec<String>> = HashMap::new();
+        let keymaps = [
+            doc.keymap().clone(),
+            win.keymap().clone(),
+            state.input_engine.mode.keymap().clone(),
+            state.engine.keymap().clone(),
+        ];
+        for keymap in &keymaps {
+            for (sequence, token) in keymap.bindings() {
+                if let Token::Command(request) = token {
+                    let sequence: Vec<String> = sequence.iter().map(|k| k.to_string()).collect();
+                    let bound = keys.entry(request.id.clone()).or_default();
+                    let sequence = sequence.join(" ");
+                    if !bound.contains(&sequence) {
+                        bound.push(sequence);
+                    }
+                }
+            }
+        }
+
+        let commands = state.command_dispatch.list();
+        // a command registered for this document type shadows the global one
+        let shadowed = |id: &str| {
+            commands
+                .iter()
+                .any(|cmd| cmd.id == id && cmd.doc_type.as_ref() == Some(&doc_type))
+        };
+        let entries = commands
+            .iter()
+            .filter(|cmd| match &cmd.doc_type {
+                Some(t) => t == &doc_type,
+                None => !shadowed(&cmd.id),
+            })
+            .filter(|cmd| cmd.id != "palette.open")
+            .map(|cmd| PaletteEntry {
+                id: cmd.id.clone(),
+                title: cmd.meta.title.clone(),
+                description: cmd.meta.description.clone(),
+                keys: keys.remove(&cmd.id).unwrap_or_default(),
+            })
+            .collect();
+
+        let mode = state.input_engine.mode.mode.clone();
+        let (doc_id, doc) = Document::new(
+            DocumentData::Palette(PaletteDocumentData::new(entries, origin, mode)),
+            None,
+        );
+        state.engine.docs.insert(doc_id.clone(), doc);
+
+        let screen = state.engine.screen_rect();
+        let (width, height) = (screen.width, screen.height);
+        state.params = Some(ExternalCommandInput::JSON(json!({
+            "Floating": {
+                "doc": doc_id,
+                "enter": true,
+                "position": "center",
+                "relative": "editor",
+                "width": PALETTE_WIDTH.min(width),
+                "height": PALETTE_HEIGHT.min(height),
+                "border": "rounded",
+            }
+        })));
+        EngineAPI::create_window(state)?;
+        state.input_engine.set_mode(ModeType::Input, state.engine);
+        Ok(None)
+    }
+
+    /// Closes the palette and runs the highlighted command in the window it was opened from.
+    pub fn accept(state: &mut APIMethodParams) -> APIMethodResult {
+        let id = match &palette(state)?.selected_entry() {
+            Some(entry) => entry.id.clone(),
+            None => return Err(ApiError::not_found("No command matches the query")),
+        };
+        Self::close(state)?;
+        state.command_dispatch.dispatch(
+            &CommandRequest { id, args: vec![] },
+            state.engine,
+            state.input_engine,
+            state.ui,
+        )
+    }
+
+    /// Closes the palette, returning to the window and mode it was opened from.
+    pub fn close(state: &mut APIMethodParams) -> APIMethodResult {
+        let palette = palette(state)?;
+        let (origin, mode) = (palette.origin.clone(), palette.origin_mode.clone());
+        let doc_id = state.engine.get_current_window().1.id.clone();
+        state.engine.close_document(&doc_id)?;
+        if state.engine.windows.contains_key(&origin) {
+            state.engine.active_window = origin;
+        }
+        state.input_engine.set_mode(mode, state.engine);
+        Ok(None)
+    }
+}
+
+/// The palette shown in the current window.
+fn palette<'a>(state: &'a mut APIMethodParams) -> Result<&'a PaletteDocumentData, ApiError> {
+    let win_id = state.engine.active_window.clone();
+    let (_, doc) = state.engine.try_get_window(&win_id)?;
+    match &doc.data {
+        DocumentData::Palette(palette) => Ok(palette),
+        _ => Err(ApiError::WrongDocumentType {
+            expected: DocType::Palette,
+            found: doc.doc_type.clone(),
+        }),
+    }
+}
+
+impl APIRegister for PaletteAPI {
+    fn register_methods(api: &mut super::API) {
+        api.register_api(vec![
+            APIMethodSpec::new(
+                "palette.open",
+                "Opens the command palette over the current window.",
+                Self::open,
+            ),
+            APIMethodSpec::new(
+                "palette.accept",
+                "Closes the palette and runs the highlighted command.",
+                Self::accept,
+            ),
+            APIMethodSpec::new(
+                "palette.close",
+                "Closes the palette without running anything.",
+                Self::close,
+            ),
+        ]);
+    }
+}
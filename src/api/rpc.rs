@@ -0,0 +1,327 @@
+use std::{
+    collections::HashMap,
+    io::{ErrorKind, Read, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::{
+    api::{ApiError, ExternalCommandInput},
+    commands::command_dispatcher::{CommandDispatcher, SubscriptionId},
+    engine::{Engine, EngineEvent, EngineEventKind},
+    input::input_engine::InputEngine,
+    render::UI,
+};
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+/// How many bytes of responses and events may wait for a client that is not reading
+/// before it is dropped.
+const MAX_OUTGOING: usize = 8 * 1024 * 1024;
+
+/// Newline-delimited JSON-RPC 2.0 server on a unix socket. Requests are dispatched
+/// through the same method table the Python API uses; `events.subscribe` and
+/// `events.unsubscribe` are handled per connection and push `event` notifications.
+pub struct RpcServer {
+    path: PathBuf,
+    listener: UnixListener,
+    clients: Vec<RpcClient>,
+}
+
+struct RpcClient {
+    stream: UnixStream,
+    buffer: Vec<u8>,
+    /// Bytes sent but not yet taken by the socket.
+    outgoing: Vec<u8>,
+    subscriptions: HashMap<SubscriptionId, EngineEventKind>,
+    closed: bool,
+}
+
+#[derive(Deserialize)]
+struct RpcRequest {
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct SubscribeParams {
+    event: EngineEventKind,
+}
+
+#[derive(Deserialize)]
+struct UnsubscribeParams {
+    subscription_id: SubscriptionId,
+}
+
+impl RpcServer {
+    pub fn bind(path: &Path) -> Result<Self, String> {
+        if path.exists() {
+            // a socket left behind by an instance that did not shut down cleanly
+            if UnixStream::connect(path).is_ok() {
+                return Err(format!("{} is already in use", path.display()));
+            }
+            std::fs::remove_file(path).map_err(|e| e.to_string())?;
+        }
+        let listener = UnixListener::bind(path).map_err(|e| e.to_string())?;
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            listener,
+            clients: vec![],
+        })
+    }
+
+    /// Accepts new connections and answers every complete request line received so far.
+    /// Returns whether any request was handled.
+    pub fn poll(
+        &mut self,
+        engine: &mut Engine,
+        input_engine: &mut InputEngine,
+        ui: &mut UI,
+        command_dispatch: &mut CommandDispatcher,
+    ) -> bool {
+        self.accept();
+        let mut handled = false;
+        for client in self.clients.iter_mut() {
+            client.flush();
+            for line in client.read_lines() {
+                handled = true;
+                if let Some(response) =
+                    client.handle_line(&line, engine, input_engine, ui, command_dispatch)
+                {
+                    client.send(&response);
+                }
+            }
+        }
+        self.clients.retain(|c| !c.closed);
+        handled
+    }
+
+    /// Pushes delivered engine events to the clients subscribed to them.
+    pub fn notify(&mut self, events: &[EngineEvent]) {
+        for event in events {
+            let kind = event.kind();
+            for client in self.clients.iter_mut() {
+                let subs: Vec<SubscriptionId> = client
+                    .subscriptions
+                    .iter()
+                    .filter(|(_, k)| **k == kind)
+                    .map(|(id, _)| id.clone())
+                    .collect();
+                for sub_id in subs {
+                    client.send(&json!({
+                        "jsonrpc": "2.0",
+                        "method": "event",
+                        "params": {
+                            "subscription_id": sub_id,
+                            "event": event,
+                        }
+                    }));
+                }
+            }
+        }
+        self.clients.retain(|c| !c.closed);
+    }
+
+    fn accept(&mut self) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    if let Err(err) = stream.set_nonblocking(true) {
+                        log::warn!("could not set up rpc client: {:?}", err);
+                        continue;
+                    }
+                    self.clients.push(RpcClient {
+                        stream,
+                        buffer: vec![],
+                        outgoing: vec![],
+                        subscriptions: HashMap::new(),
+                        closed: false,
+                    });
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    log::warn!("rpc accept failed: {:?}", err);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl Drop for RpcServer {
+    fn drop(&mut self) {
+        _ = std::fs::remove_file(&self.path);
+    }
+}
+
+impl RpcClient {
+    fn read_lines(&mut self) -> Vec<String> {
+        let mut chunk = [0u8; 4096];
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => {
+                    self.closed = true;
+                    break;
+                }
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    log::warn!("rpc read failed: {:?}", err);
+                    self.closed = true;
+                    break;
+                }
+            }
+        }
+
+        let mut lines = vec![];
+        while let Some(pos) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line).trim().to_string();
+            if !line.is_empty() {
+                lines.push(line);
+            }
+        }
+        lines
+    }
+
+    /// Queues a message and writes as much of the queue as the socket takes now; the
+    /// rest goes out on later polls.
+    fn send(&mut self, message: &Value) {
+        if self.closed {
+            return;
+        }
+        self.outgoing
+            .extend_from_slice(message.to_string().as_bytes());
+        self.outgoing.push(b'\n');
+        if self.outgoing.len() > MAX_OUTGOING {
+            log::warn!("rpc client is not reading its messages, dropping it");
+            self.closed = true;
+            return;
+        }
+        self.flush();
+    }
+
+    fn flush(&mut self) {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => {
+                    self.closed = true;
+                    break;
+                }
+                Ok(n) => {
+                    self.outgoing.drain(..n);
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    log::warn!("rpc write failed: {:?}", err);
+                    self.closed = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    fn handle_line(
+        &mut self,
+        line: &str,
+        engine: &mut Engine,
+        input_engine: &mut InputEngine,
+        ui: &mut UI,
+        command_dispatch: &mut CommandDispatcher,
+    ) -> Option<Value> {
+        let request = match serde_json::from_str::<RpcRequest>(line) {
+            Ok(request) => request,
+            Err(err) => {
+                return Some(error_response(
+                    Value::Null,
+                    PARSE_ERROR,
+                    err.to_string(),
+                    Value::Null,
+                ));
+            }
+        };
+        let id = request.id.clone();
+        let result = match request.method.as_str() {
+            "events.subscribe" => self.subscribe(request.params),
+            "events.unsubscribe" => self.unsubscribe(request.params),
+            method => command_dispatch.call_api(
+                method,
+                request.params.map(ExternalCommandInput::JSON),
+                engine,
+                input_engine,
+                ui,
+            ),
+        };
+
+        // requests without an id are notifications and get no response
+        let id = id?;
+        Some(match result {
+            Ok(value) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": value,
+            }),
+            Err(err) => error_response(
+                id,
+                error_code(&err),
+                err.to_string(),
+                serde_json::to_value(&err).unwrap_or_default(),
+            ),
+        })
+    }
+
+    fn subscribe(&mut self, params: Option<Value>) -> Result<Option<Value>, ApiError> {
+        let params: SubscribeParams = serde_path_to_error::deserialize(params.unwrap_or_default())?;
+        let id = Uuid::new_v4().to_string();
+        self.subscriptions.insert(id.clone(), params.event);
+        Ok(Some(json!({"subscription_id": id})))
+    }
+
+    fn unsubscribe(&mut self, params: Option<Value>) -> Result<Option<Value>, ApiError> {
+        let params: UnsubscribeParams =
+            serde_path_to_error::deserialize(params.unwrap_or_default())?;
+        self.subscriptions
+            .remove(&params.subscription_id)
+            .map(|_| None)
+            .ok_or_else(|| {
+                ApiError::not_found(format!(
+                    "Subscription `{}` not found",
+                    params.subscription_id
+                ))
+            })
+    }
+}
+
+fn error_code(err: &ApiError) -> i64 {
+    match err {
+        ApiError::UnknownMethod { .. } => METHOD_NOT_FOUND,
+        ApiError::InvalidParams { .. } => INVALID_PARAMS,
+        _ => SERVER_ERROR,
+    }
+}
+
+/// Builds an error response; `data` carries the structured `ApiError` so clients can
+/// match on its `kind` instead of the message.
+fn error_response(id: Value, code: i64, message: String, data: Value) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {
+            "code": code,
+            "message": message,
+            "data": data,
+        }
+    })
+}
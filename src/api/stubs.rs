@@ -0,0 +1,320 @@
+use std::collections::BTreeMap;
+
+use pyo3::{Python, types::PyAnyMethods};
+use serde_json::{Map, Value};
+
+use crate::api::{APIDescription, error::python_exceptions};
+
+const PY_KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
+/// Renders the API description as a Python stub file for the `api` global.
+pub fn generate(description: &APIDescription) -> String {
+    let mut stubs = Stubs::default();
+    for (name, schema) in &description.defs {
+        stubs.define(name, schema);
+    }
+
+    let mut api = String::from(
+        "class API:\n    \"\"\"The editor API, passed to commands and event handlers and bound to `api` in scripts\"\"\"\n",
+    );
+    for method in &description.methods {
+        let hint = pascal_case(&method.name);
+        let mut args = format!("self, id: Literal[{}]", py_str(&method.name));
+        if let Some(params) = &method.params {
+            let ty = stubs.py_type(params, &format!("{}Params", hint));
+            if method.params_optional {
+                args.push_str(&format!(", params: Optional[{}] = None", ty));
+            } else {
+                args.push_str(&format!(", params: {}", ty));
+            }
+        }
+        let returns = match &method.returns {
+            Some(returns) => stubs.py_type(returns, &format!("{}Result", hint)),
+            None => "None".to_string(),
+        };
+        api.push_str(&format!(
+            "\n    @overload\n    def call({}) -> {}:\n        \"\"\"{}\"\"\"\n",
+            args, returns, method.description
+        ));
+    }
+
+    let mut out = String::from(
+        "# Generated by `neocel --gen-stubs`, do not edit.\n\
+         \"\"\"Type stubs for the neocel plugin API.\"\"\"\n\n\
+         from typing import Any, Callable, Dict, List, Literal, NotRequired, Optional, Tuple, TypedDict, Union, overload\n\n",
+    );
+    out.push_str(&exceptions());
+    for rendered in stubs.types.values() {
+        out.push_str(rendered);
+        out.push('\n');
+    }
+    out.push_str(&api);
+    out.push_str("\napi: API\n");
+    out
+}
+
+/// The `neocel` module's exception classes, as registered with Python.
+fn exceptions() -> String {
+    Python::attach(|py| {
+        let mut out = String::new();
+        for (name, class) in python_exceptions(py) {
+            let base: String = class
+                .getattr("__base__")
+                .and_then(|base| base.getattr("__name__"))
+                .and_then(|base| base.extract())
+                .unwrap_or_else(|_| "Exception".to_string());
+            let doc: Option<String> = class.getattr("__doc__").and_then(|d| d.extract()).ok();
+            out.push_str(&format!("class {}({}):\n", name, base));
+            match doc {
+                Some(doc) => out.push_str(&format!("    \"\"\"{}\"\"\"\n\n", doc)),
+                None => out.push_str("    ...\n\n"),
+            }
+        }
+        out
+    })
+}
+
+#[derive(Default)]
+struct Stubs {
+    /// Rendered TypedDicts and aliases by name, so the output order is stable.
+    types: BTreeMap<String, String>,
+}
+
+impl Stubs {
+    /// Emits a named type for a `$defs` entry or an object schema nested in one.
+    fn define(&mut self, name: &str, schema: &Value) {
+        if self.types.contains_key(name) {
+            return;
+        }
+        // reserve the name first so recursive schemas terminate
+        self.types.insert(name.to_string(), String::new());
+        let rendered = match schema.get("properties").and_then(Value::as_object) {
+            Some(properties) => self.typed_dict(name, schema, properties),
+            None => format!("{} = {}\n", name, self.py_type(schema, name)),
+        };
+        self.types.insert(name.to_string(), rendered);
+    }
+
+    fn typed_dict(
+        &mut self,
+        name: &str,
+        schema: &Value,
+        properties: &Map<String, Value>,
+    ) -> String {
+        let required: Vec<&str> = schema
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|keys| keys.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        let mut fields = vec![];
+        for (key, property) in properties {
+            // a lone key is an enum variant tag; name its fields after the enclosing type
+            let hint = if properties.len() == 1 && name.ends_with(&pascal_case(key)) {
+                format!("{}Fields", name)
+            } else {
+                format!("{}{}", name, pascal_case(key))
+            };
+            let mut ty = self.py_type(property, &hint);
+            if !required.contains(&key.as_str()) {
+                ty = format!("NotRequired[{}]", ty);
+            }
+            fields.push((key, ty));
+        }
+
+        if !fields.iter().all(|(key, _)| is_identifier(key)) {
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|(key, ty)| format!("{}: {}", py_str(key), ty))
+                .collect();
+            return format!(
+                "{} = TypedDict({}, {{{}}})\n",
+                name,
+                py_str(name),
+                fields.join(", ")
+            );
+        }
+        let mut out = format!("class {}(TypedDict):\n", name);
+        if let Some(description) = schema.get("description").and_then(Value::as_str) {
+            out.push_str(&format!("    \"\"\"{}\"\"\"\n", description));
+        }
+        if fields.is_empty() {
+            out.push_str("    ...\n");
+        }
+        for (key, ty) in fields {
+            out.push_str(&format!("    {}: {}\n", key, ty));
+        }
+        out
+    }
+
+    /// The Python type of a schema; `hint` names any TypedDict it needs to define.
+    fn py_type(&mut self, schema: &Value, hint: &str) -> String {
+        let Some(obj) = schema.as_object() else {
+            return "Any".to_string();
+        };
+        if obj.get("x-python-type").and_then(Value::as_str) == Some("callable") {
+            return "Callable[..., Any]".to_string();
+        }
+        if let Some(reference) = obj.get("$ref").and_then(Value::as_str) {
+            return reference.rsplit('/').next().unwrap_or("Any").to_string();
+        }
+        if let Some(value) = obj.get("const") {
+            return format!("Literal[{}]", py_literal(value));
+        }
+        if let Some(values) = obj.get("enum").and_then(Value::as_array) {
+            let values: Vec<String> = values.iter().map(py_literal).collect();
+            return format!("Literal[{}]", values.join(", "));
+        }
+        if let Some(alternatives) = obj
+            .get("anyOf")
+            .or_else(|| obj.get("oneOf"))
+            .and_then(Value::as_array)
+        {
+            return self.union(alternatives, hint);
+        }
+        if let Some([single]) = obj
+            .get("allOf")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+        {
+            return self.py_type(single, hint);
+        }
+        match obj.get("type") {
+            Some(Value::String(ty)) => self.base_type(ty, obj, hint),
+            Some(Value::Array(types)) => {
+                let alternatives: Vec<Value> = types
+                    .iter()
+                    .map(|ty| {
+                        let mut alternative = obj.clone();
+                        alternative.insert("type".to_string(), ty.clone());
+                        Value::Object(alternative)
+                    })
+                    .collect();
+                self.union(&alternatives, hint)
+            }
+            _ => "Any".to_string(),
+        }
+    }
+
+    fn union(&mut self, alternatives: &[Value], hint: &str) -> String {
+        let mut types: Vec<String> = vec![];
+        for (i, alternative) in alternatives.iter().enumerate() {
+            let hint = match single_key(alternative) {
+                Some(key) => format!("{}{}", hint, pascal_case(key)),
+                None if alternatives.len() > 1 => format!("{}{}", hint, i),
+                None => hint.to_string(),
+            };
+            let ty = self.py_type(alternative, &hint);
+            if !types.contains(&ty) {
+                types.push(ty);
+            }
+        }
+        let nullable = types.iter().any(|ty| ty == "None");
+        types.retain(|ty| ty != "None");
+        let inner = match types.len() {
+            0 => return "None".to_string(),
+            1 => types.remove(0),
+            _ => format!("Union[{}]", types.join(", ")),
+        };
+        if nullable {
+            format!("Optional[{}]", inner)
+        } else {
+            inner
+        }
+    }
+
+    fn base_type(&mut self, ty: &str, obj: &Map<String, Value>, hint: &str) -> String {
+        match ty {
+            "string" => "str".to_string(),
+            "integer" => "int".to_string(),
+            "number" => "float".to_string(),
+            "boolean" => "bool".to_string(),
+            "null" => "None".to_string(),
+            "array" => {
+                if let Some(items) = obj.get("prefixItems").and_then(Value::as_array) {
+                    let items: Vec<String> = items
+                        .iter()
+                        .enumerate()
+                        .map(|(i, item)| self.py_type(item, &format!("{}{}", hint, i)))
+                        .collect();
+                    return format!("Tuple[{}]", items.join(", "));
+                }
+                let item = obj
+                    .get("items")
+                    .map(|items| self.py_type(items, &format!("{}Item", hint)))
+                    .unwrap_or_else(|| "Any".to_string());
+                format!("List[{}]", item)
+            }
+            "object" => {
+                if obj.contains_key("properties") {
+                    self.define(hint, &Value::Object(obj.clone()));
+                    return hint.to_string();
+                }
+                let values = obj
+                    .get("patternProperties")
+                    .and_then(Value::as_object)
+                    .and_then(|patterns| patterns.values().next())
+                    .or_else(|| obj.get("additionalProperties"));
+                match values {
+                    Some(values) if values.is_object() => {
+                        format!(
+                            "Dict[str, {}]",
+                            self.py_type(values, &format!("{}Value", hint))
+                        )
+                    }
+                    _ => "Dict[str, Any]".to_string(),
+                }
+            }
+            _ => "Any".to_string(),
+        }
+    }
+}
+
+/// The tag of an externally tagged enum variant, which serialises as `{tag: fields}`.
+fn single_key(schema: &Value) -> Option<&str> {
+    let properties = schema.get("properties")?.as_object()?;
+    if properties.len() == 1 {
+        properties.keys().next().map(String::as_str)
+    } else {
+        None
+    }
+}
+
+fn pascal_case(name: &str) -> String {
+    name.split(['_', '.', '-'])
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !PY_KEYWORDS.contains(&name)
+}
+
+fn py_str(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+fn py_literal(value: &Value) -> String {
+    match value {
+        Value::Null => "None".to_string(),
+        Value::Bool(true) => "True".to_string(),
+        Value::Bool(false) => "False".to_string(),
+        Value::String(s) => py_str(s),
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    api::{
+        APIMethodParams, APIMethodResult, APIMethodSpec, APIRegister, ExternalCommandInput,
+        engine_api::EngineAPI, utils::try_parse,
+    },
+    engine::{
+        document::{Document, DocumentData},
+        documents::text::TextDocumentData,
+        messages::{Message, MessageLevel},
+    },
+};
+
+pub struct UIAPI {}
+
+impl UIAPI {
+    pub fn notify(state: &mut APIMethodParams) -> APIMethodResult {
+        let params = try_parse::<NotifyParams>(&state.params)?;
+        let mut message = Message::new(params.level, params.text).popup(params.popup);
+        if let Some(ms) = params.timeout_ms {
+            message = message.timeout(Duration::from_millis(ms));
+        }
+        state.engine.notify(message);
+        Ok(None)
+    }
+
+    pub fn messages(state: &mut APIMethodParams) -> APIMethodResult {
+        let messages: Vec<&Message> = state.engine.messages.history().collect();
+        Ok(Some(serde_json::to_value(messages)?))
+    }
+
+    /// Opens the message history as a text document in a split.
+    pub fn show_messages(state: &mut APIMethodParams) -> APIMethodResult {
+        let lines: Vec<String> = state
+            .engine
+            .messages
+            .history()
+            .flat_map(|message| {
+                let level = message.level.name();
+                message
+                    .text
+                    .lines()
+                    .map(move |line| format!("[{}] {}", level, line))
+            })
+            .collect();
+        let (doc_id, doc) = Document::new(
+            DocumentData::Text(TextDocumentData {
+                data: lines,
+                selected: None,
+            }),
+            None,
+        );
+        state.engine.docs.insert(doc_id.clone(), doc);
+        let src_win = state.engine.active_window.clone();
+        state.params = Some(ExternalCommandInput::JSON(json!({
+            "Split": {
+                "doc": doc_id,
+                "enter": true,
+                "src_win": src_win,
+                "direction": "down",
+            }
+        })));
+        EngineAPI::create_window(state)
+    }
+}
+
+impl APIRegister for UIAPI {
+    fn register_methods(api: &mut super::API) {
+        api.register_api(vec![
+            APIMethodSpec::new(
+                "ui.notify",
+                "Shows a message in the footer, and as a popup if `popup` is set.",
+                Self::notify,
+            )
+            .params::<NotifyParams>(),
+            APIMethodSpec::new(
+                "ui.messages",
+                "Lists the messages shown so far, oldest first.",
+                Self::messages,
+            )
+            .returns::<Vec<Message>>(),
+            APIMethodSpec::new(
+                "ui.show_messages",
+                "Opens the message history in a split below the current window.",
+                Self::show_messages,
+            ),
+        ]);
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+struct NotifyParams {
+    level: MessageLevel,
+    text: String,
+    /// How long the message stays on screen; by default longer for errors.
+    timeout_ms: Option<u64>,
+    #[serde(default)]
+    popup: bool,
+}
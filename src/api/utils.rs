@@ -1,11 +1,11 @@
 use pyo3::{Py, PyAny, Python, types::PyAnyMethods};
-use pythonize::depythonize;
+use pythonize::Depythonizer;
 use serde::de::DeserializeOwned;
 use serde_json::Value;
 
-use crate::api::ExternalCommandInput;
+use crate::api::{ApiError, ExternalCommandInput};
 
-pub fn try_parse<T>(input: &Option<ExternalCommandInput>) -> Result<T, String>
+pub fn try_parse<T>(input: &Option<ExternalCommandInput>) -> Result<T, ApiError>
 where
     T: DeserializeOwned,
 {
@@ -15,19 +15,32 @@ where
                 ExternalCommandInput::Python(obj) => Python::attach(|py| {
                     let bound_obj = obj.bind(py);
                     let res = if bound_obj.is_callable() {
-                        let result = bound_obj.call0().map_err(|e| e.to_string())?;
+                        let result = bound_obj.call0()?;
                         result
                     } else {
                         bound_obj.clone()
                     };
                     // 2. Try to deserialize into Command
-                    let params: T = depythonize(&res).map_err(|e| e.to_string())?;
+                    let params: T =
+                        serde_path_to_error::deserialize(&mut Depythonizer::from_object(&res))?;
                     Ok(params)
                 }),
-                ExternalCommandInput::JSON(value) => serde_json::from_value::<T>(value.clone())
-                    .map_err(|e| format!("Failed to parse input params: {}", e)),
+                ExternalCommandInput::JSON(value) => {
+                    Ok(serde_path_to_error::deserialize(value.clone())?)
+                }
             }
         }
-        None => Err("missing input parameters".to_string()),
+        None => Err(ApiError::invalid_params("missing input parameters")),
+    }
+}
+
+/// Like `try_parse`, but treats missing parameters as `T::default()`.
+pub fn try_parse_or_default<T>(input: &Option<ExternalCommandInput>) -> Result<T, ApiError>
+where
+    T: DeserializeOwned + Default,
+{
+    match input {
+        None | Some(ExternalCommandInput::JSON(Value::Null)) => Ok(T::default()),
+        Some(_) => try_parse(input),
     }
 }
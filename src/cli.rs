@@ -0,0 +1,63 @@
+use std::path::PathBuf;
+
+/// File opened when neocel is started without one.
+const DEFAULT_FILE: &str = "./test.csv";
+
+pub struct Args {
+    pub file: Option<PathBuf>,
+    pub listen: Option<PathBuf>,
+    pub headless: bool,
+    pub script: Option<PathBuf>,
+    /// Write Python type stubs for the API here and exit.
+    pub gen_stubs: Option<PathBuf>,
+}
+
+impl Args {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Self {
+            file: None,
+            listen: None,
+            headless: false,
+            script: None,
+            gen_stubs: None,
+        };
+        let mut args = args.into_iter().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--listen" => {
+                    let socket = args
+                        .next()
+                        .ok_or_else(|| "--listen requires a socket path".to_string())?;
+                    parsed.listen = Some(PathBuf::from(socket));
+                }
+                "--headless" => parsed.headless = true,
+                "--script" => {
+                    let script = args
+                        .next()
+                        .ok_or_else(|| "--script requires a file path".to_string())?;
+                    parsed.script = Some(PathBuf::from(script));
+                }
+                "--gen-stubs" => {
+                    let path = args
+                        .next()
+                        .ok_or_else(|| "--gen-stubs requires a file path".to_string())?;
+                    parsed.gen_stubs = Some(PathBuf::from(path));
+                }
+                flag if flag.starts_with("--") => return Err(format!("Unknown option: {}", flag)),
+                file => {
+                    if parsed.file.is_some() {
+                        return Err(format!("Unexpected argument: {}", file));
+                    }
+                    parsed.file = Some(PathBuf::from(file));
+                }
+            }
+        }
+        if parsed.headless && parsed.script.is_none() {
+            return Err("--headless requires --script <file>".to_string());
+        }
+        if parsed.file.is_none() && !parsed.headless {
+            parsed.file = Some(PathBuf::from(DEFAULT_FILE));
+        }
+        Ok(parsed)
+    }
+}
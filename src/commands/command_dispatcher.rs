@@ -1,31 +1,193 @@
 use crate::{
-    api::{API, APICaller, ExternalCommandInput},
-    engine::{Engine, document::DocType},
+    api::{API, APICaller, ApiError, ExternalCommandInput, error::register_python_module},
+    engine::{
+        Engine, EngineEvent, EngineEventKind,
+        document::DocType,
+        messages::{Message, MessageLevel},
+    },
     input::input_engine::InputEngine,
     render::UI,
 };
-use pyo3::{ffi::PyCFunction, prelude::*, types::PyDict};
+use pyo3::prelude::*;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use serde_json::{Value, json};
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use serde_json::Value;
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    marker::PhantomData,
+    rc::Rc,
+};
+use uuid::Uuid;
+
+/// Upper bound on how many rounds of events raised by subscribers themselves are
+/// delivered in one flush, so two handlers reacting to each other cannot hang the editor.
+const MAX_EVENT_ROUNDS: usize = 16;
 
 impl CommandDispatcher {
     pub fn new() -> Self {
         Self {
             global: HashMap::new(),
             per_document: HashMap::new(),
+            subscriptions: HashMap::new(),
         }
     }
     pub fn register_global(&mut self, id: &str, func: CommandFunction) {
-        self.global
-            .insert(id.to_string(), Rc::new(RefCell::new(func)));
+        self.register(id, None, func, CommandMeta::default());
     }
 
     pub fn register_for_doc(&mut self, doc_type: DocType, id: &str, func: CommandFunction) {
+        self.register(id, Some(doc_type), func, CommandMeta::default());
+    }
+
+    /// Registers a command for every document, or only for `doc_type` documents.
+    pub fn register(
+        &mut self,
+        id: &str,
+        doc_type: Option<DocType>,
+        func: CommandFunction,
+        meta: CommandMeta,
+    ) {
+        let command = RegisteredCommand {
+            source: CommandSource::of(&func),
+            handle: Rc::new(RefCell::new(func)),
+            meta,
+        };
+        match doc_type {
+            Some(doc_type) => self
+                .per_document
+                .entry(doc_type)
+                .or_default()
+                .insert(id.to_string(), command),
+            None => self.global.insert(id.to_string(), command),
+        };
+    }
+
+    /// The title of the command `id` resolves to in a document of `doc_type`.
+    pub fn title(&self, id: &str, doc_type: &DocType) -> Option<String> {
         self.per_document
-            .entry(doc_type)
+            .get(doc_type)
+            .and_then(|cmds| cmds.get(id))
+            .or_else(|| self.global.get(id))
+            .and_then(|cmd| cmd.meta.title.clone())
+    }
+
+    /// Every registered command with its metadata, global ones first.
+    pub fn list(&self) -> Vec<CommandInfo> {
+        let global = self.global.iter().map(|(id, cmd)| (None, id, cmd));
+        let per_document = self.per_document.iter().flat_map(|(doc_type, cmds)| {
+            cmds.iter().map(move |(id, cmd)| (Some(doc_type), id, cmd))
+        });
+        let mut commands: Vec<CommandInfo> = global
+            .chain(per_document)
+            .map(|(doc_type, id, cmd)| CommandInfo {
+                id: id.clone(),
+                doc_type: doc_type.cloned(),
+                source: cmd.source,
+                meta: cmd.meta.clone(),
+            })
+            .collect();
+        commands.sort_by(|a, b| (&a.doc_type, &a.id).cmp(&(&b.doc_type, &b.id)));
+        commands
+    }
+
+    pub fn subscribe(&mut self, event: EngineEventKind, func: CommandFunction) -> SubscriptionId {
+        let id = Uuid::new_v4().to_string();
+        self.subscriptions
+            .entry(event)
             .or_default()
-            .insert(id.to_string(), Rc::new(RefCell::new(func)));
+            .push((id.clone(), Rc::new(RefCell::new(func))));
+        id
+    }
+
+    pub fn unsubscribe(&mut self, id: &SubscriptionId) -> bool {
+        let mut found = false;
+        for subs in self.subscriptions.values_mut() {
+            let before = subs.len();
+            subs.retain(|(sub_id, _)| sub_id != id);
+            found |= subs.len() != before;
+        }
+        found
+    }
+
+    /// Delivers the engine's queued notifications to subscribed handlers and returns the
+    /// events that were delivered.
+    pub fn flush_events(
+        &mut self,
+        engine: &mut Engine,
+        input_engine: &mut InputEngine,
+        ui: &mut UI,
+    ) -> Vec<EngineEvent> {
+        let mut delivered = vec![];
+        for _ in 0..MAX_EVENT_ROUNDS {
+            let events: Vec<EngineEvent> = engine.notifications.drain(..).collect();
+            if events.is_empty() {
+                break;
+            }
+            for event in events {
+                let handlers: Vec<(SubscriptionId, CommandHandle)> = self
+                    .subscriptions
+                    .get(&event.kind())
+                    .cloned()
+                    .unwrap_or_default();
+                let payload = serde_json::to_value(&event).unwrap_or_default();
+                for (sub_id, handler) in handlers {
+                    let mut api = API::new();
+                    let res = api.run_command(engine, input_engine, ui, self, |caller| {
+                        CommandContext::scope(caller, |ctx| {
+                            let mut func = handler.borrow_mut();
+                            Self::call_event_handler(&mut func, ctx, payload.clone())
+                        })
+                    });
+                    match res {
+                        // returning `False` from a `BeforeQuit` handler keeps the editor open
+                        Ok(Some(Value::Bool(false)))
+                            if matches!(event, EngineEvent::BeforeQuit) =>
+                        {
+                            engine.veto_quit()
+                        }
+                        Ok(_) => {}
+                        Err(err) => {
+                            log::warn!("event handler {:?} failed: {}", sub_id, err);
+                            // a failing `message` handler would otherwise feed itself
+                            if !matches!(event, EngineEvent::Message(_)) {
+                                engine.notify(Message::new(
+                                    MessageLevel::Error,
+                                    format!("{:?} handler failed: {}", event.kind(), err),
+                                ));
+                            }
+                        }
+                    }
+                }
+                delivered.push(event);
+            }
+        }
+        // what the last round's handlers raised is dropped, but logged to trace the loop
+        let dropped: Vec<_> = engine.notifications.drain(..).map(|e| e.kind()).collect();
+        if !dropped.is_empty() {
+            log::warn!(
+                "dropped events after {} rounds of handlers, do they feed each other? {:?}",
+                MAX_EVENT_ROUNDS,
+                dropped
+            );
+        }
+        delivered
+    }
+
+    /// Calls an API method directly, outside of any command.
+    pub fn call_api(
+        &mut self,
+        method: &str,
+        params: Option<ExternalCommandInput>,
+        engine: &mut Engine,
+        input_engine: &mut InputEngine,
+        ui: &mut UI,
+    ) -> CommandResult {
+        let mut params = params;
+        let mut api = API::new();
+        api.run_command(engine, input_engine, ui, self, |caller| {
+            caller(method.to_string(), params.take())
+        })
     }
 
     pub fn dispatch(
@@ -34,23 +196,23 @@ impl CommandDispatcher {
         engine: &mut Engine,
         input_engine: &mut InputEngine,
         ui: &mut UI,
-    ) -> Result<Option<Value>, String> {
+    ) -> CommandResult {
         let doc_type = &engine.get_current_window().1.doc_type.clone();
         let selected_command = self
             .per_document
             .get(doc_type)
             .and_then(|m| m.get(&cmd.id))
-            .cloned()
-            .or_else(|| self.global.get(&cmd.id).cloned())
-            .ok_or_else(|| format!("Command not found: {}", cmd.id))?;
+            .or_else(|| self.global.get(&cmd.id))
+            .map(|command| command.handle.clone())
+            .ok_or_else(|| ApiError::not_found(format!("Command not found: {}", cmd.id)))?;
 
         let mut api = API::new();
 
         api.run_command(engine, input_engine, ui, self, move |caller| {
-            let mut ctx = CommandContext { fp: caller };
-            let mut cmd_fn = selected_command.borrow_mut();
-
-            Self::call_command_func(&mut cmd_fn, &mut ctx, cmd.args.clone())
+            CommandContext::scope(caller, |ctx| {
+                let mut cmd_fn = selected_command.borrow_mut();
+                Self::call_command_func(&mut cmd_fn, ctx, cmd.args.clone())
+            })
         })
     }
 
@@ -61,31 +223,58 @@ impl CommandDispatcher {
     ) -> CommandResult {
         match func {
             CommandFunction::Rust(f) => f(ctx, args),
-            CommandFunction::Internal(id, params) => ctx.call(
-                id.clone(),
-                Some(ExternalCommandInput::JSON(params.clone().unwrap_or_default())),
-            ),
+            // without params of its own the method gets the first argument, as the input
+            // engine passes counts, registers and motions
+            CommandFunction::Internal(id, params) => {
+                let params = params.clone().or_else(|| args.into_iter().next());
+                ctx.call(
+                    id.clone(),
+                    Some(ExternalCommandInput::JSON(params.unwrap_or_default())),
+                )
+            }
             CommandFunction::Python(py_func) => {
                 Python::attach(|py| {
                     let py_args = pythonize::pythonize(py, &args)
                         .map_err(|e| format!("Failed to convert args: {}", e))?;
                     let pyapi = ctx.to_py_api()?;
                     // Create API context with raw pointer
-                    let result = py_func
-                        .call1(py, (pyapi, py_args))
-                        .map_err(|e| format!("Python call failed: {}", e))?;
+                    let result = py_func.call1(py, (pyapi, py_args))?;
 
                     if result.is_none(py) {
                         Ok(None)
                     } else {
                         pythonize::depythonize(result.bind(py))
                             .map(Some)
-                            .map_err(|e| format!("Failed to deserialize result: {}", e))
+                            .map_err(|e| format!("Failed to deserialize result: {}", e).into())
                     }
                 })
             }
         }
     }
+
+    fn call_event_handler(
+        func: &mut CommandFunction,
+        ctx: &mut CommandContext,
+        payload: Value,
+    ) -> CommandResult {
+        match func {
+            CommandFunction::Python(py_func) => Python::attach(|py| {
+                let py_payload = pythonize::pythonize(py, &payload)
+                    .map_err(|e| format!("Failed to convert event: {}", e))?;
+                let pyapi = ctx.to_py_api()?;
+                let result = py_func.call1(py, (pyapi, py_payload))?;
+
+                if result.is_none(py) {
+                    Ok(None)
+                } else {
+                    pythonize::depythonize(result.bind(py))
+                        .map(Some)
+                        .map_err(|e| format!("Failed to deserialize result: {}", e).into())
+                }
+            }),
+            _ => Self::call_command_func(func, ctx, vec![payload]),
+        }
+    }
 }
 
 impl Default for CommandDispatcher {
@@ -95,48 +284,162 @@ impl Default for CommandDispatcher {
 }
 
 pub type CommandId = String;
-#[derive(Clone, Deserialize, Serialize, Debug)]
+#[derive(Clone, Deserialize, Serialize, Debug, JsonSchema)]
 pub struct CommandRequest {
     pub id: CommandId,
     pub args: Vec<Value>,
 }
 
 pub struct CommandContext<'a> {
-    fp: APICaller<'a>,
+    scope: Rc<ApiScope>,
+    _caller: PhantomData<APICaller<'a>>,
 }
 impl<'a> CommandContext<'a> {
-    pub fn call(
-        &mut self,
-        id: String,
-        params: Option<ExternalCommandInput>,
-    ) -> Result<Option<Value>, String> {
-        (self.fp)(id, params)
+    /// Runs `f` with a context calling the API through `caller`. The context never
+    /// outlives this call, and `ApiContext` objects it handed to Python stop working
+    /// once it returns or unwinds.
+    pub fn scope<R>(caller: APICaller<'a>, f: impl FnOnce(&mut CommandContext<'a>) -> R) -> R {
+        let mut ctx = Self::new(caller);
+        f(&mut ctx)
+    }
+    /// Only `scope` may build a context, since the lifetime erased here is only sound
+    /// if the context is dropped, and so the pointer cleared, before `'a` ends.
+    fn new(caller: APICaller<'a>) -> Self {
+        let ptr: *mut (dyn FnMut(String, Option<ExternalCommandInput>) -> CommandResult + 'a) =
+            caller;
+        // SAFETY: only the lifetime bound is erased. The pointer is reachable solely through
+        // `ApiScope::call`, and `scope` owns the context, whose `Drop` clears it before `'a`
+        // ends even on unwind, so it is never used after the caller it points to is gone.
+        let ptr: *mut RawApiCaller = unsafe { std::mem::transmute(ptr) };
+        Self {
+            scope: Rc::new(ApiScope {
+                caller: Cell::new(Some(ptr)),
+                busy: Cell::new(false),
+            }),
+            _caller: PhantomData,
+        }
+    }
+    pub fn call(&mut self, id: String, params: Option<ExternalCommandInput>) -> CommandResult {
+        self.scope.call(id, params)
     }
-    pub fn to_py_api(&mut self) -> Result<Py<ApiContext>, String> {
+    pub fn to_py_api(&mut self) -> Result<Py<ApiContext>, ApiError> {
         Python::attach(|py| {
-            let fp_ptr = self.fp
-                as *mut dyn FnMut(
-                    String,
-                    Option<ExternalCommandInput>,
-                ) -> Result<Option<Value>, String>;
-            let static_ptr: *mut (
-                dyn FnMut(String, Option<ExternalCommandInput>) -> Result<Option<Value>, String>
-                    + 'static
-            ) = unsafe { std::mem::transmute(fp_ptr) };
-
-            Py::new(py, ApiContext { fp_ptr: static_ptr }).map_err(|e| e.to_string())
+            register_python_module(py)?;
+            Ok(Py::new(
+                py,
+                ApiContext {
+                    scope: self.scope.clone(),
+                },
+            )?)
         })
     }
 }
+impl Drop for CommandContext<'_> {
+    fn drop(&mut self) {
+        self.scope.caller.set(None);
+        self.scope.busy.set(false);
+    }
+}
+
+type RawApiCaller = dyn FnMut(String, Option<ExternalCommandInput>) -> CommandResult + 'static;
+
+/// The API caller of one dispatch, shared with every `ApiContext` handed to Python.
+/// Python may keep those objects after the command returns, so the caller is cleared
+/// when the dispatch ends and later calls fail instead of touching freed memory.
+struct ApiScope {
+    caller: Cell<Option<*mut RawApiCaller>>,
+    busy: Cell<bool>,
+}
+impl ApiScope {
+    fn call(&self, id: String, params: Option<ExternalCommandInput>) -> CommandResult {
+        let caller = self
+            .caller
+            .get()
+            .ok_or("API object used after the command that provided it returned")?;
+        if self.busy.replace(true) {
+            return Err("API object used re-entrantly; use the api passed to this call".into());
+        }
+        // a call that panics, and whose panic Python catches, must not leave it busy
+        let _busy = BusyGuard(&self.busy);
+        // SAFETY: the pointer is still set, so the `CommandContext` that owns the caller has
+        // not been dropped, and `busy` guarantees no other call through it is running.
+        unsafe { (*caller)(id, params) }
+    }
+}
 
-type CommandResult = Result<Option<Value>, String>;
+/// Clears `ApiScope::busy` when the call holding it ends, however it ends.
+struct BusyGuard<'a>(&'a Cell<bool>);
+impl Drop for BusyGuard<'_> {
+    fn drop(&mut self) {
+        self.0.set(false);
+    }
+}
+
+pub type CommandResult = Result<Option<Value>, ApiError>;
 type CommandFn = dyn FnMut(&mut CommandContext, Vec<Value>) -> CommandResult;
 
 pub struct CommandDispatcher {
-    pub global: HashMap<String, CommandHandle>,
-    pub per_document: HashMap<DocType, HashMap<String, CommandHandle>>,
+    pub global: HashMap<String, RegisteredCommand>,
+    pub per_document: HashMap<DocType, HashMap<String, RegisteredCommand>>,
+    pub subscriptions: HashMap<EngineEventKind, Vec<(SubscriptionId, CommandHandle)>>,
 }
+pub type SubscriptionId = String;
 pub type CommandHandle = Rc<RefCell<CommandFunction>>;
+
+pub struct RegisteredCommand {
+    pub handle: CommandHandle,
+    pub source: CommandSource,
+    pub meta: CommandMeta,
+}
+
+/// What the command palette, help and completion show about a command.
+#[derive(Clone, Default, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct CommandMeta {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// JSON schema of the command's `args`.
+    pub args: Option<Value>,
+    /// The plugin that registered the command.
+    pub plugin: Option<String>,
+}
+impl CommandMeta {
+    pub fn new(title: &str, description: &str) -> Self {
+        Self {
+            title: Some(title.to_string()),
+            description: Some(description.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandSource {
+    Rust,
+    Python,
+    Internal,
+}
+impl CommandSource {
+    fn of(func: &CommandFunction) -> Self {
+        match func {
+            CommandFunction::Rust(_) => Self::Rust,
+            CommandFunction::Python(_) => Self::Python,
+            CommandFunction::Internal(..) => Self::Internal,
+        }
+    }
+}
+
+/// A registered command as listed by `command.list`.
+#[derive(Serialize, JsonSchema)]
+pub struct CommandInfo {
+    pub id: CommandId,
+    /// Set for commands that only exist in documents of this type.
+    pub doc_type: Option<DocType>,
+    pub source: CommandSource,
+    #[serde(flatten)]
+    pub meta: CommandMeta,
+}
 pub enum CommandFunction {
     Rust(Box<CommandFn>),
     Python(Py<PyAny>),
@@ -144,20 +447,17 @@ pub enum CommandFunction {
 }
 #[pyclass(unsendable)]
 pub struct ApiContext {
-    fp_ptr: *mut (
-        dyn FnMut(String, Option<ExternalCommandInput>) -> Result<Option<Value>, String> + 'static
-    ),
+    scope: Rc<ApiScope>,
 }
 
 #[pymethods]
 impl ApiContext {
+    #[pyo3(signature = (id, params=None))]
     fn call(&self, id: String, params: Option<Py<PyAny>>) -> PyResult<Py<PyAny>> {
         Python::attach(|py| {
             let input = params.map(|p| ExternalCommandInput::Python(p));
 
-            // UNSAFE: Call through the raw pointer
-            let result = unsafe { (*self.fp_ptr)(id, input) }
-                .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e))?;
+            let result = self.scope.call(id, input)?;
 
             let res = match result {
                 Some(value) => pythonize::pythonize(py, &value)
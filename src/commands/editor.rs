@@ -0,0 +1,123 @@
+use serde_json::{Value, json};
+
+use crate::{
+    api::ExternalCommandInput,
+    commands::{
+        CommandRegistry,
+        command_dispatcher::{
+            CommandContext, CommandDispatcher, CommandFunction, CommandMeta, CommandResult,
+        },
+    },
+};
+
+/// The normal mode commands the input engine emits for operators and motions.
+pub struct EditorCommands {}
+
+impl CommandRegistry for EditorCommands {
+    fn register_commands(dispatcher: &mut CommandDispatcher) -> Result<(), String> {
+        dispatcher.register(
+            "editor.operator",
+            None,
+            CommandFunction::Internal("editor.operator".to_string(), None),
+            CommandMeta::new(
+                "Operator",
+                "Move by a motion, or delete, change, yank or shift the text it covers.",
+            ),
+        );
+        dispatcher.register(
+            "editor.insert",
+            None,
+            CommandFunction::Internal("doc.changeMode".to_string(), Some(json!({"mode": "Input"}))),
+            CommandMeta::new("Insert Mode", "Start typing at the cursor."),
+        );
+        dispatcher.register(
+            "editor.visual",
+            None,
+            CommandFunction::Rust(Box::new(visual)),
+            CommandMeta::new(
+                "Visual Mode",
+                "Select from the cursor as it moves, by character, line or block.",
+            ),
+        );
+        dispatcher.register(
+            "editor.put",
+            None,
+            CommandFunction::Internal("editor.put".to_string(), None),
+            CommandMeta::new(
+                "Put",
+                "Put a register after the cursor, or at the selected cell.",
+            ),
+        );
+        dispatcher.register(
+            "editor.put_transposed",
+            None,
+            CommandFunction::Internal("editor.put".to_string(), Some(json!({"transpose": true}))),
+            CommandMeta::new(
+                "Put Transposed",
+                "Put yanked cells with their rows and columns swapped.",
+            ),
+        );
+        dispatcher.register(
+            "editor.paste",
+            None,
+            CommandFunction::Internal("editor.paste".to_string(), None),
+            CommandMeta::new(
+                "Paste",
+                "Insert pasted text, spreading tab- or comma-separated values over cells.",
+            ),
+        );
+        dispatcher.register(
+            "editor.undo",
+            None,
+            CommandFunction::Internal("editor.undo".to_string(), None),
+            CommandMeta::new("Undo", "Revert the last change recorded for undo."),
+        );
+        dispatcher.register(
+            "editor.repeat",
+            None,
+            CommandFunction::Internal("editor.repeat".to_string(), None),
+            CommandMeta::new(
+                "Repeat Change",
+                "Repeat the last change, with a new count if one is typed.",
+            ),
+        );
+        dispatcher.register(
+            "macro.record",
+            None,
+            CommandFunction::Internal("macro.record".to_string(), None),
+            CommandMeta::new(
+                "Record Macro",
+                "Record typed keys into a register, until `q`.",
+            ),
+        );
+        dispatcher.register(
+            "macro.stop",
+            None,
+            CommandFunction::Internal("macro.stop".to_string(), None),
+            CommandMeta::new(
+                "Stop Recording",
+                "Store the keys recorded so far in the register.",
+            ),
+        );
+        dispatcher.register(
+            "macro.play",
+            None,
+            CommandFunction::Internal("macro.play".to_string(), None),
+            CommandMeta::new("Play Macro", "Play the keys in a register as if typed."),
+        );
+        Ok(())
+    }
+}
+
+/// Passes `v`, `V` or `Ctrl-v` on to `editor.visual`; without a kind it starts a
+/// characterwise selection.
+fn visual(ctx: &mut CommandContext, args: Vec<Value>) -> CommandResult {
+    let params = args
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| json!({"kind": "char"}));
+    ctx.call(
+        "editor.visual".to_string(),
+        Some(ExternalCommandInput::JSON(params)),
+    )
+}
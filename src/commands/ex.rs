@@ -0,0 +1,224 @@
+use serde_json::{Value, json};
+
+use crate::{
+    api::{ApiError, ExternalCommandInput},
+    commands::{
+        CommandRegistry,
+        command_dispatcher::{
+            CommandContext, CommandDispatcher, CommandFunction, CommandMeta, CommandResult,
+        },
+    },
+    engine::documents::spreadsheet::parse_cell_ref,
+};
+
+/// The built-in commands typed on the `:` command line.
+pub struct ExCommands {}
+
+impl CommandRegistry for ExCommands {
+    fn register_commands(dispatcher: &mut CommandDispatcher) -> Result<(), String> {
+        dispatcher.register(
+            "cmdline.open",
+            None,
+            CommandFunction::Internal(
+                "doc.changeMode".to_string(),
+                Some(json!({"mode": "Command"})),
+            ),
+            CommandMeta::new("Command Line", "Type a command in the footer."),
+        );
+        dispatcher.register(
+            "cmdline.complete",
+            None,
+            CommandFunction::Internal("cmdline.complete".to_string(), None),
+            CommandMeta::new("Complete", "Complete the command id or path being typed."),
+        );
+        dispatcher.register(
+            "write",
+            None,
+            CommandFunction::Rust(Box::new(write)),
+            CommandMeta::new(
+                "Write",
+                "`:w [path]` saves the document, optionally elsewhere.",
+            ),
+        );
+        dispatcher.register(
+            "quit",
+            None,
+            CommandFunction::Internal("kill".to_string(), None),
+            CommandMeta::new("Quit", "`:q` quits the editor."),
+        );
+        dispatcher.register(
+            "edit",
+            None,
+            CommandFunction::Rust(Box::new(edit)),
+            CommandMeta::new("Edit", "`:e <path>` opens a file in the current window."),
+        );
+        dispatcher.register(
+            "split",
+            None,
+            CommandFunction::Rust(Box::new(|ctx, args| split(ctx, args, "down"))),
+            CommandMeta::new("Split", "`:sp [path]` splits the window horizontally."),
+        );
+        dispatcher.register(
+            "vsplit",
+            None,
+            CommandFunction::Rust(Box::new(|ctx, args| split(ctx, args, "right"))),
+            CommandMeta::new(
+                "Vertical Split",
+                "`:vs [path]` splits the window vertically.",
+            ),
+        );
+        dispatcher.register(
+            "resize",
+            None,
+            CommandFunction::Rust(Box::new(resize)),
+            CommandMeta::new(
+                "Resize Window",
+                "`:resize <dir> [n]` moves the window's edge out by n cells, or in if negative.",
+            ),
+        );
+        dispatcher.register(
+            "equalize",
+            None,
+            CommandFunction::Internal("window.equalize".to_string(), None),
+            CommandMeta::new(
+                "Equalize Windows",
+                "`:equalize` gives every window equal space.",
+            ),
+        );
+        dispatcher.register(
+            "swap",
+            None,
+            CommandFunction::Rust(Box::new(swap)),
+            CommandMeta::new(
+                "Swap Window",
+                "`:swap <dir>` exchanges the window with its neighbour on that side.",
+            ),
+        );
+        dispatcher.register(
+            "rotate",
+            None,
+            CommandFunction::Rust(Box::new(rotate)),
+            CommandMeta::new(
+                "Rotate Windows",
+                "`:rotate [reverse]` moves the windows in the window's row or column along.",
+            ),
+        );
+        dispatcher.register(
+            "zoom",
+            None,
+            CommandFunction::Internal("window.zoom".to_string(), None),
+            CommandMeta::new(
+                "Zoom Window",
+                "`:zoom` fills the screen with the window, or restores the layout.",
+            ),
+        );
+        dispatcher.register(
+            "goto-cell",
+            None,
+            CommandFunction::Rust(Box::new(goto_cell)),
+            CommandMeta::new("Go to Cell", "`:goto-cell B12` moves the cursor to a cell."),
+        );
+        dispatcher.register(
+            "sort",
+            None,
+            CommandFunction::Rust(Box::new(sort)),
+            CommandMeta::new(
+                "Sort",
+                "`:sort [desc]` sorts rows by the cursor's column, or lines of text.",
+            ),
+        );
+        dispatcher.register(
+            "messages",
+            None,
+            CommandFunction::Internal("ui.show_messages".to_string(), None),
+            CommandMeta::new("Messages", "`:messages` opens the message history."),
+        );
+        Ok(())
+    }
+}
+
+fn call(ctx: &mut CommandContext, id: &str, params: Value) -> CommandResult {
+    ctx.call(id.to_string(), Some(ExternalCommandInput::JSON(params)))
+}
+
+/// The argument at `index`, which the command cannot do without.
+fn required_arg<'a>(args: &'a [Value], index: usize, what: &str) -> Result<&'a Value, ApiError> {
+    args.get(index).ok_or_else(|| {
+        ApiError::invalid_field(&format!("args[{}]", index), format!("missing {}", what))
+    })
+}
+
+fn current_window(ctx: &mut CommandContext) -> Result<Value, ApiError> {
+    Ok(call(ctx, "window.get_current", Value::Null)?.unwrap_or_default()["window"].clone())
+}
+
+fn write(ctx: &mut CommandContext, args: Vec<Value>) -> CommandResult {
+    let params = match args.first() {
+        Some(path) => json!({"path": path}),
+        None => Value::Null,
+    };
+    call(ctx, "doc.save", params)
+}
+
+fn edit(ctx: &mut CommandContext, args: Vec<Value>) -> CommandResult {
+    let path = required_arg(&args, 0, "file path")?;
+    call(ctx, "doc.open", json!({"path": path}))
+}
+
+fn split(ctx: &mut CommandContext, args: Vec<Value>, direction: &str) -> CommandResult {
+    let window = current_window(ctx)?;
+    call(
+        ctx,
+        "window.create",
+        json!({"Split": {
+            "doc": window["doc_id"],
+            "enter": true,
+            "src_win": window["id"],
+            "direction": direction,
+        }}),
+    )?;
+    match args.first() {
+        Some(path) => call(ctx, "doc.open", json!({"path": path})),
+        None => Ok(None),
+    }
+}
+
+fn resize(ctx: &mut CommandContext, args: Vec<Value>) -> CommandResult {
+    let dir = required_arg(&args, 0, "direction")?;
+    let cells = match args.get(1) {
+        Some(Value::String(cells)) => Some(cells.parse::<i32>().map_err(|_| {
+            ApiError::invalid_field("args[1]", format!("{} is not a number", cells))
+        })?),
+        Some(cells) => cells.as_i64().map(|cells| cells as i32),
+        None => None,
+    };
+    call(ctx, "window.resize", json!({"dir": dir, "cells": cells}))
+}
+
+fn swap(ctx: &mut CommandContext, args: Vec<Value>) -> CommandResult {
+    let dir = required_arg(&args, 0, "direction")?;
+    call(ctx, "window.swap", json!({"dir": dir}))
+}
+
+fn rotate(ctx: &mut CommandContext, args: Vec<Value>) -> CommandResult {
+    let reverse = args.first().and_then(Value::as_str) == Some("reverse");
+    call(ctx, "window.rotate", json!({"reverse": reverse}))
+}
+
+fn goto_cell(ctx: &mut CommandContext, args: Vec<Value>) -> CommandResult {
+    let reference = required_arg(&args, 0, "cell reference")?;
+    let (row, col) = reference.as_str().and_then(parse_cell_ref).ok_or_else(|| {
+        ApiError::invalid_field("args[0]", format!("{} is not a cell like B12", reference))
+    })?;
+    let window = current_window(ctx)?;
+    call(
+        ctx,
+        "window.set_cursor",
+        json!({"win_id": window["id"], "row": row, "col": col}),
+    )
+}
+
+fn sort(ctx: &mut CommandContext, args: Vec<Value>) -> CommandResult {
+    let descending = args.first().and_then(Value::as_str) == Some("desc");
+    call(ctx, "doc.sort", json!({"descending": descending}))
+}
@@ -1,12 +1,13 @@
 pub mod command_dispatcher;
+pub mod editor;
+pub mod ex;
 pub mod globals;
 
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt};
 
 use crossterm::event::{KeyCode as CtKey, KeyEvent, KeyModifiers};
 
 use bitflags::bitflags;
-use serde::Deserialize;
 
 use crate::{commands::command_dispatcher::CommandDispatcher, input::{Token, keymaps::ActionNode}};
 pub trait CommandRegistry {
@@ -70,13 +71,15 @@ impl From<KeyEvent> for Key {
         if ev.modifiers.contains(KeyModifiers::ALT) {
             mods |= Modifiers::ALT;
         }
-        if ev.modifiers.contains(KeyModifiers::SHIFT) {
+        // a shifted character already arrives as its shifted form, e.g. `:`
+        if ev.modifiers.contains(KeyModifiers::SHIFT) && !matches!(ev.code, CtKey::Char(_)) {
             mods |= Modifiers::SHIFT;
         }
 
         let code = match ev.code {
             CtKey::Char(c) => KeyCode::Char(c),
             CtKey::Enter => KeyCode::Enter,
+            CtKey::Tab => KeyCode::Tab,
             CtKey::BackTab => KeyCode::BackTab,
             CtKey::Esc => KeyCode::Esc,
             CtKey::Backspace => KeyCode::Backspace,
@@ -95,7 +98,38 @@ impl From<KeyEvent> for Key {
 }
 
 
-fn parse_key(key_str: &str) -> Result<Key, String> {
+/// Formats the key the way `parse_key` reads it, e.g. `ctrl+p` or `space`.
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (Modifiers::CTRL, "ctrl"),
+            (Modifiers::ALT, "alt"),
+            (Modifiers::SHIFT, "shift"),
+            (Modifiers::SUPER, "super"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{}+", name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::Enter => f.write_str("enter"),
+            KeyCode::Esc => f.write_str("esc"),
+            KeyCode::Backspace => f.write_str("backspace"),
+            KeyCode::Tab => f.write_str("tab"),
+            KeyCode::BackTab => f.write_str("backtab"),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            KeyCode::Up => f.write_str("up"),
+            KeyCode::Down => f.write_str("down"),
+            KeyCode::Left => f.write_str("left"),
+            KeyCode::Right => f.write_str("right"),
+        }
+    }
+}
+
+/// Reads a key the way bindings name it, e.g. `ctrl+p` or `space`.
+pub fn parse_key(key_str: &str) -> Result<Key, String> {
     let parts: Vec<&str> = key_str.split('+').collect();
     let mut modifiers = Modifiers::empty();
     let mut key_part = key_str;
@@ -129,7 +163,8 @@ fn parse_key(key_str: &str) -> Result<Key, String> {
                 .map_err(|_| format!("Invalid function key: {}", s))?;
             KeyCode::F(num)
         }
-        s if s.len() == 1 => KeyCode::Char(s.chars().next().unwrap()),
+        // keep the case of a letter, so `Q` and `q` can be bound apart
+        _ if key_part.chars().count() == 1 => KeyCode::Char(key_part.chars().next().unwrap()),
         _ => return Err(format!("Unknown key: {}", key_part)),
     };
 
@@ -17,7 +17,8 @@ pub struct Config {
 
 impl Config {
     pub fn get_style_color(&mut self, identifier: &str, default_val: Option<Color>) -> Color {
-        match hex_to_color(self.styles[&identifier.to_string()].as_str()) {
+        let hex = self.styles.get(identifier).map_or("", String::as_str);
+        match hex_to_color(hex) {
             Ok(color) => color,
             Err(str) => {
                 if let Some(def) = default_val {
@@ -78,7 +79,7 @@ pub fn parse_keymap(config: &HashMap<String, String>) -> HashMap<KeyEvent, Strin
         if let Some(key) = parse_keybinding(key_str) {
             out.insert(key, command.clone());
         } else {
-            eprintln!("Invalid keybinding: {}", key_str);
+            log::warn!("Invalid keybinding: {}", key_str);
         }
     }
 
@@ -1,30 +1,36 @@
 use crate::{
+    api::ApiError,
     commands::{Key, Modifiers, command_dispatcher::CommandRequest},
     engine::{
-        Edit,
+        Edit, WindowState,
         documents::{
-            InsertModeProvider, spreadsheet::SpreadSheetDocumentData, text::TextDocumentData,
+            DocumentDataProvider, InsertModeProvider, palette::PaletteDocumentData,
+            spreadsheet::SpreadSheetDocumentData, text::TextDocumentData,
         },
+        selection::{Selection, SelectionSize},
     },
     input::keymaps::{ActionNode, KeymapProvider},
 };
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::{
-    collections::{HashMap, HashSet},
-    path::PathBuf,
-};
+use std::{collections::HashMap, path::PathBuf};
 use uuid::Uuid;
 
 pub type DocId = String;
+/// Start and end `(row, col)` of a span of a document; end is exclusive.
+pub type DocRange = ((usize, usize), (usize, usize));
 
-#[derive(Eq, Clone, Hash, PartialEq, Serialize, Deserialize)]
+#[derive(
+    Eq, Ord, Clone, Debug, Hash, PartialEq, PartialOrd, Serialize, Deserialize, JsonSchema,
+)]
 #[serde(rename_all = "snake_case")]
 pub enum DocType {
     SpreadSheet,
     Info,
     Text,
+    Palette,
 }
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct Document {
     pub id: DocId,
     pub doc_type: DocType,
@@ -41,6 +47,7 @@ impl Document {
             DocumentData::Text(_) => DocType::Text,
             DocumentData::SpreadSheet(_) => DocType::SpreadSheet,
             DocumentData::Help(_) => DocType::Info,
+            DocumentData::Palette(_) => DocType::Palette,
             _ => DocType::Info,
         };
         let id = Uuid::new_v4().to_string();
@@ -58,21 +65,105 @@ impl Document {
     }
 }
 
-#[derive(Serialize)]
+impl Document {
+    /// Writes the document to `path`, or to the path it was opened from.
+    pub fn save(&mut self, path: Option<PathBuf>) -> Result<PathBuf, ApiError> {
+        let path = path
+            .or_else(|| self.path.clone())
+            .ok_or_else(|| ApiError::from("Document has no file path"))?;
+        let content = match &self.data {
+            DocumentData::SpreadSheet(data) => data.to_raw()?,
+            DocumentData::Text(data) => data.to_raw()?,
+            DocumentData::Help(content) | DocumentData::Config(content) => content.clone(),
+            DocumentData::Palette(_) => return Err("A palette cannot be saved".into()),
+        };
+        std::fs::write(&path, content)?;
+        self.path = Some(path.clone());
+        Ok(path)
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
 pub enum DocumentData {
     SpreadSheet(SpreadSheetDocumentData),
     Text(TextDocumentData),
     Help(String),
     Config(String),
+    Palette(PaletteDocumentData),
 }
 impl DocumentData {
     pub fn as_insertable(&mut self) -> Option<&mut dyn InsertModeProvider> {
         match self {
             Self::SpreadSheet(t) => Some(t),
             Self::Text(t) => Some(t),
+            Self::Palette(t) => Some(t),
             _ => None,
         }
     }
+    /// The visual selection of a text or spreadsheet document.
+    pub fn selection_mut(&mut self) -> Option<&mut Option<Selection>> {
+        match self {
+            Self::SpreadSheet(t) => Some(&mut t.selected),
+            Self::Text(t) => Some(&mut t.selected),
+            _ => None,
+        }
+    }
+    /// The size of the visual selection, whose far end is `win`'s cursor in text or
+    /// the selected cell of a spreadsheet.
+    pub fn selection_size(&self, win: &WindowState) -> Option<SelectionSize> {
+        match self {
+            Self::SpreadSheet(t) => Some(t.selected?.size(t.selected_cell)),
+            Self::Text(t) => Some(t.selected?.size((win.cursor_row, win.cursor_col))),
+            _ => None,
+        }
+    }
+    /// Number of rows and columns spanned by the document's content.
+    pub fn extent(&self) -> (usize, usize) {
+        match self {
+            Self::SpreadSheet(t) => t.extent(),
+            Self::Text(t) => (
+                t.data.len(),
+                t.data.iter().map(|l| l.chars().count()).max().unwrap_or(0),
+            ),
+            Self::Help(s) | Self::Config(s) => (
+                s.lines().count(),
+                s.lines().map(|l| l.chars().count()).max().unwrap_or(0),
+            ),
+            Self::Palette(p) => (p.matches.len() + 1, p.query.chars().count()),
+        }
+    }
+    pub fn validate_position(&self, row: usize, col: usize) -> Result<(), String> {
+        match self {
+            Self::SpreadSheet(t) => {
+                let (rows, cols) = t.extent();
+                if row >= rows.max(1) || col >= cols.max(1) {
+                    return Err(format!(
+                        "Cell ({}, {}) is outside the sheet ({} rows, {} columns)",
+                        row, col, rows, cols
+                    ));
+                }
+                Ok(())
+            }
+            Self::Text(t) => {
+                if row >= t.data.len().max(1) {
+                    return Err(format!(
+                        "Line {} is outside the document ({} lines)",
+                        row,
+                        t.data.len()
+                    ));
+                }
+                let len = t.data.get(row).map(|l| l.chars().count()).unwrap_or(0);
+                if col > len {
+                    return Err(format!(
+                        "Column {} is outside line {} ({} characters)",
+                        col, row, len
+                    ));
+                }
+                Ok(())
+            }
+            _ => Err("Document has no cursor".to_string()),
+        }
+    }
 }
 impl KeymapProvider for Document {
     fn get_keymap_cache(&self) -> &Option<crate::input::keymaps::ActionNode> {
@@ -105,6 +196,21 @@ impl KeymapProvider for Document {
                 action: None,
             },
         );
+        if self.doc_type == DocType::Palette {
+            keymap.insert(
+                Key {
+                    code: crate::commands::KeyCode::Esc,
+                    modifiers: Modifiers::empty(),
+                },
+                ActionNode {
+                    children: HashMap::new(),
+                    action: Some(crate::input::Token::Command(CommandRequest {
+                        id: "palette.close".to_string(),
+                        args: vec![],
+                    })),
+                },
+            );
+        }
         ActionNode {
             children: keymap,
             action: None,
@@ -1,20 +1,33 @@
 use crate::{
-    commands::Key,
-    engine::{Engine, WindowState},
+    api::ApiError,
+    commands::{Key, command_dispatcher::CommandRequest},
+    engine::{Engine, WindowState, document::DocRange},
 };
 
+pub mod palette;
 pub mod spreadsheet;
 pub mod text;
 pub trait InsertModeProvider {
-    fn handle_key(&mut self, window: &mut WindowState, key: Key) -> Result<(), String>;
+    /// Applies a key typed in insert mode, returning the range it modified, if any.
+    fn handle_key(
+        &mut self,
+        window: &mut WindowState,
+        key: Key,
+    ) -> Result<Option<DocRange>, ApiError>;
+    /// A command the typed keys asked to run, e.g. `palette.accept` after Enter.
+    fn take_command(&mut self) -> Option<CommandRequest> {
+        None
+    }
 }
 pub trait DocumentDataProvider {
     fn new() -> Self;
 
-    fn from_file(path: &str) -> Result<Self, String>
+    fn from_file(path: &str) -> Result<Self, ApiError>
     where
         Self: Sized;
     fn from_raw(content: &str) -> Result<Self, String>
     where
         Self: Sized;
+    /// Serialises the document back into the format it was read from.
+    fn to_raw(&self) -> Result<String, String>;
 }
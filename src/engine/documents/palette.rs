@@ -0,0 +1,154 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{
+    api::ApiError,
+    commands::{Key, KeyCode, command_dispatcher::CommandRequest},
+    engine::{WindowId, WindowState, document::DocRange, documents::InsertModeProvider},
+    input::input_engine::ModeType,
+};
+
+/// A command offered by the palette.
+#[derive(Serialize, JsonSchema, Clone)]
+pub struct PaletteEntry {
+    pub id: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Key sequences bound to the command, e.g. `ctrl+q` or `space down`.
+    pub keys: Vec<String>,
+}
+impl PaletteEntry {
+    pub fn label(&self) -> &str {
+        self.title.as_deref().unwrap_or(&self.id)
+    }
+}
+
+/// The query line and filtered command list of a command palette.
+#[derive(Serialize, JsonSchema)]
+pub struct PaletteDocumentData {
+    pub query: String,
+    pub entries: Vec<PaletteEntry>,
+    /// Indices into `entries` matching the query, best match first.
+    pub matches: Vec<usize>,
+    /// Index into `matches` of the highlighted entry.
+    pub selected: usize,
+    /// The window the palette was opened from, whose commands it lists.
+    pub origin: WindowId,
+    pub origin_mode: ModeType,
+    #[serde(skip)]
+    accepted: bool,
+}
+impl PaletteDocumentData {
+    pub fn new(entries: Vec<PaletteEntry>, origin: WindowId, origin_mode: ModeType) -> Self {
+        let mut data = Self {
+            query: String::new(),
+            matches: vec![],
+            entries,
+            selected: 0,
+            origin,
+            origin_mode,
+            accepted: false,
+        };
+        data.filter();
+        data
+    }
+    pub fn selected_entry(&self) -> Option<&PaletteEntry> {
+        self.matches.get(self.selected).map(|&i| &self.entries[i])
+    }
+    /// Re-ranks the entries against the query and resets the selection.
+    fn filter(&mut self) {
+        let mut scored: Vec<(i64, usize)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| {
+                let by_title = entry
+                    .title
+                    .as_deref()
+                    .and_then(|title| fuzzy_score(&self.query, title));
+                let by_id = fuzzy_score(&self.query, &entry.id);
+                by_title.max(by_id).map(|score| (score, i))
+            })
+            .collect();
+        // stable, so equal scores keep the entries' order
+        scored.sort_by_key(|(score, _)| -score);
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.selected = 0;
+    }
+}
+
+impl InsertModeProvider for PaletteDocumentData {
+    fn handle_key(
+        &mut self,
+        window: &mut WindowState,
+        key: Key,
+    ) -> Result<Option<DocRange>, ApiError> {
+        let before = self.query.chars().count();
+        match key.code {
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.filter();
+            }
+            KeyCode::Backspace => {
+                if self.query.pop().is_none() {
+                    return Ok(None);
+                }
+                self.filter();
+            }
+            KeyCode::Up => {
+                self.selected = self.selected.saturating_sub(1);
+                return Ok(None);
+            }
+            KeyCode::Down => {
+                if self.selected + 1 < self.matches.len() {
+                    self.selected += 1;
+                }
+                return Ok(None);
+            }
+            KeyCode::Enter => {
+                self.accepted = self.selected_entry().is_some();
+                return Ok(None);
+            }
+            _ => return Ok(None),
+        }
+        let after = self.query.chars().count();
+        window.cursor_row = 0;
+        window.cursor_col = after;
+        Ok(Some(((0, before.min(after)), (0, before.max(after)))))
+    }
+    fn take_command(&mut self) -> Option<CommandRequest> {
+        if !std::mem::take(&mut self.accepted) {
+            return None;
+        }
+        Some(CommandRequest {
+            id: "palette.accept".to_string(),
+            args: vec![],
+        })
+    }
+}
+
+/// Scores `text` against `query` when the query's characters appear in it in order,
+/// ignoring case; consecutive matches and matches at word starts score higher.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut last: Option<usize> = None;
+    let mut pos = 0;
+    for q in query.chars().flat_map(char::to_lowercase) {
+        if q == ' ' {
+            continue;
+        }
+        let found = pos + text[pos..].iter().position(|&c| c == q)?;
+        score += 1;
+        if last.is_some_and(|last| last + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || matches!(text[found - 1], ' ' | '.' | '_' | '-') {
+            score += 3;
+        }
+        score -= (found - last.map_or(0, |last| last + 1)).min(5) as i64;
+        last = Some(found);
+        pos = found + 1;
+    }
+    Some(score)
+}
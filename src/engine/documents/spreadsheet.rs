@@ -1,31 +1,44 @@
 use std::{
+    cmp::Ordering,
     collections::{HashMap, HashSet},
     fs::File,
 };
 
-use csv::ReaderBuilder;
+use csv::{ReaderBuilder, WriterBuilder};
+use schemars::JsonSchema;
 use serde::Serialize;
 
-use crate::engine::{
-    WindowState,
-    documents::{DocumentDataProvider, InsertModeProvider},
+use crate::{
+    api::ApiError,
+    engine::{
+        WindowState,
+        document::DocRange,
+        documents::{DocumentDataProvider, InsertModeProvider},
+        selection::Selection,
+    },
 };
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct SpreadSheetDocumentData {
     pub cells: HashMap<usize, HashMap<usize, Cell>>,
     pub selected_cell: (usize, usize),
+    /// The visual selection, from its anchor to the selected cell.
+    pub selected: Option<Selection>,
+    /// Widths set by dragging a column's header border, in place of the fitted width.
+    pub column_widths: HashMap<usize, usize>,
 }
 impl DocumentDataProvider for SpreadSheetDocumentData {
     fn new() -> Self {
         Self {
             cells: HashMap::new(),
             selected_cell: (0, 0),
+            selected: None,
+            column_widths: HashMap::new(),
         }
     }
 
-    fn from_file(path: &str) -> Result<Self, String> {
-        let file = File::open(path.clone()).map_err(|e| e.to_string())?;
+    fn from_file(path: &str) -> Result<Self, ApiError> {
+        let file = File::open(path)?;
         let mut reader = ReaderBuilder::new().has_headers(false).from_reader(file);
 
         let mut outer_map: HashMap<usize, HashMap<usize, Cell>> = HashMap::new();
@@ -52,9 +65,30 @@ impl DocumentDataProvider for SpreadSheetDocumentData {
         Ok(Self {
             cells: outer_map,
             selected_cell: (0, 0),
+            selected: None,
+            column_widths: HashMap::new(),
         })
     }
 
+    fn to_raw(&self) -> Result<String, String> {
+        let (rows, cols) = self.extent();
+        let mut writer = WriterBuilder::new().has_headers(false).from_writer(vec![]);
+        for row in 0..rows {
+            let record: Vec<&str> = (0..cols)
+                .map(|col| {
+                    self.cells
+                        .get(&row)
+                        .and_then(|r| r.get(&col))
+                        .map(|c| c.raw.as_str())
+                        .unwrap_or("")
+                })
+                .collect();
+            writer.write_record(&record).map_err(|e| e.to_string())?;
+        }
+        let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+        String::from_utf8(bytes).map_err(|e| e.to_string())
+    }
+
     fn from_raw(content: &str) -> Result<Self, String> {
         let mut reader = ReaderBuilder::new()
         .has_headers(false)
@@ -84,21 +118,180 @@ impl DocumentDataProvider for SpreadSheetDocumentData {
         Ok(Self {
             cells: outer_map,
             selected_cell: (0, 0),
+            selected: None,
+            column_widths: HashMap::new(),
         })
     }
 }
+/// The narrowest a column can be dragged to.
+pub const MIN_COLUMN_WIDTH: usize = 3;
+
+impl SpreadSheetDocumentData {
+    /// How many terminal columns `col` takes in a table: the width it was dragged
+    /// to, or enough for its longest value.
+    pub fn column_width(&self, col: usize) -> usize {
+        if let Some(width) = self.column_widths.get(&col) {
+            return *width;
+        }
+        let longest = self
+            .cells
+            .values()
+            .filter_map(|row| row.get(&col))
+            .map(|cell| cell.raw.len())
+            .max()
+            .unwrap_or(3);
+        (longest + 4).max(10)
+    }
+    /// Number of rows and columns up to and including the last populated cell.
+    pub fn extent(&self) -> (usize, usize) {
+        let rows = self.cells.keys().max().map(|r| r + 1).unwrap_or(0);
+        let cols = self
+            .cells
+            .values()
+            .flat_map(|r| r.keys())
+            .max()
+            .map(|c| c + 1)
+            .unwrap_or(0);
+        (rows, cols)
+    }
+    /// Reorders whole rows by their value in `col`; empty cells sort last either way.
+    pub fn sort_rows(&mut self, col: usize, descending: bool) {
+        let (rows, _) = self.extent();
+        let mut sorted: Vec<HashMap<usize, Cell>> = (0..rows)
+            .map(|row| self.cells.remove(&row).unwrap_or_default())
+            .collect();
+        sorted.sort_by(|a, b| {
+            let empty = CellValue::Empty;
+            let a = a.get(&col).map_or(&empty, |c| &c.value);
+            let b = b.get(&col).map_or(&empty, |c| &c.value);
+            match (a.is_empty(), b.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) if descending => b.sort_cmp(a),
+                (false, false) => a.sort_cmp(b),
+            }
+        });
+        for (row, cells) in sorted.into_iter().enumerate() {
+            if !cells.is_empty() {
+                self.cells.insert(row, cells);
+            }
+        }
+    }
+    pub fn is_filled(&self, row: usize, col: usize) -> bool {
+        self.cells
+            .get(&row)
+            .and_then(|r| r.get(&col))
+            .is_some_and(|c| !c.raw.is_empty())
+    }
+    /// The rectangle of filled cells connected to `cell`, including diagonally, as
+    /// inclusive corners; like a spreadsheet's "current region".
+    pub fn data_region(&self, cell: (usize, usize)) -> ((usize, usize), (usize, usize)) {
+        let (mut from, mut to) = (cell, cell);
+        let any_filled = |rows: (usize, usize), cols: (usize, usize)| {
+            (rows.0..=rows.1).any(|r| (cols.0..=cols.1).any(|c| self.is_filled(r, c)))
+        };
+        loop {
+            let (rows, cols) = self.extent();
+            let span_rows = (from.0.saturating_sub(1), to.0 + 1);
+            let span_cols = (from.1.saturating_sub(1), to.1 + 1);
+            let mut grown = false;
+            if from.0 > 0 && any_filled((from.0 - 1, from.0 - 1), span_cols) {
+                from.0 -= 1;
+                grown = true;
+            }
+            if to.0 + 1 < rows && any_filled((to.0 + 1, to.0 + 1), span_cols) {
+                to.0 += 1;
+                grown = true;
+            }
+            if from.1 > 0 && any_filled(span_rows, (from.1 - 1, from.1 - 1)) {
+                from.1 -= 1;
+                grown = true;
+            }
+            if to.1 + 1 < cols && any_filled(span_rows, (to.1 + 1, to.1 + 1)) {
+                to.1 += 1;
+                grown = true;
+            }
+            if !grown {
+                return (from, to);
+            }
+        }
+    }
+    /// The raw contents of the cells between two inclusive corners, one line per row
+    /// with cells separated by tabs.
+    pub fn range_text(&self, from: (usize, usize), to: (usize, usize)) -> Vec<String> {
+        (from.0..=to.0)
+            .map(|row| {
+                (from.1..=to.1)
+                    .map(|col| {
+                        self.cells
+                            .get(&row)
+                            .and_then(|r| r.get(&col))
+                            .map_or("", |c| c.raw.as_str())
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\t")
+            })
+            .collect()
+    }
+    /// Sets a cell's raw contents; an empty string removes the cell.
+    pub fn set_raw(&mut self, row: usize, col: usize, raw: &str) {
+        if raw.is_empty() {
+            self.clear_range((row, col), (row, col));
+            return;
+        }
+        self.cells.entry(row).or_default().insert(
+            col,
+            Cell {
+                raw: raw.to_string(),
+                value: CellValue::parse_from_str(raw),
+                ast: None,
+                dependencies: HashSet::new(),
+                used_by: HashSet::new(),
+            },
+        );
+    }
+    /// Empties the cells between two inclusive corners.
+    pub fn clear_range(&mut self, from: (usize, usize), to: (usize, usize)) {
+        for row in from.0..=to.0 {
+            if let Some(cells) = self.cells.get_mut(&row) {
+                cells.retain(|col, _| !(from.1..=to.1).contains(col));
+                if cells.is_empty() {
+                    self.cells.remove(&row);
+                }
+            }
+        }
+    }
+}
+
+/// Reads an A1-style reference such as `B12` as a zero-based `(row, col)`.
+pub fn parse_cell_ref(reference: &str) -> Option<(usize, usize)> {
+    let split = reference.find(|c: char| c.is_ascii_digit())?;
+    let (letters, digits) = reference.split_at(split);
+    if letters.is_empty() || !letters.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let col = letters
+        .chars()
+        .try_fold(0usize, |acc, c| {
+            acc.checked_mul(26)?
+                .checked_add((c.to_ascii_uppercase() as u8 - b'A') as usize + 1)
+        })?;
+    let row: usize = digits.parse().ok()?;
+    Some((row.checked_sub(1)?, col - 1))
+}
 impl InsertModeProvider for SpreadSheetDocumentData {
     fn handle_key(
         &mut self,
         window: &mut WindowState,
         key: crate::commands::Key,
-    ) -> Result<(), String> {
-        Ok(())
+    ) -> Result<Option<DocRange>, ApiError> {
+        Ok(None)
     }
 }
 pub type CellId = String;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct Cell {
     pub raw: String,
     pub value: CellValue,
@@ -107,7 +300,7 @@ pub struct Cell {
     pub used_by: HashSet<CellId>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub enum CellValue {
     Empty,
     Number(f64),
@@ -116,6 +309,28 @@ pub enum CellValue {
 }
 
 impl CellValue {
+    pub fn is_empty(&self) -> bool {
+        matches!(self, CellValue::Empty)
+    }
+    /// Sort order: numbers, then text ignoring case, then errors, then empty cells.
+    pub fn sort_cmp(&self, other: &Self) -> Ordering {
+        fn rank(value: &CellValue) -> u8 {
+            match value {
+                CellValue::Number(_) => 0,
+                CellValue::Text(_) => 1,
+                CellValue::Error(_) => 2,
+                CellValue::Empty => 3,
+            }
+        }
+        match (self, other) {
+            (CellValue::Number(a), CellValue::Number(b)) => a.total_cmp(b),
+            (CellValue::Text(a), CellValue::Text(b))
+            | (CellValue::Error(a), CellValue::Error(b)) => {
+                a.to_lowercase().cmp(&b.to_lowercase())
+            }
+            _ => rank(self).cmp(&rank(other)),
+        }
+    }
     pub fn parse_from_str(s: &str) -> Self {
         let trimmed = s.trim();
 
@@ -131,5 +346,5 @@ impl CellValue {
         }
     }
 }
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct Expr {}
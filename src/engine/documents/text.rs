@@ -1,20 +1,25 @@
 use std::collections::btree_map::Range;
 
+use schemars::JsonSchema;
 use serde::Serialize;
 
 use crate::{
+    api::ApiError,
     commands::KeyCode,
     engine::{
         WindowState,
-        document::DocumentData,
+        document::DocRange,
         documents::{DocumentDataProvider, InsertModeProvider},
+        motion::line_len,
+        selection::Selection,
     },
 };
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct TextDocumentData {
     pub data: Vec<String>,
-    pub selected: Option<((usize, usize), (usize, usize))>,
+    /// The visual selection, from its anchor to the window's cursor.
+    pub selected: Option<Selection>,
 }
 impl DocumentDataProvider for TextDocumentData {
     fn new() -> Self {
@@ -31,59 +36,185 @@ impl DocumentDataProvider for TextDocumentData {
             selected: None,
         })
     }
-    fn from_file(path: &str) -> Result<Self, String>
+    fn to_raw(&self) -> Result<String, String> {
+        let mut content = self.data.join("\n");
+        content.push('\n');
+        Ok(content)
+    }
+    fn from_file(path: &str) -> Result<Self, ApiError>
         where
             Self: Sized {
-        Err("not implimented".to_string())
+        let content = std::fs::read_to_string(path)?;
+        Ok(Self::from_raw(&content)?)
     }
 }
+impl TextDocumentData {
+    pub fn sort_lines(&mut self, descending: bool) {
+        self.data.sort();
+        if descending {
+            self.data.reverse();
+        }
+    }
+    /// The text from `start` up to `end`, split at line breaks. Positions count
+    /// characters, and an end at a line's length takes the whole line without its break.
+    pub fn text_range(&self, start: (usize, usize), end: (usize, usize)) -> Vec<String> {
+        (start.0..=end.0)
+            .map(|row| {
+                let line = self.data.get(row).map_or("", String::as_str);
+                let from = if row == start.0 { start.1 } else { 0 };
+                let to = if row == end.0 { end.1 } else { usize::MAX };
+                line.chars()
+                    .skip(from)
+                    .take(to.saturating_sub(from))
+                    .collect()
+            })
+            .collect()
+    }
+    /// Removes the text from `start` up to `end`, joining the lines at either side.
+    pub fn delete_range(&mut self, start: (usize, usize), end: (usize, usize)) {
+        if start.0 >= self.data.len() {
+            return;
+        }
+        let end_row = end.0.min(self.data.len() - 1);
+        let tail = self.data[end_row][byte_index(&self.data[end_row], end.1)..].to_string();
+        let line = &mut self.data[start.0];
+        line.truncate(byte_index(line, start.1));
+        line.push_str(&tail);
+        self.data.drain(start.0 + 1..=end_row);
+    }
+    /// Inserts text split at line breaks at `pos` and returns the position just after it.
+    pub fn insert_text(&mut self, pos: (usize, usize), text: &[String]) -> (usize, usize) {
+        if self.data.is_empty() {
+            self.data.push(String::new());
+        }
+        let row = pos.0.min(self.data.len() - 1);
+        let line = &mut self.data[row];
+        let tail = line.split_off(byte_index(line, pos.1));
+        let mut lines = text.iter();
+        line.push_str(lines.next().map_or("", String::as_str));
+        let mut rest: Vec<String> = lines.cloned().collect();
+        let height = rest.len();
+        let end = match rest.last_mut() {
+            Some(last) => {
+                let end = (row + height, last.chars().count());
+                last.push_str(&tail);
+                end
+            }
+            None => {
+                let end = (row, line.chars().count());
+                line.push_str(&tail);
+                end
+            }
+        };
+        self.data.splice(row + 1..row + 1, rest);
+        end
+    }
+    /// Whole lines `first..=last`, clamped to the document.
+    pub fn line_range(&self, first: usize, last: usize) -> Vec<String> {
+        self.data
+            .iter()
+            .skip(first)
+            .take(last + 1 - first)
+            .cloned()
+            .collect()
+    }
+    /// Removes lines `first..=last`, leaving one empty line if nothing else remains.
+    pub fn delete_lines(&mut self, first: usize, last: usize) {
+        if first < self.data.len() {
+            self.data.drain(first..=last.min(self.data.len() - 1));
+        }
+        if self.data.is_empty() {
+            self.data.push(String::new());
+        }
+    }
+    /// Shifts lines `first..=last` right by `width` spaces, or left when `width` is
+    /// negative, by as much leading whitespace as they have. Empty lines stay empty.
+    pub fn indent_lines(&mut self, first: usize, last: usize, width: isize) {
+        for line in self.data.iter_mut().skip(first).take(last + 1 - first) {
+            if width > 0 && !line.is_empty() {
+                line.insert_str(0, &" ".repeat(width as usize));
+            } else if width < 0 {
+                let blank = line
+                    .chars()
+                    .take(width.unsigned_abs())
+                    .take_while(|c| *c == ' ' || *c == '\t')
+                    .count();
+                line.drain(..blank);
+            }
+        }
+    }
+    /// Column of the first character on `row` that is not whitespace.
+    pub fn first_non_blank(&self, row: usize) -> usize {
+        self.data.get(row).map_or(0, |line| {
+            let blank = line.chars().take_while(|c| c.is_whitespace()).count();
+            blank.min(line.chars().count().saturating_sub(1))
+        })
+    }
+}
+
+/// Byte offset of the character at `col`, or the line's length past its end.
+fn byte_index(line: &str, col: usize) -> usize {
+    line.char_indices().nth(col).map_or(line.len(), |(i, _)| i)
+}
 impl InsertModeProvider for TextDocumentData {
     fn handle_key(
         &mut self,
         window: &mut WindowState,
         key: crate::commands::Key,
-    ) -> Result<(), String> {
+    ) -> Result<Option<DocRange>, ApiError> {
         // Make sure it's a TextDocument
         // Shortcut to cursor state
         let cursor_row = &mut window.cursor_row;
         let cursor_col = &mut window.cursor_col;
         let lines = &mut self.data;
 
-        match key.code {
+        let changed = match key.code {
             // Insert character
             KeyCode::Char(c) => {
                 if *cursor_row >= lines.len() {
                     lines.push(String::new());
                 }
                 let line = &mut lines[*cursor_row];
-                if *cursor_col > line.len() {
-                    *cursor_col = line.len();
-                }
-                line.insert(*cursor_col, c);
+                *cursor_col = (*cursor_col).min(line.chars().count());
+                line.insert(byte_index(line, *cursor_col), c);
                 *cursor_col += 1;
+                Some(((*cursor_row, *cursor_col - 1), (*cursor_row, *cursor_col)))
             }
 
             // New line
             KeyCode::Enter => {
+                if *cursor_row >= lines.len() {
+                    lines.push(String::new());
+                }
                 let line = &mut lines[*cursor_row];
-                let remainder = line.split_off(*cursor_col);
+                let remainder = line.split_off(byte_index(line, *cursor_col));
                 lines.insert(*cursor_row + 1, remainder);
                 *cursor_row += 1;
                 *cursor_col = 0;
+                Some((
+                    (*cursor_row - 1, lines[*cursor_row - 1].chars().count()),
+                    (*cursor_row, 0),
+                ))
             }
 
             // Backspace
             KeyCode::Backspace => {
+                let len = lines.get(*cursor_row).map_or(0, |line| line.chars().count());
+                *cursor_col = (*cursor_col).min(len);
                 if *cursor_col > 0 {
                     let line = &mut lines[*cursor_row];
-                    line.remove(*cursor_col - 1);
+                    line.remove(byte_index(line, *cursor_col - 1));
                     *cursor_col -= 1;
+                    Some(((*cursor_row, *cursor_col), (*cursor_row, *cursor_col + 1)))
                 } else if *cursor_row > 0 {
                     let current = lines.remove(*cursor_row);
                     *cursor_row -= 1;
                     let prev = &mut lines[*cursor_row];
-                    *cursor_col = prev.len();
+                    *cursor_col = prev.chars().count();
                     prev.push_str(&current);
+                    Some(((*cursor_row, *cursor_col), (*cursor_row + 1, 0)))
+                } else {
+                    None
                 }
             }
 
@@ -93,35 +224,39 @@ impl InsertModeProvider for TextDocumentData {
                     *cursor_col -= 1;
                 } else if *cursor_row > 0 {
                     *cursor_row -= 1;
-                    *cursor_col = lines[*cursor_row].len();
+                    *cursor_col = lines[*cursor_row].chars().count();
                 }
+                None
             }
 
             KeyCode::Right => {
-                if *cursor_col < lines[*cursor_row].len() {
+                if *cursor_col < line_len(lines, *cursor_row) {
                     *cursor_col += 1;
                 } else if *cursor_row + 1 < lines.len() {
                     *cursor_row += 1;
                     *cursor_col = 0;
                 }
+                None
             }
 
             KeyCode::Up => {
                 if *cursor_row > 0 {
                     *cursor_row -= 1;
-                    *cursor_col = (*cursor_col).min(lines[*cursor_row].len());
+                    *cursor_col = (*cursor_col).min(line_len(lines, *cursor_row));
                 }
+                None
             }
 
             KeyCode::Down => {
                 if *cursor_row + 1 < lines.len() {
                     *cursor_row += 1;
-                    *cursor_col = (*cursor_col).min(lines[*cursor_row].len());
+                    *cursor_col = (*cursor_col).min(line_len(lines, *cursor_row));
                 }
+                None
             }
 
-            _ => {}
+            _ => None,
         };
-        Ok(())
+        Ok(changed)
     }
 }
@@ -5,7 +5,7 @@ use crate::{
     render::Rect,
 };
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum SplitDir {
     Vert,
     Horz,
@@ -21,97 +21,242 @@ pub enum LayoutNode {
     },
 }
 
+/// The fewest rows or columns `resize` leaves either side of a split, enough for a
+/// window's border and a line inside it.
+const MIN_WINDOW_SIZE: usize = 3;
+
 impl LayoutNode {
+    /// The window across the nearest split line on the `dir` side of `win_id`, picking
+    /// the one closest to that line when the other side is split further.
     pub fn get_neighbor(&self, win_id: WindowId, dir: SplitDirection) -> Option<WindowId> {
-        let mut candidate: Option<&LayoutNode> = None;
-        let mut next: Option<&LayoutNode> = Some(self);
-
-        while let Some(item) = next {
-            match item {
-                LayoutNode::Leaf(id) => {
-                    break;
-                }
-                Self::Split {
-                    direction,
-                    ratio,
-                    first,
-                    second,
-                } => {
-                    match dir {
-                        SplitDirection::Up => {
-                            if matches!(direction, SplitDir::Vert) && second.contains(&win_id) {
-                                candidate = Some(first);
-                                next = Some(second);
-                            } else {
-                                next = None;
-                            }
-                        }
-                        SplitDirection::Down => {
-                            if matches!(direction, SplitDir::Vert) && first.contains(&win_id) {
-                                candidate = Some(second);
-                                next = Some(first)
-                            } else {
-                                next = None;
-                            }
-                        }
-                        SplitDirection::Left => {
-                            if matches!(direction, SplitDir::Horz) && second.contains(&win_id) {
-                                candidate = Some(first);
-                                next = Some(second);
-                            } else {
-                                next = None;
-                            }
-                        }
-                        SplitDirection::Right => {
-                            if matches!(direction, SplitDir::Vert) && first.contains(&win_id) {
-                                candidate = Some(second);
-                                next = Some(first);
-                            } else {
-                                next = None;
-                            }
-                        }
-                    };
-                }
+        let path = self.path_to(&win_id)?;
+        let (orientation, far) = edge(&dir);
+        let mut node = self;
+        let mut candidate = None;
+        for &in_second in &path {
+            let LayoutNode::Split {
+                direction,
+                first,
+                second,
+                ..
+            } = node
+            else {
+                break;
             };
+            if *direction == orientation && in_second == far {
+                candidate = Some(if far { first } else { second });
+            }
+            node = if in_second { second } else { first };
         }
 
-        while let Some(item) = candidate {
-            match item {
-                LayoutNode::Leaf(id) => {
-                    // found the neighbor
-                    return Some(id.clone());
-                }
-
+        let mut node = candidate?;
+        loop {
+            match node.as_ref() {
+                LayoutNode::Leaf(id) => return Some(id.clone()),
                 LayoutNode::Split {
                     direction,
                     first,
                     second,
                     ..
                 } => {
-                    candidate = match dir {
-                        SplitDirection::Up => {
-                            if matches!(direction, SplitDir::Vert) {
-                                Some(second.as_ref())
-                            } else {
-                                Some(first.as_ref())
-                            }
-                        }
-                        SplitDirection::Down => Some(first.as_ref()),
-                        SplitDirection::Left => {
-                            if matches!(direction, SplitDir::Vert) {
-                                Some(second.as_ref())
-                            } else {
-                                Some(first.as_ref())
-                            }
-                        }
-                        SplitDirection::Right => Some(first.as_ref()),
+                    node = if *direction == orientation && far {
+                        second
+                    } else {
+                        first
                     };
                 }
             }
         }
-        None
     }
-    fn contains(&self, target: &WindowId) -> bool {
+    /// Moves the edge of `target` on the `dir` side by `cells` rows or columns of the
+    /// layout drawn in `rect`, outwards to grow the window and inwards if negative.
+    /// Returns false if no split line runs along that edge, or the space it divides
+    /// is too small to move it.
+    pub fn resize(
+        &mut self,
+        rect: &Rect,
+        target: &WindowId,
+        dir: &SplitDirection,
+        cells: isize,
+    ) -> bool {
+        let Some(path) = self.path_to(target) else {
+            return false;
+        };
+        let (orientation, far) = edge(dir);
+        // the innermost split whose line is that edge, and the space it divides
+        let mut found = None;
+        let mut node = &*self;
+        let mut rect = *rect;
+        for (depth, &in_second) in path.iter().enumerate() {
+            let LayoutNode::Split {
+                direction,
+                ratio,
+                first,
+                second,
+            } = node
+            else {
+                break;
+            };
+            if *direction == orientation && in_second == far {
+                found = Some((depth, rect));
+            }
+            let (rect_1, rect_2) = split_rect(&rect, direction, *ratio);
+            (node, rect) = if in_second {
+                (second, rect_2)
+            } else {
+                (first, rect_1)
+            };
+        }
+        let Some((depth, rect)) = found else {
+            return false;
+        };
+        let LayoutNode::Split {
+            direction, ratio, ..
+        } = self.node_at_mut(&path[..depth])
+        else {
+            return false;
+        };
+        let len = match direction {
+            SplitDir::Vert => rect.height,
+            SplitDir::Horz => rect.width,
+        };
+        if len < 2 * MIN_WINDOW_SIZE {
+            return false;
+        }
+        // the line moves towards the second side to grow a window on the first
+        let delta = if far { -cells } else { cells };
+        let split = ((len as f32 * *ratio) as isize + delta)
+            .clamp(MIN_WINDOW_SIZE as isize, (len - MIN_WINDOW_SIZE) as isize);
+        // halfway into the cell, so truncating in `split_rect` lands on it
+        *ratio = (split as f32 + 0.5) / len as f32;
+        true
+    }
+    /// Sets every split's ratio so the windows in each row and column share its space
+    /// equally, counting a window split the other way as one.
+    pub fn equalize(&mut self) {
+        self.equalize_counts();
+    }
+    /// Equalizes the splits below this node, returning how many windows it has across
+    /// and down.
+    fn equalize_counts(&mut self) -> (usize, usize) {
+        let LayoutNode::Split {
+            direction,
+            ratio,
+            first,
+            second,
+        } = self
+        else {
+            return (1, 1);
+        };
+        let (across_1, down_1) = first.equalize_counts();
+        let (across_2, down_2) = second.equalize_counts();
+        match direction {
+            SplitDir::Vert => {
+                *ratio = down_1 as f32 / (down_1 + down_2) as f32;
+                (across_1.max(across_2), down_1 + down_2)
+            }
+            SplitDir::Horz => {
+                *ratio = across_1 as f32 / (across_1 + across_2) as f32;
+                (across_1 + across_2, down_1.max(down_2))
+            }
+        }
+    }
+    /// Exchanges the places of two windows.
+    pub fn swap(&mut self, a: &WindowId, b: &WindowId) {
+        match self {
+            LayoutNode::Leaf(id) if id == a => *id = b.clone(),
+            LayoutNode::Leaf(id) if id == b => *id = a.clone(),
+            LayoutNode::Leaf(_) => {}
+            LayoutNode::Split { first, second, .. } => {
+                first.swap(a, b);
+                second.swap(a, b);
+            }
+        }
+    }
+    /// Moves every window in the row or column `target` is in one place down or right,
+    /// the last taking the first's place, or the other way if `reverse`. Windows split
+    /// the other way within it move together, and each place keeps its size.
+    pub fn rotate(&mut self, target: &WindowId, reverse: bool) {
+        let Some(path) = self.path_to(target) else {
+            return;
+        };
+        let mut directions = vec![];
+        let mut node = &*self;
+        for &in_second in &path {
+            let LayoutNode::Split {
+                direction,
+                first,
+                second,
+                ..
+            } = node
+            else {
+                break;
+            };
+            directions.push(direction.clone());
+            node = if in_second { second } else { first };
+        }
+        let Some(orientation) = directions.last() else {
+            return;
+        };
+        // the row or column reaches up from the window's own split while splits go its way
+        let top = directions
+            .iter()
+            .rposition(|direction| direction != orientation)
+            .map_or(0, |depth| depth + 1);
+        let orientation = orientation.clone();
+        let places = self.node_at_mut(&path[..top]).places(&orientation);
+        let mut nodes: Vec<LayoutNode> = places.iter().map(|place| (**place).clone()).collect();
+        if reverse {
+            nodes.rotate_left(1);
+        } else {
+            nodes.rotate_right(1);
+        }
+        for (place, node) in places.into_iter().zip(nodes) {
+            *place = node;
+        }
+    }
+    /// The nodes side by side in a row or column of splits going `orientation`.
+    fn places(&mut self, orientation: &SplitDir) -> Vec<&mut LayoutNode> {
+        if !matches!(self, LayoutNode::Split { direction, .. } if direction == orientation) {
+            return vec![self];
+        }
+        match self {
+            LayoutNode::Split { first, second, .. } => {
+                let mut places = first.places(orientation);
+                places.extend(second.places(orientation));
+                places
+            }
+            node => vec![node],
+        }
+    }
+    /// Which side of each split on the way down to `target` it is in, `true` for the
+    /// second.
+    fn path_to(&self, target: &WindowId) -> Option<Vec<bool>> {
+        match self {
+            LayoutNode::Leaf(id) => (id == target).then(Vec::new),
+            LayoutNode::Split { first, second, .. } => {
+                let (in_second, mut path) = match first.path_to(target) {
+                    Some(path) => (false, path),
+                    None => (true, second.path_to(target)?),
+                };
+                path.insert(0, in_second);
+                Some(path)
+            }
+        }
+    }
+    fn node_at_mut(&mut self, path: &[bool]) -> &mut LayoutNode {
+        match (self, path.split_first()) {
+            (LayoutNode::Split { first, second, .. }, Some((&in_second, rest))) => {
+                if in_second {
+                    second.node_at_mut(rest)
+                } else {
+                    first.node_at_mut(rest)
+                }
+            }
+            (node, _) => node,
+        }
+    }
+    pub fn contains(&self, target: &WindowId) -> bool {
         match self {
             LayoutNode::Leaf(id) => id == target,
             LayoutNode::Split { first, second, .. } => {
@@ -169,42 +314,7 @@ impl LayoutNode {
                 first,
                 second,
             } => {
-                let (rect_1, rect_2) = match direction {
-                    SplitDir::Vert => {
-                        let split_value = (rect.height as f32 * ratio) as usize;
-                        (
-                            Rect {
-                                x: rect.x,
-                                y: rect.y,
-                                width: rect.width,
-                                height: split_value,
-                            },
-                            Rect {
-                                x: rect.x,
-                                y: rect.y + split_value,
-                                width: rect.width,
-                                height: rect.height - split_value,
-                            },
-                        )
-                    }
-                    SplitDir::Horz => {
-                        let split_value = (rect.width as f32 * ratio) as usize;
-                        (
-                            Rect {
-                                x: rect.x,
-                                y: rect.y,
-                                width: split_value,
-                                height: rect.height,
-                            },
-                            Rect {
-                                x: rect.x + split_value,
-                                y: rect.y,
-                                width: rect.width - split_value,
-                                height: rect.height,
-                            },
-                        )
-                    }
-                };
+                let (rect_1, rect_2) = split_rect(rect, direction, *ratio);
                 first.walk_nodes(&rect_1, f);
                 second.walk_nodes(&rect_2, f);
             }
@@ -239,3 +349,245 @@ impl LayoutNode {
         None
     }
 }
+
+/// The two parts a split going `direction` divides `rect` into.
+fn split_rect(rect: &Rect, direction: &SplitDir, ratio: f32) -> (Rect, Rect) {
+    match direction {
+        SplitDir::Vert => {
+            let split_value = (rect.height as f32 * ratio) as usize;
+            (
+                Rect {
+                    x: rect.x,
+                    y: rect.y,
+                    width: rect.width,
+                    height: split_value,
+                },
+                Rect {
+                    x: rect.x,
+                    y: rect.y + split_value,
+                    width: rect.width,
+                    height: rect.height - split_value,
+                },
+            )
+        }
+        SplitDir::Horz => {
+            let split_value = (rect.width as f32 * ratio) as usize;
+            (
+                Rect {
+                    x: rect.x,
+                    y: rect.y,
+                    width: split_value,
+                    height: rect.height,
+                },
+                Rect {
+                    x: rect.x + split_value,
+                    y: rect.y,
+                    width: rect.width - split_value,
+                    height: rect.height,
+                },
+            )
+        }
+    }
+}
+
+/// The way the split line along a window's edge on the `dir` side runs, and whether
+/// the window is on the second side of it.
+fn edge(dir: &SplitDirection) -> (SplitDir, bool) {
+    match dir {
+        SplitDirection::Up => (SplitDir::Vert, true),
+        SplitDirection::Down => (SplitDir::Vert, false),
+        SplitDirection::Left => (SplitDir::Horz, true),
+        SplitDirection::Right => (SplitDir::Horz, false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(id: &str) -> LayoutNode {
+        LayoutNode::Leaf(id.to_string())
+    }
+
+    fn split(direction: SplitDir, first: LayoutNode, second: LayoutNode) -> LayoutNode {
+        LayoutNode::Split {
+            direction,
+            ratio: 0.5,
+            first: Box::new(first),
+            second: Box::new(second),
+        }
+    }
+
+    fn screen(width: usize, height: usize) -> Rect {
+        Rect {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+
+    /// Each window with its `(x, y, width, height)`, in layout order.
+    fn places(layout: &LayoutNode, rect: &Rect) -> Vec<(String, (usize, usize, usize, usize))> {
+        layout
+            .get_rects(rect)
+            .into_iter()
+            .map(|(id, r)| (id, (r.x, r.y, r.width, r.height)))
+            .collect()
+    }
+
+    fn ids(layout: &LayoutNode) -> Vec<String> {
+        places(layout, &screen(80, 20))
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    #[test]
+    fn resize_moves_the_split_on_that_edge() {
+        let rect = screen(80, 20);
+        let mut layout = split(SplitDir::Horz, leaf("a"), leaf("b"));
+        assert!(layout.resize(&rect, &"a".to_string(), &SplitDirection::Right, 5));
+        assert_eq!(places(&layout, &rect)[0].1, (0, 0, 45, 20));
+        assert!(layout.resize(&rect, &"b".to_string(), &SplitDirection::Left, 10));
+        assert_eq!(places(&layout, &rect)[1].1, (35, 0, 45, 20));
+        assert!(layout.resize(&rect, &"a".to_string(), &SplitDirection::Right, -1));
+        assert_eq!(places(&layout, &rect)[0].1, (0, 0, 34, 20));
+    }
+
+    #[test]
+    fn resize_needs_a_split_along_the_edge() {
+        let rect = screen(80, 20);
+        let mut layout = split(SplitDir::Horz, leaf("a"), leaf("b"));
+        assert!(!layout.resize(&rect, &"a".to_string(), &SplitDirection::Left, 1));
+        assert!(!layout.resize(&rect, &"a".to_string(), &SplitDirection::Up, 1));
+        assert!(!layout.resize(&rect, &"c".to_string(), &SplitDirection::Right, 1));
+        assert!(!leaf("a").resize(&rect, &"a".to_string(), &SplitDirection::Right, 1));
+    }
+
+    #[test]
+    fn resize_keeps_windows_their_minimum_size() {
+        let rect = screen(80, 20);
+        let mut layout = split(SplitDir::Horz, leaf("a"), leaf("b"));
+        assert!(layout.resize(&rect, &"a".to_string(), &SplitDirection::Right, 100));
+        assert_eq!(places(&layout, &rect)[1].1, (77, 0, 3, 20));
+
+        // too narrow to leave either side its minimum, so nothing moves
+        let narrow = screen(5, 20);
+        let mut layout = split(SplitDir::Horz, leaf("a"), leaf("b"));
+        assert!(!layout.resize(&narrow, &"a".to_string(), &SplitDirection::Right, 1));
+        assert_eq!(places(&layout, &narrow)[0].1, (0, 0, 2, 20));
+    }
+
+    #[test]
+    fn resize_finds_the_innermost_split_on_the_edge() {
+        let rect = screen(80, 20);
+        let mut layout = split(
+            SplitDir::Horz,
+            leaf("a"),
+            split(SplitDir::Vert, leaf("b"), leaf("c")),
+        );
+        assert!(layout.resize(&rect, &"c".to_string(), &SplitDirection::Up, 2));
+        assert_eq!(places(&layout, &rect)[2].1, (40, 8, 40, 12));
+        assert!(layout.resize(&rect, &"c".to_string(), &SplitDirection::Left, 3));
+        assert_eq!(places(&layout, &rect)[1].1, (37, 0, 43, 8));
+    }
+
+    #[test]
+    fn equalize_counts_windows_across_and_down() {
+        let rect = screen(90, 20);
+        let mut layout = split(
+            SplitDir::Horz,
+            leaf("a"),
+            split(SplitDir::Horz, leaf("b"), leaf("c")),
+        );
+        layout.equalize();
+        let widths: Vec<usize> = places(&layout, &rect).iter().map(|(_, r)| r.2).collect();
+        assert_eq!(widths, [30, 30, 30]);
+
+        // a column split the other way counts as one window across
+        let mut layout = split(
+            SplitDir::Horz,
+            leaf("a"),
+            split(SplitDir::Vert, leaf("b"), leaf("c")),
+        );
+        assert!(layout.resize(&rect, &"a".to_string(), &SplitDirection::Right, 20));
+        layout.equalize();
+        let sizes: Vec<_> = places(&layout, &rect).into_iter().map(|(_, r)| r).collect();
+        assert_eq!(sizes, [(0, 0, 45, 20), (45, 0, 45, 10), (45, 10, 45, 10)]);
+    }
+
+    #[test]
+    fn swap_exchanges_two_windows() {
+        let mut layout = split(
+            SplitDir::Horz,
+            leaf("a"),
+            split(SplitDir::Vert, leaf("b"), leaf("c")),
+        );
+        layout.swap(&"a".to_string(), &"c".to_string());
+        assert_eq!(ids(&layout), ["c", "b", "a"]);
+    }
+
+    #[test]
+    fn rotate_turns_the_windows_of_a_row() {
+        let row = || {
+            split(
+                SplitDir::Horz,
+                leaf("a"),
+                split(SplitDir::Horz, leaf("b"), leaf("c")),
+            )
+        };
+        let mut layout = row();
+        layout.rotate(&"b".to_string(), false);
+        assert_eq!(ids(&layout), ["c", "a", "b"]);
+        let mut layout = row();
+        layout.rotate(&"b".to_string(), true);
+        assert_eq!(ids(&layout), ["b", "c", "a"]);
+    }
+
+    #[test]
+    fn rotate_moves_a_column_split_the_other_way_as_one() {
+        let layout = || {
+            split(
+                SplitDir::Horz,
+                leaf("a"),
+                split(SplitDir::Vert, leaf("b"), leaf("c")),
+            )
+        };
+        let mut column = layout();
+        column.rotate(&"b".to_string(), false);
+        assert_eq!(ids(&column), ["a", "c", "b"]);
+        let mut row = layout();
+        row.rotate(&"a".to_string(), false);
+        assert_eq!(ids(&row), ["b", "c", "a"]);
+        let rect = screen(80, 20);
+        assert_eq!(places(&row, &rect)[2].1, (40, 0, 40, 20));
+    }
+
+    #[test]
+    fn neighbors_are_across_the_nearest_split() {
+        let layout = split(
+            SplitDir::Horz,
+            leaf("a"),
+            split(SplitDir::Vert, leaf("b"), leaf("c")),
+        );
+        let neighbor = |id: &str, dir| layout.get_neighbor(id.to_string(), dir);
+        assert_eq!(neighbor("a", SplitDirection::Right).as_deref(), Some("b"));
+        assert_eq!(neighbor("c", SplitDirection::Left).as_deref(), Some("a"));
+        assert_eq!(neighbor("b", SplitDirection::Down).as_deref(), Some("c"));
+        assert_eq!(neighbor("a", SplitDirection::Left), None);
+    }
+
+    #[test]
+    fn removing_a_window_collapses_its_split() {
+        let layout = split(
+            SplitDir::Horz,
+            leaf("a"),
+            split(SplitDir::Vert, leaf("b"), leaf("c")),
+        );
+        let layout = layout.remove_window(&"b".to_string()).unwrap();
+        assert_eq!(ids(&layout), ["a", "c"]);
+        assert!(!layout.contains(&"b".to_string()));
+        assert!(leaf("a").remove_window(&"a".to_string()).is_none());
+    }
+}
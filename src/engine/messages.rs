@@ -0,0 +1,114 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// How many messages `:messages` keeps.
+const HISTORY_LIMIT: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageLevel {
+    Info,
+    Warn,
+    Error,
+}
+impl MessageLevel {
+    pub fn name(self) -> &'static str {
+        match self {
+            MessageLevel::Info => "info",
+            MessageLevel::Warn => "warn",
+            MessageLevel::Error => "error",
+        }
+    }
+    fn default_timeout(self) -> Duration {
+        match self {
+            MessageLevel::Info | MessageLevel::Warn => Duration::from_secs(4),
+            MessageLevel::Error => Duration::from_secs(8),
+        }
+    }
+}
+
+/// A line for the footer's message area, and optionally a notification popup.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Message {
+    pub level: MessageLevel,
+    pub text: String,
+    /// Seconds since the Unix epoch when the message was posted.
+    pub time: u64,
+    pub popup: bool,
+    #[serde(skip)]
+    timeout: Duration,
+    #[serde(skip)]
+    posted: Option<Instant>,
+    #[serde(skip)]
+    expired: bool,
+}
+impl Message {
+    pub fn new(level: MessageLevel, text: impl Into<String>) -> Self {
+        Self {
+            level,
+            text: text.into(),
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            popup: false,
+            timeout: level.default_timeout(),
+            posted: None,
+            expired: false,
+        }
+    }
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    /// Also shows the message in the notification stack at the top right.
+    pub fn popup(mut self, popup: bool) -> Self {
+        self.popup = popup;
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct Messages {
+    history: VecDeque<Message>,
+}
+impl Messages {
+    pub fn push(&mut self, mut message: Message) {
+        message.posted = Some(Instant::now());
+        if self.history.len() == HISTORY_LIMIT {
+            self.history.pop_front();
+        }
+        self.history.push_back(message);
+    }
+    /// Every message still kept, oldest first.
+    pub fn history(&self) -> impl Iterator<Item = &Message> {
+        self.history.iter()
+    }
+    /// The newest message that has not timed out, for the footer.
+    pub fn current(&self) -> Option<&Message> {
+        self.history.iter().rev().find(|m| !m.expired)
+    }
+    /// Popup messages that have not timed out, newest first.
+    pub fn popups(&self) -> impl Iterator<Item = &Message> {
+        self.history.iter().rev().filter(|m| m.popup && !m.expired)
+    }
+    /// Marks timed out messages, returning true if any disappeared from the screen.
+    pub fn expire(&mut self) -> bool {
+        let now = Instant::now();
+        let mut changed = false;
+        for message in self.history.iter_mut().filter(|m| !m.expired) {
+            if message
+                .posted
+                .is_some_and(|posted| now >= posted + message.timeout)
+            {
+                message.expired = true;
+                changed = true;
+            }
+        }
+        changed
+    }
+}
@@ -1,23 +1,37 @@
-use std::{collections::HashMap, vec};
+use std::{collections::HashMap, path::Path, time::Duration, vec};
 pub mod document;
 pub mod documents;
 pub mod layout;
+pub mod messages;
+pub mod motion;
 pub mod parse;
 pub mod popup;
+pub mod registers;
+pub mod selection;
+pub mod text_object;
 use crate::{
+    api::ApiError,
     commands::{Key, command_dispatcher::CommandRequest},
     config::Config,
     engine::{
-        document::{DocId, Document, DocumentData},
-        documents::{DocumentDataProvider, text::TextDocumentData},
+        document::{DocId, DocRange, DocType, Document, DocumentData},
+        documents::{
+            DocumentDataProvider, spreadsheet::SpreadSheetDocumentData, text::TextDocumentData,
+        },
         layout::LayoutNode,
+        messages::{Message, Messages},
         popup::PopupWindow,
+        registers::Registers,
     },
-    input::keymaps::{ActionNode, KeymapProvider},
-    render::{Rect, helpers::BorderStyle},
+    input::{
+        input_engine::ModeType,
+        keymaps::{ActionNode, KeymapProvider},
+    },
+    render::{HEADLESS_SIZE, Rect, helpers::BorderStyle},
 };
-use crossterm::event::Event;
+use crossterm::event::{Event, MouseEvent, MouseEventKind};
 use crossterm::terminal;
+use schemars::JsonSchema;
 use serde::{self, Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -25,17 +39,27 @@ pub type EngineEventCallback = Box<dyn FnMut(&mut Engine, &EngineEvent)>;
 
 pub struct Engine {
     pub events: Vec<EngineEvent>,
+    /// Events waiting to be delivered to plugin subscribers by the command dispatcher.
+    pub notifications: Vec<EngineEvent>,
     pub docs: HashMap<DocId, Document>,
 
     pub windows: HashMap<WindowId, WindowState>,
     pub active_window: WindowId,
 
     pub layout: Option<LayoutNode>,
+    /// The whole layout, put aside while `layout` shows one window zoomed to fill it.
+    pub zoomed: Option<LayoutNode>,
     pub popups: Option<PopupWindow>,
     pub config: Config,
+    pub messages: Messages,
+    pub registers: Registers,
 
     pub keymap: Option<ActionNode>,
+    /// Bindings for every document of a type, layered below each document's own.
+    pub doc_type_keymaps: HashMap<DocType, ActionNode>,
     pub should_quit: bool,
+    /// Set by `request_quit` and cleared again if a `BeforeQuit` subscriber vetoes it.
+    pub quit_requested: bool,
 
     subscriptions: HashMap<EngineEventKind, Vec<EngineEventCallback>>,
 }
@@ -48,15 +72,21 @@ impl Engine {
         let (win_id, win) = WindowState::new(doc_id.clone());
         Self {
             events: vec![EngineEvent::WindowCreate(win_id.clone())],
+            notifications: vec![],
             windows: HashMap::from([(win_id.clone(), win)]),
             popups: None,
+            messages: Messages::default(),
+            registers: Registers::default(),
             keymap: None,
+            doc_type_keymaps: HashMap::new(),
             should_quit: false,
+            quit_requested: false,
             active_window: win_id.clone(),
             config,
             subscriptions: HashMap::new(),
             docs: HashMap::from([(doc_id, doc)]),
             layout: Some(LayoutNode::Leaf(win_id)),
+            zoomed: None,
         }
     }
 
@@ -64,6 +94,13 @@ impl Engine {
         self.subscriptions.entry(event).or_default().push(func);
     }
     pub fn emit(&mut self, event: &EngineEvent) {
+        // only pastes are recorded for undo, so the positions they kept are stale once
+        // anything else changes the document
+        if let EngineEvent::DocumentChanged(doc_id, _) = event
+            && let Some(doc) = self.docs.get_mut(doc_id)
+        {
+            doc.undo_stack.clear();
+        }
         let kind = event.kind();
         let mut subs = self.subscriptions.remove(&kind).unwrap_or_default();
 
@@ -72,6 +109,145 @@ impl Engine {
         }
         self.subscriptions.insert(kind, subs);
         self.events.push(event.clone());
+        self.notifications.push(event.clone());
+    }
+    /// Shows a message in the footer and keeps it for `:messages`.
+    pub fn notify(&mut self, message: Message) {
+        self.messages.push(message.clone());
+        self.emit(&EngineEvent::Message(message));
+    }
+    pub fn request_quit(&mut self) {
+        self.quit_requested = true;
+        self.emit(&EngineEvent::BeforeQuit);
+    }
+    pub fn veto_quit(&mut self) {
+        self.quit_requested = false;
+    }
+    /// Quits if a requested quit survived its `BeforeQuit` subscribers.
+    pub fn confirm_quit(&mut self) {
+        if self.quit_requested {
+            self.should_quit = true;
+        }
+    }
+    /// Zooms a window to fill the screen, or puts the layout back if one is zoomed.
+    pub fn toggle_zoom(&mut self, win_id: &WindowId) -> Result<(), ApiError> {
+        if self.zoomed.is_some() {
+            self.unzoom();
+        } else if self.layout.as_ref().is_some_and(|l| l.contains(win_id)) {
+            self.zoomed = self.layout.replace(LayoutNode::Leaf(win_id.clone()));
+        } else {
+            return Err(ApiError::not_found(format!(
+                "Window `{}` not found in layout",
+                win_id
+            )));
+        }
+        self.emit(&EngineEvent::LayoutChange);
+        Ok(())
+    }
+    /// Puts back the layout a zoomed window hides, which anything changing the layout
+    /// does first so that no window is lost from it.
+    pub fn unzoom(&mut self) {
+        if let Some(layout) = self.zoomed.take() {
+            self.layout = Some(layout);
+            self.emit(&EngineEvent::LayoutChange);
+        }
+    }
+    pub fn close_window(&mut self, win_id: &WindowId) -> Result<(), ApiError> {
+        self.unzoom();
+        if self
+            .popups
+            .as_ref()
+            .is_some_and(|popup| popup.layout.contains(win_id))
+        {
+            self.popups = None;
+        } else if let Some(old_layout) = std::mem::take(&mut self.layout) {
+            let new_layout = old_layout
+                .remove_window(win_id)
+                .ok_or_else(|| {
+                    ApiError::not_found(format!("Window `{}` not found in layout", win_id))
+                })?;
+
+            self.layout = Some(new_layout);
+        }
+        self.windows.remove(win_id);
+
+        if &self.active_window == win_id {
+            self.active_window = self
+                .windows
+                .keys()
+                .next()
+                .cloned()
+                .ok_or_else(|| "No windows left after closing window".to_string())?;
+        }
+
+        self.emit(&EngineEvent::WindowClose(win_id.clone()));
+        Ok(())
+    }
+    /// Closes every window showing the document and drops it.
+    pub fn close_document(&mut self, doc_id: &DocId) -> Result<(), ApiError> {
+        if !self.docs.contains_key(doc_id) {
+            return Err(ApiError::not_found(format!(
+                "Document `{}` not found",
+                doc_id
+            )));
+        }
+        let wins: Vec<WindowId> = self
+            .windows
+            .values()
+            .filter(|w| &w.doc_id == doc_id)
+            .map(|w| w.id.clone())
+            .collect();
+        if wins.len() == self.windows.len() {
+            return Err("Cannot close the document shown in every window".into());
+        }
+        for win_id in wins {
+            self.close_window(&win_id)?;
+        }
+        self.docs.remove(doc_id);
+        self.emit(&EngineEvent::DocumentClosed(doc_id.clone()));
+        Ok(())
+    }
+    /// Loads the file at `path`, or reuses the document already open from it. A missing
+    /// file opens as an empty text document that is created on save.
+    pub fn open_document(&mut self, path: &Path) -> Result<DocId, ApiError> {
+        if let Some(doc) = self
+            .docs
+            .values()
+            .find(|doc| doc.path.as_deref() == Some(path))
+        {
+            return Ok(doc.id.clone());
+        }
+        let data = if path.extension().is_some_and(|ext| ext == "csv") {
+            DocumentData::SpreadSheet(SpreadSheetDocumentData::from_file(&path.to_string_lossy())?)
+        } else if path.exists() {
+            DocumentData::Text(TextDocumentData::from_file(&path.to_string_lossy())?)
+        } else {
+            DocumentData::Text(TextDocumentData::new())
+        };
+        let (doc_id, doc) = Document::new(data, Some(path.to_path_buf()));
+        self.docs.insert(doc_id.clone(), doc);
+        self.emit(&EngineEvent::DocumentCreate(doc_id.clone()));
+        Ok(doc_id)
+    }
+    /// Shows another document in a window, starting at its top-left.
+    pub fn show_document(&mut self, win_id: &WindowId, doc_id: &DocId) -> Result<(), ApiError> {
+        if !self.docs.contains_key(doc_id) {
+            return Err(ApiError::not_found(format!(
+                "Document `{}` not found",
+                doc_id
+            )));
+        }
+        let win = self
+            .windows
+            .get_mut(win_id)
+            .ok_or_else(|| ApiError::not_found(format!("Window `{}` not found", win_id)))?;
+        win.doc_id = doc_id.clone();
+        win.cursor_row = 0;
+        win.cursor_col = 0;
+        win.scroll_rows = 0;
+        win.scroll_cols = 0;
+        self.emit(&EngineEvent::WindowDocChange(win_id.clone(), doc_id.clone()));
+        Ok(())
     }
     pub fn get_window(&mut self, win_id: &WindowId) -> (&mut WindowState, &mut Document) {
         let win = self.windows.get_mut(win_id).unwrap();
@@ -83,55 +259,96 @@ impl Engine {
         let win_id = self.active_window.clone();
         self.get_window(&win_id)
     }
+    pub fn try_get_window(
+        &mut self,
+        win_id: &WindowId,
+    ) -> Result<(&mut WindowState, &mut Document), ApiError> {
+        let win = self
+            .windows
+            .get_mut(win_id)
+            .ok_or_else(|| ApiError::not_found(format!("Window `{}` not found", win_id)))?;
+        let doc = self
+            .docs
+            .get_mut(&win.doc_id)
+            .ok_or_else(|| ApiError::not_found(format!("Document `{}` not found", win.doc_id)))?;
+        Ok((win, doc))
+    }
+    /// Screen area available to the layout, i.e. the terminal minus the footer line.
+    /// Without a terminal to measure, as under `--headless`, the screen is the one the
+    /// headless UI draws to.
+    pub fn screen_rect(&self) -> Rect {
+        let (cols, rows) = terminal::size().unwrap_or(HEADLESS_SIZE);
+        Rect {
+            x: 0,
+            y: 0,
+            width: cols as usize,
+            height: (rows as usize).saturating_sub(1),
+        }
+    }
+    pub fn get_window_rect(&self, win_id: &WindowId) -> Result<Rect, ApiError> {
+        let screen = self.screen_rect();
+        if let Some(layout) = &self.layout
+            && let Some((_, rect)) = layout
+                .get_rects(&screen)
+                .into_iter()
+                .find(|(id, _)| id == win_id)
+        {
+            return Ok(rect);
+        }
+        if let Some(popup) = &self.popups
+            && popup.layout.contains(win_id)
+        {
+            return Ok(popup.clone().get_rect(&screen)?);
+        }
+        Err(ApiError::not_found(format!(
+            "Window `{}` is not visible",
+            win_id
+        )))
+    }
+    /// Waits up to `timeout` for terminal input to become available.
+    pub fn has_input(&self, timeout: Duration) -> Result<bool, String> {
+        crossterm::event::poll(timeout).map_err(|err| err.to_string())
+    }
     pub fn await_input(&mut self) -> Result<Event, String> {
         loop {
             let event = crossterm::event::read().map_err(|err| err.to_string())?;
             self.emit(&EngineEvent::InputEvent(event.clone()));
-            if (event.is_mouse() && event.as_mouse_event().unwrap().kind.is_up())
+            // the pointer merely moving is of no interest
+            if event
+                .as_mouse_event()
+                .is_some_and(|mouse| mouse.kind != MouseEventKind::Moved)
                 || event.is_key_press()
+                || matches!(event, Event::Paste(_))
             {
                 return Ok(event);
             }
         }
     }
-    pub fn process_input(&mut self) -> Result<Option<Key>, String> {
+    pub fn process_input(&mut self) -> Result<Option<Input>, String> {
         let event = self.await_input()?;
 
         match event {
-            Event::Mouse(event) => {
-                let (col, row) = (event.column as usize, event.row as usize);
-                let (cols, rows) = terminal::size().map_err(|e| e.to_string())?;
-                if let Some(layout) = &self.layout {
-                    let tiles = layout.get_rects(&Rect {
-                        x: 0,
-                        y: 0,
-                        width: cols as usize,
-                        height: rows as usize,
-                    });
-                    for (win, rect) in tiles {
-                        if rect.x <= col
-                            && rect.x + rect.width > col
-                            && rect.y <= row
-                            && rect.y + rect.height > row
-                        {
-                            self.active_window = win;
-                            self.emit(&EngineEvent::LayoutChange);
-                        }
-                    }
-                }
-                Ok(None)
-            }
+            Event::Mouse(event) => Ok(Some(Input::Mouse(event))),
             Event::Key(key_event) => {
                 let converted = crate::commands::Key::from(key_event);
 
-                Ok(Some(converted))
+                Ok(Some(Input::Key(converted)))
             }
+            Event::Paste(text) => Ok(Some(Input::Paste(text))),
             _ => Ok(None),
         }
-
-        // Normalize
     }
 }
+
+/// Terminal input for the main loop to hand on.
+pub enum Input {
+    /// A key press, for the input engine.
+    Key(Key),
+    /// A click, drag, release or wheel turn, for the window under the pointer.
+    Mouse(MouseEvent),
+    /// Text pasted into the terminal, delivered whole rather than as keys.
+    Paste(String),
+}
 impl KeymapProvider for Engine {
     fn get_keymap_cache(&self) -> &Option<crate::input::keymaps::ActionNode> {
         &self.keymap
@@ -167,13 +384,26 @@ impl KeymapProvider for Engine {
                 })),
             },
         );
+        keymap.insert(
+            Key {
+                code: crate::commands::KeyCode::Char('p'),
+                modifiers: crate::commands::Modifiers::CTRL,
+            },
+            ActionNode {
+                children: HashMap::new(),
+                action: Some(crate::input::Token::Command(CommandRequest {
+                    id: "palette.open".to_string(),
+                    args: vec![],
+                })),
+            },
+        );
         ActionNode {
             children: keymap,
             action: None,
         }
     }
 }
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum SplitDirection {
     Up,
@@ -181,14 +411,30 @@ pub enum SplitDirection {
     Left,
     Right,
 }
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case", tag = "event", content = "data")]
 pub enum EngineEvent {
     WindowCreate(WindowId),
     WindowClose(WindowId),
     WindowDocChange(WindowId, DocId),
     LayoutChange,
     DocumentCreate(DocId),
-    InputEvent(Event),
+    DocumentChanged(DocId, DocRange),
+    DocumentSaved(DocId),
+    DocumentClosed(DocId),
+    CursorMoved(WindowId, usize, usize),
+    SelectionChanged(DocId),
+    ModeChanged(ModeType, ModeType),
+    BeforeQuit,
+    Message(Message),
+    InputEvent(#[serde(serialize_with = "serialize_input_event")] Event),
+}
+
+fn serialize_input_event<S>(event: &Event, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.serialize_str(&format!("{:?}", event))
 }
 
 impl EngineEvent {
@@ -199,23 +445,50 @@ impl EngineEvent {
             EngineEvent::WindowDocChange(_, _) => EngineEventKind::WindowDocChange,
             EngineEvent::LayoutChange => EngineEventKind::LayoutChange,
             EngineEvent::DocumentCreate(_) => EngineEventKind::DocumentCreate,
+            EngineEvent::DocumentChanged(_, _) => EngineEventKind::DocumentChanged,
+            EngineEvent::DocumentSaved(_) => EngineEventKind::DocumentSaved,
+            EngineEvent::DocumentClosed(_) => EngineEventKind::DocumentClosed,
+            EngineEvent::CursorMoved(_, _, _) => EngineEventKind::CursorMoved,
+            EngineEvent::SelectionChanged(_) => EngineEventKind::SelectionChanged,
+            EngineEvent::ModeChanged(_, _) => EngineEventKind::ModeChanged,
+            EngineEvent::BeforeQuit => EngineEventKind::BeforeQuit,
+            EngineEvent::Message(_) => EngineEventKind::Message,
             EngineEvent::InputEvent(_) => EngineEventKind::InputEvent,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
 pub enum EngineEventKind {
     WindowCreate,
     WindowClose,
     WindowDocChange,
     LayoutChange,
     DocumentCreate,
+    DocumentChanged,
+    DocumentSaved,
+    DocumentClosed,
+    CursorMoved,
+    SelectionChanged,
+    ModeChanged,
+    BeforeQuit,
+    Message,
     InputEvent,
 }
-pub struct Edit {}
+/// A change kept on a document's undo stack, holding what it takes to revert it.
+pub enum Edit {
+    /// Text inserted from `start` up to `end`, and where the cursor was before.
+    Inserted {
+        start: (usize, usize),
+        end: (usize, usize),
+        cursor: (usize, usize),
+    },
+    /// The raw contents cells held before they were overwritten, empty if unset.
+    Cells(Vec<((usize, usize), String)>),
+}
 pub type WindowId = String;
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, JsonSchema)]
 pub struct WindowState {
     pub id: WindowId,
     pub doc_id: DocId,
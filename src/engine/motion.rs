@@ -0,0 +1,455 @@
+use crate::api::ApiError;
+
+/// A `(row, col)` position in a text document, counted in characters.
+pub type Pos = (usize, usize);
+
+/// What an operator does to the text a motion moves over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Delete,
+    Change,
+    Yank,
+    Indent,
+    Outdent,
+}
+impl Operator {
+    pub fn parse(name: &str) -> Result<Self, ApiError> {
+        Ok(match name {
+            "d" => Operator::Delete,
+            "c" => Operator::Change,
+            "y" => Operator::Yank,
+            ">" => Operator::Indent,
+            "<" => Operator::Outdent,
+            other => {
+                return Err(ApiError::invalid_field(
+                    "operator",
+                    format!("unknown operator `{}`", other),
+                ));
+            }
+        })
+    }
+}
+
+/// A cursor movement typed in normal mode, on its own or after an operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Motion {
+    Left,
+    Right,
+    Down,
+    Up,
+    WordForward,
+    WordBackward,
+    WordEnd,
+    LineStart,
+    LineEnd,
+    FirstLine,
+    LastLine,
+    FindForward(char),
+    TillForward(char),
+    FindBackward(char),
+    TillBackward(char),
+    /// The current line, from a doubled operator such as `dd`.
+    Line,
+}
+impl Motion {
+    /// Reads a motion as the keymap names it, e.g. `w`, `gg` or `f` with its target.
+    pub fn parse(name: &str, target: Option<char>) -> Result<Self, ApiError> {
+        let target =
+            || target.ok_or_else(|| ApiError::invalid_field("char", "motion needs a character"));
+        Ok(match name {
+            "h" => Motion::Left,
+            "l" => Motion::Right,
+            "j" => Motion::Down,
+            "k" => Motion::Up,
+            "w" => Motion::WordForward,
+            "b" => Motion::WordBackward,
+            "e" => Motion::WordEnd,
+            "0" => Motion::LineStart,
+            "$" => Motion::LineEnd,
+            "gg" => Motion::FirstLine,
+            "G" => Motion::LastLine,
+            "f" => Motion::FindForward(target()?),
+            "t" => Motion::TillForward(target()?),
+            "F" => Motion::FindBackward(target()?),
+            "T" => Motion::TillBackward(target()?),
+            "line" => Motion::Line,
+            other => {
+                return Err(ApiError::invalid_field(
+                    "motion",
+                    format!("unknown motion `{}`", other),
+                ));
+            }
+        })
+    }
+    /// Motions that take a character to search for.
+    pub fn needs_char(name: &str) -> bool {
+        matches!(name, "f" | "t" | "F" | "T")
+    }
+    pub fn is_linewise(self) -> bool {
+        matches!(
+            self,
+            Motion::Down | Motion::Up | Motion::FirstLine | Motion::LastLine | Motion::Line
+        )
+    }
+    /// Whether an operator includes the character the motion lands on.
+    pub fn is_inclusive(self) -> bool {
+        matches!(
+            self,
+            Motion::WordEnd | Motion::LineEnd | Motion::FindForward(_) | Motion::TillForward(_)
+        )
+    }
+}
+
+/// Where `motion` repeated `count` times takes the cursor, or `None` if it cannot move.
+/// A `count` of 0 means none was typed, which `gg` and `G` tell apart from 1. `pending` is set when an operator waits for the motion, which may then stop just past
+/// the end of a line instead of on its last character.
+pub fn text_motion(
+    lines: &[String],
+    cursor: Pos,
+    motion: Motion,
+    count: usize,
+    pending: bool,
+) -> Option<Pos> {
+    let line_number = count;
+    let count = count.max(1);
+    let last_row = lines.len().saturating_sub(1);
+    let (row, col) = cursor;
+    let mut pos = match motion {
+        Motion::Left => (row, col.saturating_sub(count)),
+        Motion::Right => (row, col + count),
+        Motion::Down => (row + count, col),
+        Motion::Up => (row.saturating_sub(count), col),
+        Motion::LineStart => (row, 0),
+        Motion::LineEnd => ((row + count - 1).min(last_row), usize::MAX),
+        Motion::FirstLine => (count_or(line_number, 0).min(last_row), 0),
+        Motion::LastLine => (count_or(line_number, last_row).min(last_row), 0),
+        Motion::Line => ((row + count - 1).min(last_row), 0),
+        Motion::WordForward => repeat(cursor, count, |p| word_forward(lines, p)),
+        Motion::WordBackward => repeat(cursor, count, |p| word_backward(lines, p)),
+        Motion::WordEnd => repeat(cursor, count, |p| word_end(lines, p)),
+        Motion::FindForward(c) | Motion::TillForward(c) => {
+            let chars: Vec<char> = lines.get(row)?.chars().collect();
+            let found = (col + 1..chars.len())
+                .filter(|&i| chars[i] == c)
+                .nth(count - 1)?;
+            match motion {
+                Motion::TillForward(_) => (row, found - 1),
+                _ => (row, found),
+            }
+        }
+        Motion::FindBackward(c) | Motion::TillBackward(c) => {
+            let chars: Vec<char> = lines.get(row)?.chars().collect();
+            let found = (0..col.min(chars.len()))
+                .rev()
+                .filter(|&i| chars[i] == c)
+                .nth(count - 1)?;
+            match motion {
+                Motion::TillBackward(_) => (row, found + 1),
+                _ => (row, found),
+            }
+        }
+    };
+    if pos.0 > last_row {
+        if row == last_row {
+            return None;
+        }
+        pos.0 = last_row;
+    }
+    let len = line_len(lines, pos.0);
+    let max_col = if pending && !motion.is_inclusive() {
+        len
+    } else {
+        len.saturating_sub(1)
+    };
+    pos.1 = pos.1.min(max_col);
+    Some(pos)
+}
+
+/// Where `motion` takes the selected cell of a sheet spanning `extent` rows and columns.
+pub fn cell_motion(
+    cell: Pos,
+    extent: (usize, usize),
+    motion: Motion,
+    count: usize,
+) -> Result<Pos, ApiError> {
+    let line_number = count;
+    let count = count.max(1);
+    let (last_row, last_col) = (extent.0.saturating_sub(1), extent.1.saturating_sub(1));
+    let (row, col) = cell;
+    Ok(match motion {
+        Motion::Left => (row, col.saturating_sub(count)),
+        Motion::Right => (row, (col + count).min(last_col)),
+        Motion::Down => ((row + count).min(last_row), col),
+        Motion::Up => (row.saturating_sub(count), col),
+        Motion::LineStart => (row, 0),
+        Motion::LineEnd => (row, last_col),
+        Motion::FirstLine => (count_or(line_number, 0).min(last_row), col),
+        Motion::LastLine => (count_or(line_number, last_row).min(last_row), col),
+        other => {
+            return Err(ApiError::invalid_field(
+                "motion",
+                format!("{:?} does not move between cells", other),
+            ));
+        }
+    })
+}
+
+/// `count` as a one-based line number, or `default` if no count was typed.
+fn count_or(count: usize, default: usize) -> usize {
+    count.checked_sub(1).unwrap_or(default)
+}
+
+fn repeat(start: Pos, count: usize, step: impl Fn(Pos) -> Pos) -> Pos {
+    (0..count).fold(start, |pos, _| step(pos))
+}
+
+pub fn line_len(lines: &[String], row: usize) -> usize {
+    lines.get(row).map_or(0, |l| l.chars().count())
+}
+
+/// Whitespace, punctuation or a keyword character, as vim groups them into words.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum CharClass {
+    Blank,
+    Punctuation,
+    Word,
+}
+
+impl CharClass {
+    pub fn of(c: char) -> Self {
+        if c.is_whitespace() {
+            CharClass::Blank
+        } else if c.is_alphanumeric() || c == '_' {
+            CharClass::Word
+        } else {
+            CharClass::Punctuation
+        }
+    }
+}
+
+/// The class of the character at `pos`; the end of a line counts as blank.
+fn class_at(lines: &[String], pos: Pos) -> CharClass {
+    char_at(lines, pos).map_or(CharClass::Blank, CharClass::of)
+}
+
+/// The character at `pos`, or `None` at the end of a line.
+pub fn char_at(lines: &[String], pos: Pos) -> Option<char> {
+    lines.get(pos.0).and_then(|l| l.chars().nth(pos.1))
+}
+
+fn is_empty_line(lines: &[String], pos: Pos) -> bool {
+    pos.1 == 0 && line_len(lines, pos.0) == 0
+}
+
+/// The next position, visiting the end of each line as if it held a newline.
+pub fn next(lines: &[String], (row, col): Pos) -> Option<Pos> {
+    if col < line_len(lines, row) {
+        Some((row, col + 1))
+    } else if row + 1 < lines.len() {
+        Some((row + 1, 0))
+    } else {
+        None
+    }
+}
+
+pub fn prev(lines: &[String], (row, col): Pos) -> Option<Pos> {
+    if col > 0 {
+        Some((row, col - 1))
+    } else if row > 0 {
+        Some((row - 1, line_len(lines, row - 1)))
+    } else {
+        None
+    }
+}
+
+/// Just past the last character of the document, where word motions stop.
+fn doc_end(lines: &[String]) -> Pos {
+    let row = lines.len().saturating_sub(1);
+    (row, line_len(lines, row))
+}
+
+fn word_forward(lines: &[String], start: Pos) -> Pos {
+    let class = class_at(lines, start);
+    let mut pos = start;
+    if class != CharClass::Blank {
+        loop {
+            match next(lines, pos) {
+                Some(p) => pos = p,
+                None => return doc_end(lines),
+            }
+            if class_at(lines, pos) != class {
+                break;
+            }
+        }
+    }
+    // an empty line is a word of its own
+    while class_at(lines, pos) == CharClass::Blank && !(pos != start && is_empty_line(lines, pos)) {
+        match next(lines, pos) {
+            Some(p) => pos = p,
+            None => return doc_end(lines),
+        }
+    }
+    pos
+}
+
+fn word_backward(lines: &[String], start: Pos) -> Pos {
+    let Some(mut pos) = prev(lines, start) else {
+        return start;
+    };
+    while class_at(lines, pos) == CharClass::Blank && !is_empty_line(lines, pos) {
+        match prev(lines, pos) {
+            Some(p) => pos = p,
+            None => return (0, 0),
+        }
+    }
+    let class = class_at(lines, pos);
+    while pos.1 > 0 && class_at(lines, (pos.0, pos.1 - 1)) == class {
+        pos.1 -= 1;
+    }
+    pos
+}
+
+fn word_end(lines: &[String], start: Pos) -> Pos {
+    let Some(mut pos) = next(lines, start) else {
+        return start;
+    };
+    while class_at(lines, pos) == CharClass::Blank {
+        match next(lines, pos) {
+            Some(p) => pos = p,
+            None => return doc_end(lines),
+        }
+    }
+    let class = class_at(lines, pos);
+    while pos.1 + 1 < line_len(lines, pos.0) && class_at(lines, (pos.0, pos.1 + 1)) == class {
+        pos.1 += 1;
+    }
+    pos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|l| l.to_string()).collect()
+    }
+
+    fn go(text: &[&str], cursor: Pos, motion: Motion, count: usize) -> Option<Pos> {
+        text_motion(&lines(text), cursor, motion, count, false)
+    }
+
+    #[test]
+    fn words_split_at_blanks_and_punctuation() {
+        let text = ["foo bar.baz"];
+        assert_eq!(go(&text, (0, 0), Motion::WordForward, 0), Some((0, 4)));
+        assert_eq!(go(&text, (0, 4), Motion::WordForward, 1), Some((0, 7)));
+        assert_eq!(go(&text, (0, 0), Motion::WordForward, 3), Some((0, 8)));
+        assert_eq!(go(&text, (0, 8), Motion::WordBackward, 1), Some((0, 7)));
+        assert_eq!(go(&text, (0, 7), Motion::WordBackward, 1), Some((0, 4)));
+        assert_eq!(go(&text, (0, 0), Motion::WordEnd, 1), Some((0, 2)));
+        assert_eq!(go(&text, (0, 0), Motion::WordEnd, 2), Some((0, 6)));
+    }
+
+    #[test]
+    fn an_empty_line_is_a_word() {
+        let text = ["foo", "", "bar"];
+        assert_eq!(go(&text, (0, 0), Motion::WordForward, 1), Some((1, 0)));
+        assert_eq!(go(&text, (1, 0), Motion::WordForward, 1), Some((2, 0)));
+        assert_eq!(go(&text, (2, 0), Motion::WordBackward, 1), Some((1, 0)));
+    }
+
+    #[test]
+    fn word_motions_cross_lines() {
+        let text = ["foo", "  bar"];
+        assert_eq!(go(&text, (0, 0), Motion::WordForward, 1), Some((1, 2)));
+        assert_eq!(go(&text, (1, 2), Motion::WordBackward, 1), Some((0, 0)));
+        assert_eq!(go(&text, (0, 2), Motion::WordEnd, 1), Some((1, 4)));
+    }
+
+    #[test]
+    fn last_word_stops_on_the_line_or_past_it_for_an_operator() {
+        let text = lines(&["foo bar"]);
+        let w = Motion::WordForward;
+        assert_eq!(text_motion(&text, (0, 4), w, 1, false), Some((0, 6)));
+        assert_eq!(text_motion(&text, (0, 4), w, 1, true), Some((0, 7)));
+    }
+
+    #[test]
+    fn columns_count_characters() {
+        let text = ["héllo wörld"];
+        assert_eq!(go(&text, (0, 0), Motion::WordForward, 1), Some((0, 6)));
+        assert_eq!(go(&text, (0, 6), Motion::WordEnd, 1), Some((0, 10)));
+        assert_eq!(go(&text, (0, 0), Motion::LineEnd, 1), Some((0, 10)));
+        assert_eq!(go(&text, (0, 0), Motion::FindForward('ö'), 1), Some((0, 7)));
+    }
+
+    #[test]
+    fn vertical_motions_stop_at_the_edges() {
+        let text = ["a", "bcd", "e"];
+        assert_eq!(go(&text, (0, 0), Motion::Up, 1), Some((0, 0)));
+        assert_eq!(go(&text, (2, 0), Motion::Up, 5), Some((0, 0)));
+        assert_eq!(go(&text, (0, 0), Motion::Down, 5), Some((2, 0)));
+        assert_eq!(go(&text, (2, 0), Motion::Down, 1), None);
+        assert_eq!(go(&text, (1, 2), Motion::Down, 1), Some((2, 0)));
+    }
+
+    #[test]
+    fn line_end_of_an_empty_line_stays_at_its_start() {
+        assert_eq!(go(&[""], (0, 0), Motion::LineEnd, 1), Some((0, 0)));
+        assert_eq!(go(&["ab", ""], (0, 0), Motion::LineEnd, 2), Some((1, 0)));
+    }
+
+    #[test]
+    fn find_and_till_on_the_line() {
+        let text = ["a,b,c"];
+        assert_eq!(go(&text, (0, 0), Motion::FindForward(','), 1), Some((0, 1)));
+        assert_eq!(go(&text, (0, 0), Motion::FindForward(','), 2), Some((0, 3)));
+        assert_eq!(go(&text, (0, 0), Motion::TillForward(','), 2), Some((0, 2)));
+        assert_eq!(
+            go(&text, (0, 4), Motion::FindBackward(','), 1),
+            Some((0, 3))
+        );
+        assert_eq!(
+            go(&text, (0, 4), Motion::TillBackward(','), 2),
+            Some((0, 2))
+        );
+        assert_eq!(go(&text, (0, 0), Motion::FindForward(';'), 1), None);
+    }
+
+    #[test]
+    fn counts_are_line_numbers_for_gg_and_g() {
+        let text = ["a", "b", "c", "d"];
+        assert_eq!(go(&text, (2, 0), Motion::FirstLine, 0), Some((0, 0)));
+        assert_eq!(go(&text, (0, 0), Motion::LastLine, 0), Some((3, 0)));
+        assert_eq!(go(&text, (0, 0), Motion::LastLine, 2), Some((1, 0)));
+        assert_eq!(go(&text, (0, 0), Motion::FirstLine, 9), Some((3, 0)));
+    }
+
+    #[test]
+    fn cell_motions_stay_inside_the_sheet() {
+        let extent = (3, 4);
+        assert_eq!(
+            cell_motion((0, 0), extent, Motion::Right, 10).ok(),
+            Some((0, 3))
+        );
+        assert_eq!(
+            cell_motion((1, 1), extent, Motion::Down, 5).ok(),
+            Some((2, 1))
+        );
+        assert_eq!(
+            cell_motion((1, 1), extent, Motion::LineEnd, 1).ok(),
+            Some((1, 3))
+        );
+        assert!(cell_motion((0, 0), extent, Motion::WordForward, 1).is_err());
+    }
+
+    #[test]
+    fn parse_needs_a_char_for_find() {
+        assert_eq!(
+            Motion::parse("f", Some('x')).ok(),
+            Some(Motion::FindForward('x'))
+        );
+        assert!(Motion::parse("f", None).is_err());
+        assert!(Motion::parse("q", None).is_err());
+    }
+}
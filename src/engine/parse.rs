@@ -36,6 +36,8 @@ pub fn parse_csv_to_doc(path: PathBuf) -> Result<(DocId, Document), Box<dyn std:
         crate::engine::DocumentData::SpreadSheet(SpreadSheetDocumentData {
             cells: outer_map,
             selected_cell: (0, 0),
+            selected: None,
+            column_widths: HashMap::new(),
         }),
         Some(path),
     ))
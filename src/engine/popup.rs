@@ -1,14 +1,15 @@
+use schemars::JsonSchema;
 use serde::Deserialize;
 
 use crate::{engine::layout::LayoutNode, render::Rect};
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum RelativeTo {
     Editor,
     Win(String),
     Cursor,
 }
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum PopupPosition {
     TopRight,
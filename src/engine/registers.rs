@@ -0,0 +1,290 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::api::ApiError;
+
+/// How many yanks and deletes the ring keeps as registers `0` to `9`.
+const RING_SIZE: usize = 10;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RegisterKind {
+    /// Part of a line or lines, put inside the cursor line.
+    #[default]
+    Chars,
+    /// Whole lines, put above or below the cursor line.
+    Lines,
+    /// A rectangle of spreadsheet cells, one row per line with cells separated by tabs.
+    Cells,
+    /// A rectangle of text from a blockwise selection, put one line below the other
+    /// starting in the same column.
+    Block,
+}
+
+/// Text yanked or deleted by an operator, ready to be put back.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Register {
+    /// The yanked text split at line breaks.
+    pub text: Vec<String>,
+    #[serde(default)]
+    pub kind: RegisterKind,
+}
+impl Register {
+    pub fn new(text: Vec<String>, kind: RegisterKind) -> Self {
+        Self { text, kind }
+    }
+    /// The cells of a block, or of text read as tab-separated rows.
+    pub fn cells(&self) -> Vec<Vec<String>> {
+        self.text
+            .iter()
+            .map(|line| line.split('\t').map(str::to_string).collect())
+            .collect()
+    }
+    /// The register with rows and columns swapped; only blocks of cells change.
+    pub fn transposed(&self) -> Self {
+        let cells = self.cells();
+        let width = cells.iter().map(Vec::len).max().unwrap_or(0);
+        let text = (0..width)
+            .map(|col| {
+                cells
+                    .iter()
+                    .map(|row| row.get(col).map_or("", String::as_str))
+                    .collect::<Vec<_>>()
+                    .join("\t")
+            })
+            .collect();
+        Self::new(text, self.kind)
+    }
+    /// The register put `count` times in a row: text runs on, lines and cells stack up.
+    pub fn repeated(&self, count: usize) -> Self {
+        let mut register = self.clone();
+        for _ in 1..count {
+            register.append(self.clone());
+        }
+        register
+    }
+    fn append(&mut self, other: Register) {
+        match (self.kind, other.kind) {
+            (RegisterKind::Chars, RegisterKind::Chars) => {
+                let mut lines = other.text.into_iter();
+                if let (Some(last), Some(first)) = (self.text.last_mut(), lines.next()) {
+                    last.push_str(&first);
+                }
+                self.text.extend(lines);
+            }
+            _ => {
+                self.text.extend(other.text);
+                if other.kind != RegisterKind::Chars {
+                    self.kind = other.kind;
+                }
+            }
+        }
+    }
+}
+
+/// The unnamed register, `a` to `z`, the ring of recent yanks and deletes as `0` to
+/// `9`, and `+` for the system clipboard.
+#[derive(Default)]
+pub struct Registers {
+    unnamed: Option<Register>,
+    named: BTreeMap<char, Register>,
+    ring: VecDeque<Register>,
+    clipboard: Option<Register>,
+}
+impl Registers {
+    /// Stores what `y`, `d` or `c` took in the unnamed register, the ring and the
+    /// register `name`, if given. An uppercase name appends to its register.
+    pub fn store(&mut self, name: Option<char>, register: Register) -> Result<(), ApiError> {
+        Self::check_writable(name)?;
+        match name {
+            Some('+') => self.clipboard = Some(register.clone()),
+            Some(c) if c.is_ascii_lowercase() => {
+                self.named.insert(c, register.clone());
+            }
+            Some(c) if c.is_ascii_uppercase() => {
+                let c = c.to_ascii_lowercase();
+                match self.named.get_mut(&c) {
+                    Some(existing) => existing.append(register.clone()),
+                    None => {
+                        self.named.insert(c, register.clone());
+                    }
+                }
+            }
+            _ => {}
+        }
+        if self.ring.len() == RING_SIZE {
+            self.ring.pop_back();
+        }
+        self.ring.push_front(register.clone());
+        self.unnamed = Some(register);
+        Ok(())
+    }
+    /// Fails for a register an operator cannot yank into, such as the ring's `0` to `9`.
+    pub fn check_writable(name: Option<char>) -> Result<(), ApiError> {
+        match name {
+            None => Ok(()),
+            Some(c) if c == '"' || c == '+' || c.is_ascii_alphabetic() => Ok(()),
+            Some(c) => Err(read_only(c)),
+        }
+    }
+    /// Sets a register directly, without touching the unnamed register or the ring.
+    pub fn set(&mut self, name: char, register: Register) -> Result<(), ApiError> {
+        match name {
+            '"' => self.unnamed = Some(register),
+            '+' => self.clipboard = Some(register),
+            c if c.is_ascii_lowercase() => {
+                self.named.insert(c, register);
+            }
+            c => return Err(read_only(c)),
+        }
+        Ok(())
+    }
+    /// Stores a macro recorded with `q`, leaving the unnamed register and the ring
+    /// alone. An uppercase name appends to its register.
+    pub fn record(&mut self, name: char, register: Register) -> Result<(), ApiError> {
+        if name.is_ascii_uppercase() {
+            let name = name.to_ascii_lowercase();
+            match self.named.get_mut(&name) {
+                Some(existing) => existing.append(register),
+                None => {
+                    self.named.insert(name, register);
+                }
+            }
+            return Ok(());
+        }
+        self.set(name, register)
+    }
+    /// The register put uses for `name`, or the unnamed one if none is named.
+    pub fn get(&self, name: Option<char>) -> Result<Option<&Register>, ApiError> {
+        Ok(match name {
+            None | Some('"') => self.unnamed.as_ref(),
+            Some('+') => self.clipboard.as_ref(),
+            Some(c) if c.is_ascii_alphabetic() => self.named.get(&c.to_ascii_lowercase()),
+            Some(c) if c.is_ascii_digit() => self.ring.get(c as usize - '0' as usize),
+            Some(c) => {
+                return Err(ApiError::invalid_field(
+                    "register",
+                    format!("no register `{}`", c),
+                ));
+            }
+        })
+    }
+    /// Every register that holds something, by name.
+    pub fn list(&self) -> BTreeMap<char, &Register> {
+        let mut registers: BTreeMap<char, &Register> =
+            self.named.iter().map(|(c, r)| (*c, r)).collect();
+        registers.extend(
+            self.ring
+                .iter()
+                .enumerate()
+                .map(|(i, r)| ((b'0' + i as u8) as char, r)),
+        );
+        if let Some(register) = &self.unnamed {
+            registers.insert('"', register);
+        }
+        if let Some(register) = &self.clipboard {
+            registers.insert('+', register);
+        }
+        registers
+    }
+}
+
+fn read_only(name: char) -> ApiError {
+    ApiError::invalid_field(
+        "register",
+        format!("`{}` is not a register that can be written", name),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(text: &[&str]) -> Register {
+        Register::new(
+            text.iter().map(|l| l.to_string()).collect(),
+            RegisterKind::Chars,
+        )
+    }
+
+    fn lines(text: &[&str]) -> Register {
+        Register::new(
+            text.iter().map(|l| l.to_string()).collect(),
+            RegisterKind::Lines,
+        )
+    }
+
+    fn text(registers: &Registers, name: char) -> Vec<String> {
+        registers.get(Some(name)).unwrap().unwrap().text.clone()
+    }
+
+    #[test]
+    fn store_fills_the_unnamed_register_and_the_ring() {
+        let mut registers = Registers::default();
+        for i in 0..12 {
+            registers.store(None, chars(&[&i.to_string()])).unwrap();
+        }
+        assert_eq!(text(&registers, '"'), ["11"]);
+        assert_eq!(text(&registers, '0'), ["11"]);
+        assert_eq!(text(&registers, '9'), ["2"]);
+        assert!(registers.get(Some('a')).unwrap().is_none());
+    }
+
+    #[test]
+    fn uppercase_names_append() {
+        let mut registers = Registers::default();
+        registers.store(Some('a'), chars(&["foo"])).unwrap();
+        registers.store(Some('A'), chars(&["bar", "baz"])).unwrap();
+        assert_eq!(text(&registers, 'a'), ["foobar", "baz"]);
+        // the unnamed register holds only the appended part
+        assert_eq!(text(&registers, '"'), ["bar", "baz"]);
+
+        registers.store(Some('b'), chars(&["x"])).unwrap();
+        registers.store(Some('B'), lines(&["y"])).unwrap();
+        let b = registers.get(Some('b')).unwrap().unwrap();
+        assert_eq!(b.text, ["x", "y"]);
+        assert_eq!(b.kind, RegisterKind::Lines);
+    }
+
+    #[test]
+    fn ring_registers_are_read_only() {
+        let mut registers = Registers::default();
+        assert!(registers.store(Some('0'), chars(&["x"])).is_err());
+        assert!(registers.set('5', chars(&["x"])).is_err());
+        assert!(registers.get(Some('%')).is_err());
+        assert!(registers.set('+', chars(&["x"])).is_ok());
+        assert_eq!(text(&registers, '+'), ["x"]);
+    }
+
+    #[test]
+    fn recording_leaves_the_unnamed_register_alone() {
+        let mut registers = Registers::default();
+        registers.store(None, chars(&["yanked"])).unwrap();
+        registers.record('q', chars(&["dd"])).unwrap();
+        registers.record('Q', chars(&["j"])).unwrap();
+        assert_eq!(text(&registers, 'q'), ["ddj"]);
+        assert_eq!(text(&registers, '"'), ["yanked"]);
+    }
+
+    #[test]
+    fn transposed_swaps_rows_and_columns() {
+        let block = Register::new(
+            vec!["1\t2".to_string(), "3\t4\t5".to_string()],
+            RegisterKind::Cells,
+        );
+        let transposed = block.transposed();
+        assert_eq!(transposed.text, ["1\t3", "2\t4", "\t5"]);
+        assert_eq!(transposed.kind, RegisterKind::Cells);
+        assert_eq!(transposed.transposed().text, ["1\t2\t", "3\t4\t5"]);
+    }
+
+    #[test]
+    fn repeated_runs_text_on_and_stacks_lines() {
+        assert_eq!(chars(&["ab"]).repeated(3).text, ["ababab"]);
+        assert_eq!(chars(&["a", "b"]).repeated(2).text, ["a", "ba", "b"]);
+        assert_eq!(lines(&["a"]).repeated(2).text, ["a", "a"]);
+        assert_eq!(lines(&["a"]).repeated(0).text, ["a"]);
+    }
+}
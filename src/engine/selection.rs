@@ -0,0 +1,105 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::engine::motion::Pos;
+
+/// How a visual selection spans the text between its anchor and the cursor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VisualKind {
+    /// Every character from the anchor to the cursor, like `v`.
+    Char,
+    /// Whole lines, like `V`.
+    Line,
+    /// The rectangle with the anchor and cursor at opposite corners, like `Ctrl-v`.
+    /// Spreadsheet selections are always blocks.
+    Block,
+}
+
+/// A visual selection: it runs from where visual mode started to the cursor, or to
+/// the selected cell of a spreadsheet, and grows and shrinks as that moves.
+#[derive(Debug, Clone, Copy, Serialize, JsonSchema)]
+pub struct Selection {
+    pub anchor: Pos,
+    pub kind: VisualKind,
+}
+impl Selection {
+    pub fn new(anchor: Pos, kind: VisualKind) -> Self {
+        Self { anchor, kind }
+    }
+    /// The first and last positions selected, both inclusive. A block's are its
+    /// top-left and bottom-right corners; only the rows of a linewise selection count.
+    pub fn corners(&self, cursor: Pos) -> (Pos, Pos) {
+        match self.kind {
+            VisualKind::Char => (self.anchor.min(cursor), self.anchor.max(cursor)),
+            VisualKind::Line | VisualKind::Block => (
+                (self.anchor.0.min(cursor.0), self.anchor.1.min(cursor.1)),
+                (self.anchor.0.max(cursor.0), self.anchor.1.max(cursor.1)),
+            ),
+        }
+    }
+    /// How much is selected, to select as much again from elsewhere.
+    pub fn size(&self, cursor: Pos) -> SelectionSize {
+        let (from, to) = self.corners(cursor);
+        let cols = match self.kind {
+            // over several lines, characters are selected up to the same column
+            VisualKind::Char if to.0 > from.0 => to.1,
+            _ => to.1 - from.1,
+        };
+        SelectionSize {
+            kind: self.kind,
+            rows: to.0 - from.0,
+            cols,
+        }
+    }
+    pub fn contains(&self, cursor: Pos, pos: Pos) -> bool {
+        let (from, to) = self.corners(cursor);
+        match self.kind {
+            VisualKind::Char => from <= pos && pos <= to,
+            VisualKind::Line => (from.0..=to.0).contains(&pos.0),
+            VisualKind::Block => {
+                (from.0..=to.0).contains(&pos.0) && (from.1..=to.1).contains(&pos.1)
+            }
+        }
+    }
+    /// How much is selected, for the footer: `3R x 2C` for a block, `3L` for lines
+    /// and `12C` for characters, counting line breaks; `line_len` gives a line's length.
+    pub fn describe(&self, cursor: Pos, line_len: impl Fn(usize) -> usize) -> String {
+        let (from, to) = self.corners(cursor);
+        let rows = to.0 - from.0 + 1;
+        match self.kind {
+            VisualKind::Block => format!("{}R x {}C", rows, to.1 - from.1 + 1),
+            VisualKind::Line => format!("{}L", rows),
+            VisualKind::Char if rows == 1 => format!("{}C", to.1 - from.1 + 1),
+            VisualKind::Char => {
+                // the rest of the first line and its break, the middle lines, and the
+                // last line up to the cursor
+                let middle: usize = (from.0 + 1..to.0).map(|row| line_len(row) + 1).sum();
+                let first = line_len(from.0).saturating_sub(from.1) + 1;
+                format!("{}C", first + middle + to.1 + 1)
+            }
+        }
+    }
+}
+
+/// The size of a selection without where it is, as `.` repeats an operator used in
+/// visual mode on as much from the cursor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct SelectionSize {
+    pub kind: VisualKind,
+    /// Rows below the first one.
+    pub rows: usize,
+    /// Columns right of the first one, or for characters over several lines the
+    /// column they end at.
+    pub cols: usize,
+}
+impl SelectionSize {
+    /// A selection this size anchored at `from`, and where its far end is.
+    pub fn select(&self, from: Pos) -> (Selection, Pos) {
+        let to = match self.kind {
+            VisualKind::Char if self.rows > 0 => (from.0 + self.rows, self.cols),
+            _ => (from.0 + self.rows, from.1 + self.cols),
+        };
+        (Selection::new(from, self.kind), to)
+    }
+}
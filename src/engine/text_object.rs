@@ -0,0 +1,433 @@
+use crate::{
+    api::ApiError,
+    engine::{
+        documents::spreadsheet::SpreadSheetDocumentData,
+        motion::{CharClass, Pos, char_at, line_len, next, prev},
+    },
+};
+
+/// Text an operator acts on as a whole, typed after `i` (inner) or `a` (around).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextObject {
+    Word,
+    Quote(char),
+    Bracket(char, char),
+    Paragraph,
+    /// The cursor's row of a spreadsheet.
+    Row,
+    /// The cursor's column of a spreadsheet.
+    Column,
+}
+impl TextObject {
+    pub fn parse(name: &str) -> Result<Self, ApiError> {
+        Ok(match name {
+            "w" => TextObject::Word,
+            "\"" => TextObject::Quote('"'),
+            "'" => TextObject::Quote('\''),
+            "`" => TextObject::Quote('`'),
+            "(" | ")" | "b" => TextObject::Bracket('(', ')'),
+            "[" | "]" => TextObject::Bracket('[', ']'),
+            "{" | "}" | "B" => TextObject::Bracket('{', '}'),
+            "<" | ">" => TextObject::Bracket('<', '>'),
+            "p" => TextObject::Paragraph,
+            "r" => TextObject::Row,
+            "c" => TextObject::Column,
+            other => {
+                return Err(ApiError::invalid_field(
+                    "motion",
+                    format!("unknown text object `{}`", other),
+                ));
+            }
+        })
+    }
+}
+
+/// The span a text object covers in a text document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectRange {
+    /// From the first position up to the second, exclusive.
+    Chars(Pos, Pos),
+    /// Whole lines, both inclusive.
+    Lines(usize, usize),
+}
+
+/// The span of `object` around `cursor`, or `None` if the cursor is not in one.
+/// `count` selects several words or paragraphs, or an outer pair of brackets.
+pub fn text_object(
+    lines: &[String],
+    cursor: Pos,
+    object: TextObject,
+    around: bool,
+    count: usize,
+) -> Result<Option<ObjectRange>, ApiError> {
+    let count = count.max(1);
+    Ok(match object {
+        TextObject::Word => word(lines, cursor, around, count),
+        TextObject::Quote(quote) => quoted(lines, cursor, quote, around),
+        TextObject::Bracket(open, close) => bracketed(lines, cursor, open, close, around, count),
+        TextObject::Paragraph => paragraph(lines, cursor.0, around, count),
+        TextObject::Row | TextObject::Column => {
+            return Err(ApiError::invalid_field(
+                "motion",
+                "rows and columns are spreadsheet objects",
+            ));
+        }
+    })
+}
+
+/// Runs of characters of the same class on a line, as `(start, end, class)`.
+fn runs(line: &str) -> Vec<(usize, usize, CharClass)> {
+    let mut runs: Vec<(usize, usize, CharClass)> = vec![];
+    for (i, c) in line.chars().enumerate() {
+        let class = CharClass::of(c);
+        match runs.last_mut() {
+            Some(run) if run.2 == class => run.1 = i + 1,
+            _ => runs.push((i, i + 1, class)),
+        }
+    }
+    runs
+}
+
+fn word(lines: &[String], (row, col): Pos, around: bool, count: usize) -> Option<ObjectRange> {
+    let runs = runs(lines.get(row)?);
+    let first = runs.iter().position(|run| run.0 <= col && col < run.1)?;
+    if !around {
+        let last = (first + count - 1).min(runs.len() - 1);
+        return Some(ObjectRange::Chars(
+            (row, runs[first].0),
+            (row, runs[last].1),
+        ));
+    }
+    // each count takes a word and the blanks after it, or blanks and the word after them
+    let is_blank = |i: usize| runs[i].2 == CharClass::Blank;
+    let (mut last, mut next) = (first, first);
+    for _ in 0..count {
+        if next >= runs.len() {
+            break;
+        }
+        last = next;
+        if next + 1 < runs.len() && is_blank(next) != is_blank(next + 1) {
+            last = next + 1;
+        }
+        next = last + 1;
+    }
+    // without blanks after the word, take the ones before it instead
+    let mut start = runs[first].0;
+    if !is_blank(last) && !is_blank(first) && first > 0 && is_blank(first - 1) {
+        start = runs[first - 1].0;
+    }
+    Some(ObjectRange::Chars((row, start), (row, runs[last].1)))
+}
+
+fn quoted(lines: &[String], (row, col): Pos, quote: char, around: bool) -> Option<ObjectRange> {
+    let chars: Vec<char> = lines.get(row)?.chars().collect();
+    let quotes: Vec<usize> = (0..chars.len())
+        .filter(|&i| chars[i] == quote && (i == 0 || chars[i - 1] != '\\'))
+        .collect();
+    // quotes pair up from the start of the line; use the pair around the cursor or the
+    // next one after it
+    let (open, close) = quotes
+        .chunks_exact(2)
+        .map(|pair| (pair[0], pair[1]))
+        .find(|&(_, close)| col <= close)?;
+    if !around {
+        return Some(ObjectRange::Chars((row, open + 1), (row, close)));
+    }
+    let trailing = chars[close + 1..]
+        .iter()
+        .take_while(|c| c.is_whitespace())
+        .count();
+    let start = if trailing == 0 {
+        open - chars[..open]
+            .iter()
+            .rev()
+            .take_while(|c| c.is_whitespace())
+            .count()
+    } else {
+        open
+    };
+    Some(ObjectRange::Chars(
+        (row, start),
+        (row, close + 1 + trailing),
+    ))
+}
+
+fn bracketed(
+    lines: &[String],
+    cursor: Pos,
+    open: char,
+    close: char,
+    around: bool,
+    count: usize,
+) -> Option<ObjectRange> {
+    let mut start = match char_at(lines, cursor) {
+        Some(c) if c == open => cursor,
+        _ => enclosing_open(lines, cursor, open, close)?,
+    };
+    for _ in 1..count {
+        start = enclosing_open(lines, start, open, close)?;
+    }
+    let end = matching_close(lines, start, open, close)?;
+    if around {
+        return Some(ObjectRange::Chars(start, (end.0, end.1 + 1)));
+    }
+    // a block whose brackets sit on their own lines is emptied line by line
+    let open_ends_line = start.1 + 1 == line_len(lines, start.0);
+    let close_starts_line = lines[end.0].chars().take(end.1).all(char::is_whitespace);
+    if open_ends_line && close_starts_line && end.0 > start.0 + 1 {
+        return Some(ObjectRange::Lines(start.0 + 1, end.0 - 1));
+    }
+    let inner = next(lines, start)?;
+    let inner = if inner.1 == line_len(lines, inner.0) && inner.0 < end.0 {
+        (inner.0 + 1, 0)
+    } else {
+        inner
+    };
+    Some(ObjectRange::Chars(inner.min(end), end))
+}
+
+/// The unmatched `open` before `from`, skipping over nested pairs.
+fn enclosing_open(lines: &[String], from: Pos, open: char, close: char) -> Option<Pos> {
+    let mut depth = 0;
+    let mut pos = from;
+    while let Some(p) = prev(lines, pos) {
+        pos = p;
+        match char_at(lines, pos) {
+            Some(c) if c == close => depth += 1,
+            Some(c) if c == open && depth == 0 => return Some(pos),
+            Some(c) if c == open => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The `close` that matches the `open` at `from`.
+fn matching_close(lines: &[String], from: Pos, open: char, close: char) -> Option<Pos> {
+    let mut depth = 0;
+    let mut pos = from;
+    while let Some(p) = next(lines, pos) {
+        pos = p;
+        match char_at(lines, pos) {
+            Some(c) if c == open => depth += 1,
+            Some(c) if c == close && depth == 0 => return Some(pos),
+            Some(c) if c == close => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+fn paragraph(lines: &[String], row: usize, around: bool, count: usize) -> Option<ObjectRange> {
+    let blank = |row: usize| lines[row].trim().is_empty();
+    // the end of the block of lines as blank, or not, as `row`
+    let block_end = |row: usize| {
+        let mut end = row;
+        while end + 1 < lines.len() && blank(end + 1) == blank(row) {
+            end += 1;
+        }
+        end
+    };
+    if row >= lines.len() {
+        return None;
+    }
+    let mut first = row;
+    while first > 0 && blank(first - 1) == blank(row) {
+        first -= 1;
+    }
+    let blocks = if around { count * 2 } else { count };
+    let mut last = block_end(first);
+    for _ in 1..blocks {
+        if last + 1 >= lines.len() {
+            break;
+        }
+        last = block_end(last + 1);
+    }
+    // `ap` at the end of the text takes the blank lines before the paragraph instead
+    if around && !blank(row) && blank(last) == blank(row) && first > 0 {
+        while first > 0 && blank(first - 1) {
+            first -= 1;
+        }
+    }
+    Some(ObjectRange::Lines(first, last))
+}
+
+/// The inclusive corners of a cell-range object around `cell`. Inner rows and columns
+/// are the run of filled cells through `cell`, around ones span the sheet; `p` is the
+/// contiguous data region, with a border of one cell when around.
+pub fn cell_object(
+    data: &SpreadSheetDocumentData,
+    cell: Pos,
+    object: TextObject,
+    around: bool,
+) -> Result<(Pos, Pos), ApiError> {
+    let (rows, cols) = data.extent();
+    let (last_row, last_col) = (rows.saturating_sub(1), cols.saturating_sub(1));
+    let (row, col) = cell;
+    Ok(match (object, around) {
+        (TextObject::Row, true) => ((row, 0), (row, last_col)),
+        (TextObject::Column, true) => ((0, col), (last_row, col)),
+        (TextObject::Row, false) => {
+            let filled = |c: usize| data.is_filled(row, c);
+            let (mut first, mut last) = (col, col);
+            while first > 0 && filled(first - 1) {
+                first -= 1;
+            }
+            while last < last_col && filled(last + 1) {
+                last += 1;
+            }
+            ((row, first), (row, last))
+        }
+        (TextObject::Column, false) => {
+            let filled = |r: usize| data.is_filled(r, col);
+            let (mut first, mut last) = (row, row);
+            while first > 0 && filled(first - 1) {
+                first -= 1;
+            }
+            while last < last_row && filled(last + 1) {
+                last += 1;
+            }
+            ((first, col), (last, col))
+        }
+        (TextObject::Paragraph, _) => {
+            let (from, to) = data.data_region(cell);
+            if around {
+                (
+                    (from.0.saturating_sub(1), from.1.saturating_sub(1)),
+                    ((to.0 + 1).min(last_row), (to.1 + 1).min(last_col)),
+                )
+            } else {
+                (from, to)
+            }
+        }
+        (other, _) => {
+            return Err(ApiError::invalid_field(
+                "motion",
+                format!("{:?} is not a spreadsheet object; use r, c or p", other),
+            ));
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::documents::DocumentDataProvider;
+
+    fn object(text: &[&str], cursor: Pos, name: &str, around: bool) -> Option<ObjectRange> {
+        let lines: Vec<String> = text.iter().map(|l| l.to_string()).collect();
+        let object = TextObject::parse(name).unwrap();
+        text_object(&lines, cursor, object, around, 1).unwrap()
+    }
+
+    fn chars(start: Pos, end: Pos) -> Option<ObjectRange> {
+        Some(ObjectRange::Chars(start, end))
+    }
+
+    #[test]
+    fn words_take_the_blanks_after_or_before_them() {
+        let text = ["foo  bar"];
+        assert_eq!(object(&text, (0, 1), "w", false), chars((0, 0), (0, 3)));
+        assert_eq!(object(&text, (0, 1), "w", true), chars((0, 0), (0, 5)));
+        assert_eq!(object(&text, (0, 6), "w", true), chars((0, 3), (0, 8)));
+        assert_eq!(object(&text, (0, 3), "w", false), chars((0, 3), (0, 5)));
+        assert_eq!(object(&["é ab"], (0, 2), "w", false), chars((0, 2), (0, 4)));
+        assert_eq!(object(&[""], (0, 0), "w", false), None);
+    }
+
+    #[test]
+    fn a_count_takes_several_words() {
+        let lines = vec!["foo  bar baz".to_string()];
+        let range = text_object(&lines, (0, 0), TextObject::Word, false, 3).unwrap();
+        assert_eq!(range, chars((0, 0), (0, 8)));
+    }
+
+    #[test]
+    fn quotes_pair_up_from_the_start_of_the_line() {
+        let text = ["say \"hi there\" ok"];
+        assert_eq!(object(&text, (0, 6), "\"", false), chars((0, 5), (0, 13)));
+        assert_eq!(object(&text, (0, 6), "\"", true), chars((0, 4), (0, 15)));
+        assert_eq!(object(&text, (0, 0), "\"", false), chars((0, 5), (0, 13)));
+        assert_eq!(object(&text, (0, 15), "\"", false), None);
+        assert_eq!(
+            object(&["x \"a\""], (0, 3), "\"", true),
+            chars((0, 1), (0, 5))
+        );
+    }
+
+    #[test]
+    fn brackets_match_across_nesting() {
+        let text = ["f(a, (b), c)"];
+        assert_eq!(object(&text, (0, 2), "(", false), chars((0, 2), (0, 11)));
+        assert_eq!(object(&text, (0, 6), "b", false), chars((0, 6), (0, 7)));
+        assert_eq!(object(&text, (0, 6), ")", true), chars((0, 5), (0, 8)));
+        assert_eq!(object(&text, (0, 5), "(", false), chars((0, 6), (0, 7)));
+        assert_eq!(object(&text, (0, 10), "[", false), None);
+        assert_eq!(object(&["{}"], (0, 0), "{", false), chars((0, 1), (0, 1)));
+
+        let lines = vec![text[0].to_string()];
+        let outer = text_object(&lines, (0, 6), TextObject::Bracket('(', ')'), false, 2);
+        assert_eq!(outer.unwrap(), chars((0, 2), (0, 11)));
+    }
+
+    #[test]
+    fn brackets_on_their_own_lines_take_the_lines_between() {
+        let text = ["fn x() {", "    a;", "    b;", "}"];
+        assert_eq!(
+            object(&text, (1, 4), "{", false),
+            Some(ObjectRange::Lines(1, 2))
+        );
+        assert_eq!(object(&text, (1, 4), "B", true), chars((0, 7), (3, 1)));
+    }
+
+    #[test]
+    fn paragraphs_are_runs_of_blank_or_filled_lines() {
+        let text = ["a", "b", "", "c", "", "", "d"];
+        assert_eq!(
+            object(&text, (0, 0), "p", false),
+            Some(ObjectRange::Lines(0, 1))
+        );
+        assert_eq!(
+            object(&text, (1, 0), "p", true),
+            Some(ObjectRange::Lines(0, 2))
+        );
+        assert_eq!(
+            object(&text, (4, 0), "p", false),
+            Some(ObjectRange::Lines(4, 5))
+        );
+        // the last paragraph takes the blank lines before it
+        assert_eq!(
+            object(&text, (6, 0), "p", true),
+            Some(ObjectRange::Lines(4, 6))
+        );
+    }
+
+    #[test]
+    fn cell_objects_are_rejected_in_text() {
+        let lines = vec!["a".to_string()];
+        assert!(text_object(&lines, (0, 0), TextObject::Row, false, 1).is_err());
+        assert!(TextObject::parse("z").is_err());
+    }
+
+    #[test]
+    fn cell_ranges_around_the_cursor() {
+        let mut data = SpreadSheetDocumentData::new();
+        for (row, col, raw) in [
+            (0, 0, "a"),
+            (0, 1, "b"),
+            (0, 4, "x"),
+            (1, 1, "c"),
+            (3, 3, "y"),
+        ] {
+            data.set_raw(row, col, raw);
+        }
+        let at = |object, around| cell_object(&data, (0, 1), object, around).unwrap();
+        assert_eq!(at(TextObject::Row, false), ((0, 0), (0, 1)));
+        assert_eq!(at(TextObject::Row, true), ((0, 0), (0, 4)));
+        assert_eq!(at(TextObject::Column, false), ((0, 1), (1, 1)));
+        assert_eq!(at(TextObject::Column, true), ((0, 1), (3, 1)));
+        assert_eq!(at(TextObject::Paragraph, false), ((0, 0), (1, 1)));
+        assert_eq!(at(TextObject::Paragraph, true), ((0, 0), (2, 2)));
+        assert!(cell_object(&data, (0, 0), TextObject::Word, false).is_err());
+    }
+}
@@ -0,0 +1,269 @@
+use serde_json::Value;
+
+use crate::commands::command_dispatcher::CommandRequest;
+
+/// Short names accepted on the command line for built-in commands.
+pub const EX_ALIASES: &[(&str, &str)] = &[
+    ("w", "write"),
+    ("q", "quit"),
+    ("e", "edit"),
+    ("sp", "split"),
+    ("vs", "vsplit"),
+    ("g", "goto-cell"),
+];
+
+/// The one-line editor shown in the footer in command mode.
+#[derive(Default)]
+pub struct CommandLine {
+    pub text: String,
+    /// Cursor position in characters.
+    pub cursor: usize,
+    history: Vec<String>,
+    /// Index into `history` while browsing it with Up/Down.
+    history_pos: Option<usize>,
+    /// The line being typed when browsing started, restored after the newest entry.
+    draft: String,
+    completion: Option<Completion>,
+}
+
+/// Candidates offered by repeated Tab presses for the word before the cursor.
+struct Completion {
+    /// Character offset where the completed word starts.
+    start: usize,
+    candidates: Vec<String>,
+    index: usize,
+}
+
+impl CommandLine {
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+        self.history_pos = None;
+        self.draft.clear();
+        self.completion = None;
+    }
+    pub fn insert(&mut self, c: char) {
+        let at = self.byte_offset(self.cursor);
+        self.text.insert(at, c);
+        self.cursor += 1;
+        self.completion = None;
+    }
+    /// Deletes the character before the cursor, returning false if there was none.
+    pub fn backspace(&mut self) -> bool {
+        if self.cursor == 0 {
+            return false;
+        }
+        self.cursor -= 1;
+        let at = self.byte_offset(self.cursor);
+        self.text.remove(at);
+        self.completion = None;
+        true
+    }
+    pub fn move_cursor(&mut self, delta: isize) {
+        let len = self.text.chars().count();
+        self.cursor = self.cursor.saturating_add_signed(delta).min(len);
+        self.completion = None;
+    }
+    /// Steps through earlier lines (`older`) or back towards the line being typed.
+    pub fn browse_history(&mut self, older: bool) {
+        let pos = match (self.history_pos, older) {
+            (None, true) if self.history.is_empty() => return,
+            (None, true) => {
+                self.draft = self.text.clone();
+                Some(self.history.len() - 1)
+            }
+            (None, false) => return,
+            (Some(pos), true) => Some(pos.saturating_sub(1)),
+            (Some(pos), false) if pos + 1 < self.history.len() => Some(pos + 1),
+            (Some(_), false) => None,
+        };
+        self.text = match pos {
+            Some(pos) => self.history[pos].clone(),
+            None => std::mem::take(&mut self.draft),
+        };
+        self.history_pos = pos;
+        self.cursor = self.text.chars().count();
+        self.completion = None;
+    }
+    /// Takes the typed line, remembering it in the history.
+    pub fn submit(&mut self) -> String {
+        let line = std::mem::take(&mut self.text);
+        if !line.trim().is_empty() && self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+        self.clear();
+        line
+    }
+
+    /// The word being completed: its start offset, its text and whether it names the command.
+    pub fn completion_word(&self) -> (usize, String, bool) {
+        let before: Vec<char> = self.text.chars().take(self.cursor).collect();
+        let start = before
+            .iter()
+            .rposition(|c| c.is_whitespace())
+            .map_or(0, |i| i + 1);
+        let is_command = before[..start].iter().all(|c| c.is_whitespace());
+        (start, before[start..].iter().collect(), is_command)
+    }
+    pub fn is_completing(&self) -> bool {
+        self.completion.is_some()
+    }
+    /// Starts cycling through `candidates` for the word at `start`.
+    pub fn start_completion(&mut self, start: usize, candidates: Vec<String>) {
+        if candidates.is_empty() {
+            return;
+        }
+        self.completion = Some(Completion {
+            start,
+            candidates,
+            index: 0,
+        });
+        self.apply_completion();
+    }
+    /// Replaces the word with the next candidate.
+    pub fn next_completion(&mut self) {
+        if let Some(completion) = &mut self.completion {
+            completion.index = (completion.index + 1) % completion.candidates.len();
+        }
+        self.apply_completion();
+    }
+    fn apply_completion(&mut self) {
+        let Some(completion) = &self.completion else {
+            return;
+        };
+        let candidate = &completion.candidates[completion.index];
+        let (from, to) = (
+            self.byte_offset(completion.start),
+            self.byte_offset(self.cursor),
+        );
+        self.text.replace_range(from..to, candidate);
+        self.cursor = completion.start + candidate.chars().count();
+    }
+    fn byte_offset(&self, chars: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(chars)
+            .map_or(self.text.len(), |(i, _)| i)
+    }
+}
+
+/// Splits a command line into a command and its arguments; double quotes group words
+/// and a backslash escapes the next character.
+pub fn parse_command_line(line: &str) -> Option<CommandRequest> {
+    let mut words: Vec<String> = vec![];
+    let mut word: Option<String> = None;
+    let mut quoted = false;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => word.get_or_insert_default().extend(chars.next()),
+            '"' => {
+                quoted = !quoted;
+                word.get_or_insert_default();
+            }
+            c if c.is_whitespace() && !quoted => words.extend(word.take()),
+            c => word.get_or_insert_default().push(c),
+        }
+    }
+    words.extend(word);
+
+    let mut words = words.into_iter();
+    let name = words.next()?;
+    let id = EX_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name.clone(), |(_, id)| id.to_string());
+    Some(CommandRequest {
+        id,
+        args: words.map(Value::String).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(text: &str) -> CommandLine {
+        let mut line = CommandLine::default();
+        text.chars().for_each(|c| line.insert(c));
+        line
+    }
+
+    #[test]
+    fn editing_counts_characters() {
+        let mut line = typed("éa");
+        line.move_cursor(-1);
+        line.insert('x');
+        assert_eq!((line.text.as_str(), line.cursor), ("éxa", 2));
+        assert!(line.backspace());
+        line.move_cursor(-5);
+        assert_eq!((line.text.as_str(), line.cursor), ("éa", 0));
+        assert!(!line.backspace());
+        line.move_cursor(9);
+        assert_eq!(line.cursor, 2);
+    }
+
+    #[test]
+    fn history_skips_blank_and_repeated_lines() {
+        let mut line = CommandLine::default();
+        for text in ["one", "one", "  ", "two"] {
+            text.chars().for_each(|c| line.insert(c));
+            line.submit();
+        }
+        assert_eq!(line.history, ["one", "two"]);
+    }
+
+    #[test]
+    fn browsing_history_restores_the_typed_line() {
+        let mut line = CommandLine::default();
+        for text in ["one", "two"] {
+            text.chars().for_each(|c| line.insert(c));
+            line.submit();
+        }
+        "dra".chars().for_each(|c| line.insert(c));
+        line.browse_history(true);
+        assert_eq!(line.text, "two");
+        line.browse_history(true);
+        line.browse_history(true);
+        assert_eq!((line.text.as_str(), line.cursor), ("one", 3));
+        line.browse_history(false);
+        assert_eq!(line.text, "two");
+        line.browse_history(false);
+        assert_eq!((line.text.as_str(), line.cursor), ("dra", 3));
+        line.browse_history(false);
+        assert_eq!(line.text, "dra");
+    }
+
+    #[test]
+    fn browsing_an_empty_history_keeps_the_line() {
+        let mut line = typed("abc");
+        line.browse_history(true);
+        assert_eq!((line.text.as_str(), line.cursor), ("abc", 3));
+    }
+
+    #[test]
+    fn completion_cycles_through_candidates() {
+        let mut line = typed("e fi");
+        assert_eq!(line.completion_word(), (2, "fi".to_string(), false));
+        assert_eq!(typed("  wr").completion_word(), (2, "wr".to_string(), true));
+
+        line.start_completion(2, vec!["file1".to_string(), "fïle2".to_string()]);
+        assert_eq!(line.text, "e file1");
+        line.next_completion();
+        assert_eq!((line.text.as_str(), line.cursor), ("e fïle2", 7));
+        line.next_completion();
+        assert_eq!(line.text, "e file1");
+        assert!(line.is_completing());
+        line.insert('x');
+        assert!(!line.is_completing());
+    }
+
+    #[test]
+    fn parse_splits_words_with_quotes_and_escapes() {
+        let request = parse_command_line(r#"w "my file.txt" a\ b """#).unwrap();
+        assert_eq!(request.id, "write");
+        assert_eq!(request.args, ["my file.txt", "a b", ""]);
+        assert_eq!(parse_command_line("doc.save").unwrap().id, "doc.save");
+        assert!(parse_command_line("   ").is_none());
+    }
+}
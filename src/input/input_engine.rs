@@ -1,142 +1,774 @@
 use crate::{
+    api::ApiError,
     commands::{Key, KeyCode, Modifiers, command_dispatcher::CommandRequest},
-    engine::Engine,
+    engine::{Engine, EngineEvent, WindowId, document::DocId, motion::Motion},
     input::{
         Token,
-        keymaps::{ActionNode, KeymapProvider},
+        command_line::{CommandLine, parse_command_line},
+        keymaps::{ActionNode, KeybindScope, KeymapProvider},
+        macros::Recording,
     },
 };
-use serde::Deserialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+/// How long a key sequence waits for its next key unless configured otherwise.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(1000);
+/// How long a sequence waits before the keys that can follow it are shown.
+pub const DEFAULT_WHICH_KEY_DELAY: Duration = Duration::from_millis(500);
+/// How many macros may be played for one typed key, so a macro that plays itself
+/// stops instead of hanging the editor.
+const MAX_MACRO_PLAYS: usize = 1000;
 
 pub struct InputEngine {
-    active_nodes: Vec<Option<ActionNode>>,
+    /// Keys that start a binding, held back until it completes, the next key shows it
+    /// can't, or the timeout passes.
+    held: Vec<Key>,
+    /// When the last key was held.
+    held_since: Option<Instant>,
+    /// Whether the held keys are typed text in insert or command mode, matched only
+    /// against that mode's keymap.
+    held_typing: bool,
+    /// Keys to feed again after a binding fired on the keys before them.
+    replay: VecDeque<Key>,
+    /// How long to wait for the next key of a sequence, like vim's `timeoutlen`.
+    pub timeout: Duration,
+    /// How long the held keys wait before `which_key` lists what can follow them.
+    pub which_key_delay: Duration,
+    /// The keys that can follow the held ones and what each leads to, while shown.
+    pub which_key: Option<Vec<(Key, String)>>,
+    /// The macro being recorded with `q`, if any.
+    pub recording: Option<Recording>,
+    /// The register last played with `@`, for `@@`.
+    pub last_macro: Option<char>,
+    /// Macros played since the last typed key.
+    macro_plays: usize,
+    /// The last change made in normal mode, for `.` to repeat.
+    pub last_change: Option<Change>,
+    /// Whether keys typed now belong to the insert session of `last_change`.
+    typing_change: bool,
     pending: PendingState,
     pub mode: Mode,
+    /// The keymaps of the modes not currently active, kept so bindings registered for
+    /// a mode survive switching away from it.
+    mode_keymaps: HashMap<ModeType, ActionNode>,
+    pub command_line: CommandLine,
 }
 
 impl InputEngine {
     pub fn new() -> Self {
         Self {
-            active_nodes: vec![],
+            held: vec![],
+            held_since: None,
+            held_typing: false,
+            replay: VecDeque::new(),
+            timeout: DEFAULT_TIMEOUT,
+            which_key_delay: DEFAULT_WHICH_KEY_DELAY,
+            which_key: None,
+            recording: None,
+            last_macro: None,
+            macro_plays: 0,
+            last_change: None,
+            typing_change: false,
             pending: PendingState::new(),
             mode: Mode::new(ModeType::Input),
+            mode_keymaps: HashMap::new(),
+            command_line: CommandLine::default(),
         }
     }
+    /// Feeds a key typed by the user, recording it if a macro is being recorded.
     pub fn feed(
         &mut self,
         key: Key,
         engine: &mut Engine,
+    ) -> Result<Option<CommandRequest>, String> {
+        if let Some(recording) = &mut self.recording {
+            recording.keys.push(key);
+        }
+        self.macro_plays = 0;
+        self.feed_replayed(key, engine)
+    }
+    /// Feeds a key replayed after a binding fired or played from a macro, which
+    /// isn't recorded again.
+    pub fn feed_replayed(
+        &mut self,
+        key: Key,
+        engine: &mut Engine,
+    ) -> Result<Option<CommandRequest>, String> {
+        // the keys of an insert session that started with a change are part of it
+        if self.typing_change {
+            match (&self.mode.mode, &mut self.last_change) {
+                (ModeType::Input, Some(change)) => change.typed.push(key),
+                _ => self.typing_change = false,
+            }
+        }
+        let command = self.match_key(key, engine)?;
+        if let Some(command) = &command {
+            self.note_change(command, engine);
+        }
+        Ok(command)
+    }
+    fn match_key(
+        &mut self,
+        key: Key,
+        engine: &mut Engine,
     ) -> Result<Option<CommandRequest>, String> {
         // get potential token from key, match on token to fill out pending state, on motion or
         // command, emit command to command_dispatcher
-        if let ModeType::Input = self.mode.mode {
-            if !(key.code == KeyCode::Esc
-                || key.modifiers.contains(Modifiers::CTRL)
-                || key.modifiers.contains(Modifiers::ALT))
-            {
+        if self.held.is_empty() {
+            self.held_typing = self.is_typing(key);
+        }
+        if self.held_typing {
+            if self.is_typing(key) {
+                self.held.push(key);
+                return self.match_typed(false, engine);
+            }
+            // any other key ends a binding being typed, and the keys held back are text
+            let typed = std::mem::take(&mut self.held);
+            let typed = self.type_keys(typed, engine)?;
+            self.held_typing = false;
+            return Ok(self.feed_key(key, engine)?.or(typed));
+        }
+        self.feed_key(key, engine)
+    }
+    /// Remembers a command typed in normal mode, or an operator on the visual
+    /// selection, as the last change if it modifies the document, starting to collect
+    /// any insert session it begins.
+    fn note_change(&mut self, command: &CommandRequest, engine: &mut Engine) {
+        let mut command = command.clone();
+        match self.mode.mode {
+            ModeType::Normal => {}
+            // repeated, the operator acts on as much again from the cursor
+            ModeType::Visualize if command.id == "editor.operator" => {
                 let (win, doc) = engine.get_current_window();
-                if let Some(d) = doc.data.as_insertable() {
-                    d.handle_key(win, key).unwrap();
-                    return Ok(None);
+                let Some(size) = doc.data.selection_size(win) else {
+                    return;
+                };
+                if let Some(args) = command.args.first_mut().and_then(Value::as_object_mut) {
+                    args.insert("selection".to_string(), json!(size));
                 }
-            } else if key.code == KeyCode::Esc {
-                self.mode.mode = ModeType::Normal;
+            }
+            _ => return,
+        }
+        let is_change = match command.id.as_str() {
+            "editor.operator" => command
+                .args
+                .first()
+                .and_then(|args| args.get("operator")?.as_str())
+                .is_some_and(|operator| operator != "y"),
+            "editor.put" | "editor.put_transposed" | "editor.insert" => true,
+            _ => false,
+        };
+        if is_change {
+            self.last_change = Some(Change {
+                command,
+                typed: vec![],
+            });
+            self.typing_change = true;
+        }
+    }
+    /// Fires the binding the held keys are waiting on once the timeout has passed: the
+    /// longest complete binding among them, with the keys after it replayed.
+    pub fn check_timeout(
+        &mut self,
+        engine: &mut Engine,
+    ) -> Result<Option<CommandRequest>, String> {
+        let Some(since) = self.held_since else {
+            return Ok(None);
+        };
+        if self.held.is_empty() || since.elapsed() < self.timeout {
+            return Ok(None);
+        }
+        if self.held_typing {
+            return self.match_typed(true, engine);
+        }
+        let layers = self.current_keymap_stack(engine);
+        // a prefix that binds nothing itself, like a leader key, waits for as long as
+        // it takes, with `which_key` showing what can follow
+        if (1..=self.held.len()).all(|len| lookup(&layers, &self.held[..len]).0.is_none())
+            && lookup(&layers, &self.held).1
+        {
+            return Ok(None);
+        }
+        match self.match_held(&layers, true) {
+            HeldMatch::Token(token) => Ok(self.apply_token(token)),
+            HeldMatch::Pending => Ok(None),
+            HeldMatch::Unbound(_) => {
                 self.reset();
+                Ok(None)
             }
         }
-        let token = self.get_token(engine, key);
-        if token.is_none() {
+    }
+    /// Refreshes `which_key` from the live keymaps: once the held keys have waited
+    /// `which_key_delay`, it lists the keys that can follow them, each with the title
+    /// `title` gives its command, or what else it leads to. Returns whether it changed.
+    pub fn update_which_key(
+        &mut self,
+        engine: &mut Engine,
+        title: impl Fn(&str) -> Option<String>,
+    ) -> bool {
+        let waited = self
+            .held_since
+            .is_some_and(|since| since.elapsed() >= self.which_key_delay);
+        let entries = if self.held.is_empty() || self.held_typing || !waited {
+            None
+        } else {
+            let layers = self.current_keymap_stack(engine);
+            let mut entries: Vec<(Key, String)> = vec![];
+            for node in layers.iter().filter_map(|layer| layer.find(&self.held)) {
+                for (key, child) in &node.children {
+                    // the highest priority layer binding a key decides what it does
+                    if entries.iter().any(|(bound, _)| bound == key) {
+                        continue;
+                    }
+                    let label = match &child.action {
+                        Some(Token::Command(cmd)) => {
+                            title(&cmd.id).unwrap_or_else(|| cmd.id.clone())
+                        }
+                        Some(token) => token.builtin_name().unwrap_or_default(),
+                        None => "+prefix".to_string(),
+                    };
+                    entries.push((*key, label));
+                }
+            }
+            entries.sort_by_key(|(key, _)| key.to_string());
+            Some(entries).filter(|entries| !entries.is_empty())
+        };
+        if entries == self.which_key {
+            return false;
+        }
+        self.which_key = entries;
+        true
+    }
+    /// The next key to feed again after a binding fired on part of a sequence. They
+    /// are fed only after that binding's command ran, as it may change the mode.
+    pub fn take_replay(&mut self) -> Option<Key> {
+        self.replay.pop_front()
+    }
+    /// Queues keys to be fed next as if typed, such as a macro's, ahead of any keys
+    /// still waiting from the macro that played it.
+    pub fn play_macro(&mut self, keys: Vec<Key>) -> Result<(), String> {
+        self.macro_plays += 1;
+        if self.macro_plays > MAX_MACRO_PLAYS {
+            self.replay.clear();
+            return Err("macros played too many times, is one playing itself?".to_string());
+        }
+        for key in keys.into_iter().rev() {
+            self.replay.push_front(key);
+        }
+        Ok(())
+    }
+    /// Turns text pasted into the terminal into one `editor.paste` command rather
+    /// than a key per character, or types it into the command line. Keys held back
+    /// as the start of a binding are typed first if they were text, and dropped if not.
+    pub fn paste(
+        &mut self,
+        text: String,
+        engine: &mut Engine,
+    ) -> Result<Option<CommandRequest>, String> {
+        if self.held_typing {
+            let typed = std::mem::take(&mut self.held);
+            self.held_typing = false;
+            self.type_keys(typed, engine)?;
+        }
+        self.reset();
+        if let ModeType::Command = self.mode.mode {
+            // the command line is a single line
+            let line = text.lines().next().unwrap_or_default();
+            line.chars().for_each(|c| self.command_line.insert(c));
+            return Ok(None);
+        }
+        Ok(Some(CommandRequest {
+            id: "editor.paste".to_string(),
+            args: vec![json!({"text": text})],
+        }))
+    }
+    fn feed_key(
+        &mut self,
+        key: Key,
+        engine: &mut Engine,
+    ) -> Result<Option<CommandRequest>, String> {
+        if let ModeType::Command = self.mode.mode {
+            return Ok(self.feed_command_line(key, engine));
+        }
+        if let ModeType::Input = self.mode.mode
+            && key.code == KeyCode::Esc
+        {
+            self.set_mode(ModeType::Normal, engine);
+            self.reset();
+            return Ok(None);
+        }
+        if let ModeType::Visualize = self.mode.mode
+            && key.code == KeyCode::Esc
+        {
+            self.set_mode(ModeType::Normal, engine);
+            self.reset();
             return Ok(None);
         }
-        match token.unwrap() {
-            Token::Digit(dig) => self.pending.count = Some(dig),
-            Token::Operator(op) => self.pending.operator = Some(op),
-            Token::Motion(op) => {
-                self.pending.motion = Some(op);
+        let typed = match key.code {
+            KeyCode::Char(c) if !key.modifiers.intersects(Modifiers::CTRL | Modifiers::ALT) => {
+                Some(c)
+            }
+            _ => None,
+        };
+        if self.pending.awaiting_register {
+            self.pending.awaiting_register = false;
+            match typed {
+                Some(c) => self.pending.register = Some(c),
+                None => self.reset(),
+            }
+            return Ok(None);
+        }
+        if typed == Some('"')
+            && self.pending.operator.is_none()
+            && self.held.is_empty()
+        {
+            self.pending.awaiting_register = true;
+            return Ok(None);
+        }
+        if let Some(action) = self.pending.awaiting_macro.take() {
+            self.take_count();
+            let command = typed.map(|register| {
+                let id = if action == '@' { "macro.play" } else { "macro.record" };
+                self.with_pending(CommandRequest {
+                    id: id.to_string(),
+                    args: vec![json!({ "register": register })],
+                })
+            });
+            self.reset();
+            return Ok(command);
+        }
+        if let Some(action @ ('q' | '@')) = typed
+            && let ModeType::Normal = self.mode.mode
+            && self.pending.operator.is_none()
+            && self.held.is_empty()
+        {
+            // `q` while recording stops, and isn't part of the macro
+            if action == 'q'
+                && let Some(recording) = &mut self.recording
+            {
+                recording.keys.pop();
+                self.reset();
+                return Ok(Some(CommandRequest {
+                    id: "macro.stop".to_string(),
+                    args: vec![],
+                }));
+            }
+            self.pending.awaiting_macro = Some(action);
+            return Ok(None);
+        }
+        if self.pending.awaiting_char {
+            let command = typed.and_then(|c| {
+                // after `i` or `a` the key names a text object, otherwise it is the
+                // character `f` or `t` looks for
+                if self.pending.modifier.is_some() {
+                    self.pending.motion = Some(c.to_string());
+                } else {
+                    self.pending.char = Some(c);
+                }
+                self.create_operator_command()
+            });
+            self.reset();
+            return Ok(command);
+        }
+        if let Some(modifier @ ('i' | 'a')) = typed
+            && self.pending.operator.is_some()
+            && self.held.is_empty()
+        {
+            self.take_count();
+            self.pending.modifier = Some(modifier.to_string());
+            self.pending.awaiting_char = true;
+            return Ok(None);
+        }
+        self.held.push(key);
+        let layers = self.current_keymap_stack(engine);
+        match self.match_held(&layers, false) {
+            HeldMatch::Token(token) => Ok(self.apply_token(token)),
+            HeldMatch::Pending => Ok(None),
+            // keys that are text in insert mode are typed after all
+            HeldMatch::Unbound(key) if self.is_typing(key) => self.type_key(key, engine),
+            HeldMatch::Unbound(_) => {
+                self.reset();
+                Ok(None)
+            }
+        }
+    }
+    /// Folds a token into the pending state, returning the command once it is complete.
+    fn apply_token(&mut self, token: Token) -> Option<CommandRequest> {
+        match token {
+            Token::Digit(dig) => self.push_digit(dig),
+            // `0` moves to the line start unless a count is being typed
+            Token::Motion(motion) if motion == "0" && self.pending.digits.is_some() => {
+                self.push_digit(0)
+            }
+            // in visual mode an operator acts on the selection straight away
+            Token::Operator(op) if matches!(self.mode.mode, ModeType::Visualize) => {
+                let cmd = CommandRequest {
+                    id: "editor.operator".to_string(),
+                    args: vec![json!({
+                        "operator": op,
+                        "register": self.pending.register,
+                    })],
+                };
+                self.reset();
+                return Some(cmd);
+            }
+            Token::Operator(op) => {
+                self.take_count();
+                match &self.pending.operator {
+                    // a doubled operator such as `dd` works on whole lines
+                    Some(pending) if *pending == op => {
+                        self.pending.motion = Some("line".to_string());
+                        let cmd = self.create_operator_command();
+                        self.reset();
+                        return cmd;
+                    }
+                    Some(_) => self.reset(),
+                    None => self.pending.operator = Some(op),
+                }
+            }
+            Token::Motion(motion) => {
+                self.take_count();
+                if Motion::needs_char(&motion) {
+                    self.pending.motion = Some(motion);
+                    self.pending.awaiting_char = true;
+                    return None;
+                }
+                self.pending.motion = Some(motion);
                 let cmd = self.create_operator_command();
                 self.reset();
-                return Ok(cmd);
+                return cmd;
             }
             Token::Command(op) => {
+                self.take_count();
+                let command = self.with_pending(op);
                 self.reset();
-                return Ok(Some(op));
+                return Some(command);
             }
         }
-        Ok(None)
+        None
     }
-    fn create_operator_command(&mut self) -> Option<CommandRequest> {
-        if self.pending.operator.is_none() {
-            self.reset();
-            return None;
+    /// Whether `key` would be typed as text: any key but `Esc` and control or alt
+    /// combinations in insert mode, and any key at all on the command line.
+    fn is_typing(&self, key: Key) -> bool {
+        match self.mode.mode {
+            ModeType::Command => true,
+            ModeType::Input => {
+                !(key.code == KeyCode::Esc
+                    || key.modifiers.intersects(Modifiers::CTRL | Modifiers::ALT))
+            }
+            _ => false,
+        }
+    }
+    /// Matches the held keys typed in insert or command mode against that mode's
+    /// keymap; a key that starts no binding is typed as text.
+    fn match_typed(
+        &mut self,
+        expired: bool,
+        engine: &mut Engine,
+    ) -> Result<Option<CommandRequest>, String> {
+        let layers = [self.mode.keymap().clone()];
+        match self.match_held(&layers, expired) {
+            HeldMatch::Token(Token::Command(op)) => Ok(Some(op)),
+            HeldMatch::Token(_) | HeldMatch::Pending => Ok(None),
+            HeldMatch::Unbound(key) => self.type_key(key, engine),
+        }
+    }
+    /// Matches the held keys against keymap layers, highest priority first. A binding
+    /// fires once no layer binds a longer sequence starting with the same keys;
+    /// until then the keys wait for more, or for the timeout to `expire` them. Keys
+    /// that can't complete a longer binding fire the longest binding they start with,
+    /// and the keys after it are replayed.
+    fn match_held(&mut self, layers: &[ActionNode], expired: bool) -> HeldMatch {
+        if !expired && lookup(layers, &self.held).1 {
+            self.held_since = Some(Instant::now());
+            return HeldMatch::Pending;
+        }
+        let bound = (1..=self.held.len())
+            .rev()
+            .find_map(|len| Some((lookup(layers, &self.held[..len]).0?, len)));
+        let len = bound.as_ref().map_or(1, |(_, len)| *len);
+        let mut keys = std::mem::take(&mut self.held);
+        self.held_since = None;
+        for key in keys.split_off(len).into_iter().rev() {
+            self.replay.push_front(key);
+        }
+        match bound {
+            Some((token, _)) => HeldMatch::Token(token),
+            None => HeldMatch::Unbound(keys[0]),
+        }
+    }
+    /// Types keys into the document, or into the command line in command mode.
+    fn type_keys(
+        &mut self,
+        keys: Vec<Key>,
+        engine: &mut Engine,
+    ) -> Result<Option<CommandRequest>, String> {
+        let mut command = None;
+        for key in keys {
+            command = self.type_key(key, engine)?.or(command);
+        }
+        Ok(command)
+    }
+    fn type_key(
+        &mut self,
+        key: Key,
+        engine: &mut Engine,
+    ) -> Result<Option<CommandRequest>, String> {
+        if let ModeType::Command = self.mode.mode {
+            return Ok(self.feed_command_line(key, engine));
+        }
+        let (win, doc) = engine.get_current_window();
+        let (win_id, doc_id) = (win.id.clone(), doc.id.clone());
+        let cursor = (win.cursor_row, win.cursor_col);
+        // documents that take no text get the key as a normal mode key would
+        let Some(d) = doc.data.as_insertable() else {
+            self.held_typing = false;
+            return self.feed_key(key, engine);
+        };
+        let changed = d.handle_key(win, key).map_err(|e| e.to_string())?;
+        let command = d.take_command();
+        let moved = (win.cursor_row, win.cursor_col);
+        if let Some(range) = changed {
+            engine.emit(&EngineEvent::DocumentChanged(doc_id, range));
         }
-        let mut args = Vec::<Value>::new();
-        args.push(json!({"operator": self.pending.operator}));
-        if self.pending.count.is_some() {
-            args.push(json!({"count":self.pending.count}));
+        if moved != cursor {
+            engine.emit(&EngineEvent::CursorMoved(win_id, moved.0, moved.1));
         }
-        if self.pending.motion.is_some() {
-            args.push(json!({"motion":self.pending.motion}));
+        Ok(command)
+    }
+    fn push_digit(&mut self, digit: u32) {
+        let digits = self.pending.digits.unwrap_or(0);
+        self.pending.digits = Some(digits.saturating_mul(10).saturating_add(digit));
+    }
+    /// Folds the digits typed so far into the count, multiplying counts given both
+    /// before and after an operator as in `2d3w`.
+    fn take_count(&mut self) {
+        if let Some(digits) = self.pending.digits.take() {
+            let count = self.pending.count.unwrap_or(1);
+            self.pending.count = Some(count.saturating_mul(digits));
         }
-        if self.pending.modifier.is_some() {
-            args.push(json!({"modifier":self.pending.modifier}));
+    }
+    /// Edits the footer command line; Enter parses it into a command.
+    fn feed_command_line(&mut self, key: Key, engine: &mut Engine) -> Option<CommandRequest> {
+        let line = &mut self.command_line;
+        match key.code {
+            KeyCode::Esc => self.set_mode(ModeType::Normal, engine),
+            KeyCode::Enter => {
+                let line = line.submit();
+                self.set_mode(ModeType::Normal, engine);
+                return parse_command_line(&line);
+            }
+            KeyCode::Tab => {
+                return Some(CommandRequest {
+                    id: "cmdline.complete".to_string(),
+                    args: vec![],
+                });
+            }
+            // backspace on an empty line leaves the command line
+            KeyCode::Backspace if !line.backspace() && line.text.is_empty() => {
+                self.set_mode(ModeType::Normal, engine);
+            }
+            KeyCode::Left => line.move_cursor(-1),
+            KeyCode::Right => line.move_cursor(1),
+            KeyCode::Up => line.browse_history(true),
+            KeyCode::Down => line.browse_history(false),
+            KeyCode::Char(c) if !key.modifiers.contains(Modifiers::CTRL) => line.insert(c),
+            _ => {}
         }
+        None
+    }
+    pub fn set_mode(&mut self, mode: ModeType, engine: &mut Engine) {
+        if let ModeType::Command = mode {
+            self.command_line.clear();
+        }
+        // leaving visual mode drops the selection
+        if let (ModeType::Visualize, false) =
+            (&self.mode.mode, matches!(mode, ModeType::Visualize))
+        {
+            let (_, doc) = engine.get_current_window();
+            let doc_id = doc.id.clone();
+            if let Some(selection) = doc.data.selection_mut()
+                && selection.take().is_some()
+            {
+                engine.emit(&EngineEvent::SelectionChanged(doc_id));
+            }
+        }
+        // each mode keeps its keymap, with any bindings registered for it
+        let previous = std::mem::replace(&mut self.mode, Mode::new(mode.clone()));
+        if let Some(keymap) = previous.get_keymap_cache() {
+            self.mode_keymaps.insert(previous.mode.clone(), keymap.clone());
+        }
+        if let Some(keymap) = self.mode_keymaps.get(&mode) {
+            self.mode.set_keymap_cache(Some(keymap.clone()));
+        }
+        self.held.clear();
+        engine.emit(&EngineEvent::ModeChanged(previous.mode, mode));
+    }
+    /// Applies `edit` to the keymap of `mode`, whether or not it is the current mode.
+    pub fn edit_mode_keymap<T>(
+        &mut self,
+        mode: ModeType,
+        edit: impl FnOnce(&mut ActionNode) -> T,
+    ) -> T {
+        if self.mode.mode == mode {
+            let mut keymap = self.mode.keymap().clone();
+            let out = edit(&mut keymap);
+            self.mode.set_keymap_cache(Some(keymap));
+            return out;
+        }
+        let keymap = self
+            .mode_keymaps
+            .entry(mode.clone())
+            .or_insert_with(|| Mode::new(mode).keymap().clone());
+        edit(keymap)
+    }
+    /// The `editor.operator` command for the pending operator and motion; without an
+    /// operator it just moves the cursor.
+    fn create_operator_command(&mut self) -> Option<CommandRequest> {
+        let pending = &self.pending;
         Some(CommandRequest {
             id: "editor.operator".to_string(),
-            args,
+            args: vec![json!({
+                "operator": pending.operator,
+                "count": pending.count,
+                "motion": pending.motion.as_ref()?,
+                "char": pending.char,
+                "modifier": pending.modifier,
+                "register": pending.register,
+            })],
         })
     }
 
+    /// Passes a count or register typed before a bound command on to it, in its first
+    /// argument, unless the binding already sets them.
+    fn with_pending(&self, mut command: CommandRequest) -> CommandRequest {
+        let pending = [
+            ("count", self.pending.count.map(|c| json!(c))),
+            ("register", self.pending.register.map(|r| json!(r))),
+        ];
+        for (key, value) in pending {
+            let Some(value) = value else { continue };
+            if command.args.is_empty() {
+                command.args.push(json!({}));
+            }
+            if let Some(args) = command.args[0].as_object_mut() {
+                args.entry(key).or_insert(value);
+            }
+        }
+        command
+    }
+
     fn current_keymap_stack(&mut self, engine: &mut Engine) -> Vec<ActionNode> {
         let (win, doc) = engine.get_current_window();
-        vec![
-            doc.keymap().clone(),
-            win.keymap().clone(),
+        let mut stack = vec![doc.keymap().clone()];
+        let (win_keymap, doc_type) = (win.keymap().clone(), doc.doc_type.clone());
+        stack.extend(engine.doc_type_keymaps.get(&doc_type).cloned());
+        stack.extend([
+            win_keymap,
             self.mode.keymap().clone(), // <- mode layer
             engine.keymap().clone(),
-        ]
+        ]);
+        stack
     }
 
-    fn reset(&mut self) {
-        self.active_nodes.clear();
-        self.pending = PendingState::new();
+    /// Applies `edit` to the keymap of `scope`, creating it if need be.
+    pub fn edit_keymap<T>(
+        &mut self,
+        engine: &mut Engine,
+        scope: &KeybindScope,
+        edit: impl FnOnce(&mut ActionNode) -> T,
+    ) -> Result<T, ApiError> {
+        fn edit_provider<T>(
+            provider: &mut impl KeymapProvider,
+            edit: impl FnOnce(&mut ActionNode) -> T,
+        ) -> T {
+            let mut keymap = provider.keymap().clone();
+            let out = edit(&mut keymap);
+            provider.set_keymap_cache(Some(keymap));
+            out
+        }
+        Ok(match scope {
+            KeybindScope::Global => edit_provider(engine, edit),
+            KeybindScope::DocType(doc_type) => {
+                let keymap = engine.doc_type_keymaps.entry(doc_type.clone());
+                edit(keymap.or_insert_with(ActionNode::new))
+            }
+            KeybindScope::Window(win_id) => {
+                let win = engine
+                    .windows
+                    .get_mut(win_id)
+                    .ok_or_else(|| ApiError::not_found(format!("no window `{}`", win_id)))?;
+                edit_provider(win, edit)
+            }
+            KeybindScope::Buffer(doc_id) => {
+                let doc = engine
+                    .docs
+                    .get_mut(doc_id)
+                    .ok_or_else(|| ApiError::not_found(format!("no buffer `{}`", doc_id)))?;
+                edit_provider(doc, edit)
+            }
+            KeybindScope::Mode(mode) => self.edit_mode_keymap(mode.clone(), edit),
+        })
     }
 
-    fn get_token(&mut self, engine: &mut Engine, key: Key) -> Option<Token> {
-        // Initialize cursors if empty
-        if self.active_nodes.is_empty() {
-            let keymaps = self.current_keymap_stack(engine);
-            self.active_nodes = keymaps.iter().map(|km| Some(km.clone())).collect();
+    /// Every keymap with the scope that writes to it: the global one, each mode's,
+    /// each document type's and each open window's and buffer's.
+    pub fn keymaps(&mut self, engine: &mut Engine) -> Vec<(KeybindScope, ActionNode)> {
+        let mut keymaps = vec![(KeybindScope::Global, engine.keymap().clone())];
+        for mode in [
+            ModeType::Normal,
+            ModeType::Input,
+            ModeType::Visualize,
+            ModeType::Command,
+        ] {
+            let keymap = self.edit_mode_keymap(mode.clone(), |keymap| keymap.clone());
+            keymaps.push((KeybindScope::Mode(mode), keymap));
         }
-
-        let mut matches = Vec::new();
-
-        for i in 0..self.active_nodes.len() {
-            if let Some(node) = self.active_nodes.get_mut(i).unwrap() {
-                if let Some(next) = node.children.get(&key) {
-                    if let Some(token) = &next.action {
-                        matches.push(token.clone());
-                    }
-                    *node = next.clone();
-                } else {
-                    self.active_nodes[i] = None;
-                }
-            }
+        let mut doc_types: Vec<_> = engine.doc_type_keymaps.iter().collect();
+        doc_types.sort_by_key(|(doc_type, _)| *doc_type);
+        for (doc_type, keymap) in doc_types {
+            keymaps.push((KeybindScope::DocType(doc_type.clone()), keymap.clone()));
         }
-
-        if matches.is_empty() && self.active_nodes.iter().all(|n| n.is_none()) {
-            self.reset();
-            return None;
+        let mut win_ids: Vec<WindowId> = engine.windows.keys().cloned().collect();
+        win_ids.sort();
+        for win_id in win_ids {
+            let keymap = engine.windows.get_mut(&win_id).unwrap().keymap().clone();
+            keymaps.push((KeybindScope::Window(win_id), keymap));
         }
-        if self.active_nodes.iter().all(|n| n.is_some()) {
-            return None;
+        let mut doc_ids: Vec<DocId> = engine.docs.keys().cloned().collect();
+        doc_ids.sort();
+        for doc_id in doc_ids {
+            let keymap = engine.docs.get_mut(&doc_id).unwrap().keymap().clone();
+            keymaps.push((KeybindScope::Buffer(doc_id), keymap));
         }
-        if !matches.is_empty() {
-            return Some(matches[0].clone());
+        keymaps
+    }
+
+    fn reset(&mut self) {
+        self.held.clear();
+        self.held_since = None;
+        self.pending = PendingState::new();
+    }
+}
+
+/// The token bound to exactly `keys` in the first layer that binds them, and whether
+/// any layer binds a longer sequence starting with them.
+fn lookup(layers: &[ActionNode], keys: &[Key]) -> (Option<Token>, bool) {
+    let mut token = None;
+    let mut continues = false;
+    for node in layers.iter().filter_map(|layer| layer.find(keys)) {
+        if token.is_none() {
+            token = node.action.clone();
         }
-        None
+        continues |= !node.children.is_empty();
     }
+    (token, continues)
 }
 
 impl Default for InputEngine {
@@ -144,11 +776,17 @@ impl Default for InputEngine {
         Self::new()
     }
 }
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, JsonSchema)]
 pub enum ModeType {
+    #[serde(alias = "insert")]
     Input,
+    #[serde(alias = "visual")]
     Visualize,
+    #[serde(alias = "normal")]
     Normal,
+    /// Typing an ex command into the footer after `:`.
+    #[serde(alias = "command")]
+    Command,
 }
 pub struct Mode {
     pub mode: ModeType,
@@ -178,6 +816,88 @@ impl KeymapProvider for Mode {
                 })),
             },
         );
+        if let ModeType::Normal = self.mode {
+            keymap.insert(
+                Key {
+                    code: crate::commands::KeyCode::Char(':'),
+                    modifiers: crate::commands::Modifiers::empty(),
+                },
+                ActionNode {
+                    children: HashMap::new(),
+                    action: Some(crate::input::Token::Command(CommandRequest {
+                        id: "cmdline.open".to_string(),
+                        args: vec![],
+                    })),
+                },
+            );
+            bind_chars(
+                &mut keymap,
+                "i",
+                Token::Command(CommandRequest {
+                    id: "editor.insert".to_string(),
+                    args: vec![],
+                }),
+            );
+            bind_chars(
+                &mut keymap,
+                "u",
+                Token::Command(CommandRequest {
+                    id: "editor.undo".to_string(),
+                    args: vec![],
+                }),
+            );
+            bind_chars(
+                &mut keymap,
+                ".",
+                Token::Command(CommandRequest {
+                    id: "editor.repeat".to_string(),
+                    args: vec![],
+                }),
+            );
+            for (key, before) in [("p", false), ("P", true)] {
+                bind_chars(
+                    &mut keymap,
+                    key,
+                    Token::Command(CommandRequest {
+                        id: "editor.put".to_string(),
+                        args: vec![json!({ "before": before })],
+                    }),
+                );
+            }
+        }
+        if let ModeType::Normal | ModeType::Visualize = self.mode {
+            for digit in 1..=9 {
+                bind_chars(&mut keymap, &digit.to_string(), Token::Digit(digit));
+            }
+            for operator in ["d", "c", "y", ">", "<"] {
+                bind_chars(&mut keymap, operator, Token::Operator(operator.to_string()));
+            }
+            for motion in [
+                "h", "j", "k", "l", "w", "b", "e", "0", "$", "gg", "G", "f", "t", "F", "T",
+            ] {
+                bind_chars(&mut keymap, motion, Token::Motion(motion.to_string()));
+            }
+            // `v`, `V` and `Ctrl-v` start visual mode, switch its kind, or leave it
+            for (key, modifiers, kind) in [
+                ('v', Modifiers::empty(), "char"),
+                ('V', Modifiers::empty(), "line"),
+                ('v', Modifiers::CTRL, "block"),
+            ] {
+                keymap.insert(
+                    Key {
+                        code: KeyCode::Char(key),
+                        modifiers,
+                    },
+                    ActionNode {
+                        children: HashMap::new(),
+                        action: Some(Token::Command(CommandRequest {
+                            id: "editor.visual".to_string(),
+                            args: vec![json!({ "kind": kind })],
+                        })),
+                    },
+                );
+            }
+        }
         ActionNode {
             children: keymap,
             action: None,
@@ -190,19 +910,82 @@ impl KeymapProvider for Mode {
         self.keymap = value;
     }
 }
+/// Binds the sequence of plain character keys in `keys` to `token`.
+fn bind_chars(keymap: &mut HashMap<Key, ActionNode>, keys: &str, token: Token) {
+    let mut children = keymap;
+    let mut chars = keys.chars().peekable();
+    while let Some(c) = chars.next() {
+        let node = children
+            .entry(Key {
+                code: KeyCode::Char(c),
+                modifiers: Modifiers::empty(),
+            })
+            .or_insert_with(ActionNode::new);
+        if chars.peek().is_none() {
+            node.action = Some(token);
+            return;
+        }
+        children = &mut node.children;
+    }
+}
+/// How the held keys match the keymaps.
+enum HeldMatch {
+    Token(Token),
+    /// The keys so far start a longer binding.
+    Pending,
+    /// The first key starts no binding; the keys after it are replayed.
+    Unbound(Key),
+}
+/// A change `.` can repeat: the command that made it and the keys typed in the
+/// insert session it started, if any, up to and including `Esc`.
+#[derive(Clone)]
+pub struct Change {
+    pub command: CommandRequest,
+    pub typed: Vec<Key>,
+}
+impl Change {
+    /// Makes the change's command act `count` times instead.
+    pub fn set_count(&mut self, count: u32) {
+        if self.command.args.is_empty() {
+            self.command.args.push(json!({}));
+        }
+        if let Some(args) = self.command.args[0].as_object_mut() {
+            args.insert("count".to_string(), json!(count));
+        }
+    }
+}
+
 pub struct PendingState {
     pub count: Option<u32>,
+    /// Count digits typed since the last operator, not yet folded into `count`.
+    pub digits: Option<u32>,
     pub operator: Option<String>,
     pub modifier: Option<String>,
     pub motion: Option<String>,
+    /// Set after `f`, `t`, `F` or `T` until the character to find is typed, or after
+    /// a modifier until the text object is.
+    pub awaiting_char: bool,
+    pub char: Option<char>,
+    /// The register named with `"` for the next operator or put.
+    pub register: Option<char>,
+    /// Set after `"` until the register's name is typed.
+    pub awaiting_register: bool,
+    /// `q` or `@` until the name of the register to record into or play is typed.
+    pub awaiting_macro: Option<char>,
 }
 impl PendingState {
     pub fn new() -> Self {
         Self {
             count: None,
+            digits: None,
             operator: None,
             modifier: None,
             motion: None,
+            awaiting_char: false,
+            char: None,
+            register: None,
+            awaiting_register: false,
+            awaiting_macro: None,
         }
     }
 }
@@ -212,3 +995,261 @@ impl Default for PendingState {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        commands::{
+            CommandRegistry, command_dispatcher::CommandDispatcher, editor::EditorCommands,
+        },
+        config::Config,
+        engine::document::DocumentData,
+        input::macros::text_to_keys,
+        render::UI,
+    };
+
+    fn config() -> Config {
+        let styles = [("background", "#1D1D1D"), ("foreground", "#F54927")];
+        Config {
+            init_location: None,
+            keybinds: HashMap::new(),
+            settings: HashMap::new(),
+            styles: styles.map(|(k, v)| (k.to_string(), v.to_string())).into(),
+            commands: HashMap::new(),
+        }
+    }
+
+    fn engine() -> Engine {
+        Engine::new(config(), None)
+    }
+
+    fn command(id: &str) -> Token {
+        Token::Command(CommandRequest {
+            id: id.to_string(),
+            args: vec![],
+        })
+    }
+
+    /// An input engine in `mode` with `bindings` of key sequences to command ids
+    /// added to the keymap of `scope`.
+    fn input(
+        engine: &mut Engine,
+        mode: ModeType,
+        scope: KeybindScope,
+        bindings: &[(&str, &str)],
+    ) -> InputEngine {
+        let mut input = InputEngine::new();
+        input.set_mode(mode, engine);
+        bind(&mut input, engine, scope, bindings);
+        input
+    }
+
+    fn normal(engine: &mut Engine, bindings: &[(&str, &str)]) -> InputEngine {
+        input(engine, ModeType::Normal, KeybindScope::Global, bindings)
+    }
+
+    fn bind(
+        input: &mut InputEngine,
+        engine: &mut Engine,
+        scope: KeybindScope,
+        bindings: &[(&str, &str)],
+    ) {
+        input
+            .edit_keymap(engine, &scope, |keymap| {
+                for (keys, id) in bindings {
+                    bind_chars(&mut keymap.children, keys, command(id));
+                }
+            })
+            .unwrap();
+    }
+
+    /// Feeds `text` as typed keys along with any keys they replay, returning the ids
+    /// of the commands they made.
+    fn feed(input: &mut InputEngine, engine: &mut Engine, text: &str) -> Vec<String> {
+        let mut commands = vec![];
+        for key in text_to_keys(text).unwrap() {
+            commands.extend(input.feed(key, engine).unwrap());
+            commands.extend(replay(input, engine));
+        }
+        commands.into_iter().map(|command| command.id).collect()
+    }
+
+    fn replay(input: &mut InputEngine, engine: &mut Engine) -> Vec<CommandRequest> {
+        let mut commands = vec![];
+        while let Some(key) = input.take_replay() {
+            commands.extend(input.feed_replayed(key, engine).unwrap());
+        }
+        commands
+    }
+
+    fn expire(input: &mut InputEngine, engine: &mut Engine) -> Vec<String> {
+        input.timeout = Duration::ZERO;
+        let mut commands = Vec::from_iter(input.check_timeout(engine).unwrap());
+        commands.extend(replay(input, engine));
+        commands.into_iter().map(|command| command.id).collect()
+    }
+
+    fn text(engine: &mut Engine) -> Vec<String> {
+        match &engine.get_current_window().1.data {
+            DocumentData::Text(data) => data.data.clone(),
+            _ => unreachable!(),
+        }
+    }
+
+    /// A text document in normal mode with the editor commands, run as the main loop
+    /// runs the commands keys make.
+    struct Editor {
+        engine: Engine,
+        input: InputEngine,
+        ui: UI,
+        commands: CommandDispatcher,
+    }
+    impl Editor {
+        fn new(lines: &[&str]) -> Self {
+            let mut engine = engine();
+            if let DocumentData::Text(data) = &mut engine.get_current_window().1.data {
+                data.data = lines.iter().map(|line| line.to_string()).collect();
+            }
+            let mut input = InputEngine::new();
+            input.set_mode(ModeType::Normal, &mut engine);
+            let mut commands = CommandDispatcher::new();
+            EditorCommands::register_commands(&mut commands).unwrap();
+            Self {
+                engine,
+                input,
+                ui: UI::headless(&config()),
+                commands,
+            }
+        }
+
+        /// Types `keys`, returning the lines of the document after.
+        fn keys(&mut self, keys: &str) -> Vec<String> {
+            for key in text_to_keys(keys).unwrap() {
+                let command = self.input.feed(key, &mut self.engine).unwrap();
+                self.run(command);
+                while let Some(key) = self.input.take_replay() {
+                    let command = self.input.feed_replayed(key, &mut self.engine).unwrap();
+                    self.run(command);
+                }
+            }
+            text(&mut self.engine)
+        }
+
+        fn run(&mut self, command: Option<CommandRequest>) {
+            if let Some(command) = command {
+                let (engine, input, ui) = (&mut self.engine, &mut self.input, &mut self.ui);
+                self.commands.dispatch(&command, engine, input, ui).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn dot_repeats_a_change_with_its_insert_session() {
+        let mut editor = Editor::new(&["one two", "three four"]);
+        assert_eq!(editor.keys("cwxy<esc>"), ["xy two", "three four"]);
+        assert_eq!(editor.keys("j0."), ["xy two", "xy four"]);
+        // moving and yanking change nothing to repeat
+        assert_eq!(editor.keys("wyw."), ["xy two", "xy xy"]);
+    }
+
+    #[test]
+    fn count_given_to_dot_replaces_the_changes() {
+        let mut editor = Editor::new(&["a b c d e f g"]);
+        assert_eq!(editor.keys("dw"), ["b c d e f g"]);
+        assert_eq!(editor.keys("2."), ["d e f g"]);
+        assert_eq!(editor.keys("."), ["f g"]);
+        let change = editor.input.last_change.as_ref().unwrap();
+        assert_eq!(change.command.args[0]["count"], 2);
+    }
+
+    #[test]
+    fn dot_repeats_a_visual_operator_on_as_much_from_the_cursor() {
+        let mut editor = Editor::new(&["abcdef", "ghijkl"]);
+        assert_eq!(editor.keys("vld"), ["cdef", "ghijkl"]);
+        assert_eq!(editor.keys("."), ["ef", "ghijkl"]);
+        assert_eq!(editor.keys("vjd"), ["hijkl"]);
+
+        let mut editor = Editor::new(&["1", "2", "3", "4", "5"]);
+        assert_eq!(editor.keys("Vjd."), ["5"]);
+
+        let mut editor = Editor::new(&["abcd", "efgh"]);
+        assert_eq!(editor.keys("vlcX<esc>"), ["Xcd", "efgh"]);
+        assert_eq!(editor.keys("j0."), ["Xcd", "Xgh"]);
+    }
+
+    #[test]
+    fn lookup_takes_the_token_from_the_first_layer_binding_the_keys() {
+        let mut first = ActionNode::new();
+        bind_chars(&mut first.children, ",a", command("first"));
+        let mut second = ActionNode::new();
+        bind_chars(&mut second.children, ",a", command("second"));
+        bind_chars(&mut second.children, ",ab", command("longer"));
+        let keys = text_to_keys(",a").unwrap();
+
+        let (token, continues) = lookup(&[first.clone(), second.clone()], &keys);
+        assert!(matches!(token, Some(Token::Command(cmd)) if cmd.id == "first"));
+        assert!(continues);
+        let (token, continues) = lookup(&[first], &keys);
+        assert!(matches!(token, Some(Token::Command(cmd)) if cmd.id == "first"));
+        assert!(!continues);
+    }
+
+    #[test]
+    fn timeout_fires_the_shorter_binding_and_replays_the_rest() {
+        let mut engine = engine();
+        let bindings = [(",a", "short"), (",amc", "long"), ("m", "after")];
+        let mut input = normal(&mut engine, &bindings);
+
+        assert!(feed(&mut input, &mut engine, ",am").is_empty());
+        assert_eq!(expire(&mut input, &mut engine), ["short", "after"]);
+        assert!(input.held.is_empty());
+        // the whole sequence still fires without waiting
+        assert_eq!(feed(&mut input, &mut engine, ",amc"), ["long"]);
+    }
+
+    #[test]
+    fn document_binding_wins_over_the_global_one() {
+        let mut engine = engine();
+        let doc_id = engine.get_current_window().1.id.clone();
+        let global = [(",a", "global"), (",b", "global")];
+        let mut input = normal(&mut engine, &global);
+        let doc = KeybindScope::Buffer(doc_id);
+        bind(&mut input, &mut engine, doc, &[(",a", "doc")]);
+
+        assert_eq!(feed(&mut input, &mut engine, ",a"), ["doc"]);
+        assert_eq!(feed(&mut input, &mut engine, ",b"), ["global"]);
+    }
+
+    #[test]
+    fn prefix_without_a_binding_waits_past_the_timeout() {
+        let mut engine = engine();
+        let bindings = [(",ab", "leader")];
+        let mut input = normal(&mut engine, &bindings);
+
+        assert!(feed(&mut input, &mut engine, ",").is_empty());
+        assert!(expire(&mut input, &mut engine).is_empty());
+        assert!(feed(&mut input, &mut engine, "a").is_empty());
+        assert!(expire(&mut input, &mut engine).is_empty());
+        assert_eq!(feed(&mut input, &mut engine, "b"), ["leader"]);
+    }
+
+    #[test]
+    fn typed_text_starting_a_failed_mapping_is_typed_back() {
+        let mut engine = engine();
+        let bindings = [("jk", "escape")];
+        let scope = KeybindScope::Mode(ModeType::Input);
+        let mut input = input(&mut engine, ModeType::Input, scope, &bindings);
+
+        assert!(feed(&mut input, &mut engine, "j").is_empty());
+        assert!(text(&mut engine).is_empty());
+        assert!(feed(&mut input, &mut engine, "jx").is_empty());
+        assert_eq!(text(&mut engine), ["jjx"]);
+        // a held key that times out is typed too
+        assert!(feed(&mut input, &mut engine, "j").is_empty());
+        assert!(expire(&mut input, &mut engine).is_empty());
+        assert_eq!(text(&mut engine), ["jjxj"]);
+        assert_eq!(feed(&mut input, &mut engine, "jk"), ["escape"]);
+        assert_eq!(text(&mut engine), ["jjxj"]);
+    }
+}
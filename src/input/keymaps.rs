@@ -1,6 +1,32 @@
 use std::collections::HashMap;
 
-use crate::{commands::Key, input::Token};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    commands::Key,
+    engine::{
+        WindowId,
+        document::{DocId, DocType},
+    },
+    input::{Token, input_engine::ModeType},
+};
+
+/// Which keymap a binding lives in. The keymaps are layered, and the first one in
+/// this order that binds a key decides what it does: the buffer's, those of its
+/// document type, the window's, the current mode's and the global one.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum KeybindScope {
+    Global,
+    /// Every document of a type, including ones opened later.
+    DocType(DocType),
+    /// One window by its id.
+    Window(WindowId),
+    /// One document by its id.
+    Buffer(DocId),
+    Mode(ModeType),
+}
 
 #[derive(Clone, Debug)]
 pub struct ActionNode {
@@ -14,6 +40,39 @@ impl ActionNode {
             children: HashMap::new()
         }
     }
+    /// The node reached by following `keys` from this one.
+    pub fn find(&self, keys: &[Key]) -> Option<&ActionNode> {
+        keys.iter().try_fold(self, |node, key| node.children.get(key))
+    }
+    /// Unbinds exactly `keys`, pruning nodes left with nothing below them, and
+    /// returns what they were bound to.
+    pub fn remove(&mut self, keys: &[Key]) -> Option<Token> {
+        let (first, rest) = keys.split_first()?;
+        let child = self.children.get_mut(first)?;
+        let removed = if rest.is_empty() {
+            child.action.take()
+        } else {
+            child.remove(rest)
+        };
+        if child.action.is_none() && child.children.is_empty() {
+            self.children.remove(first);
+        }
+        removed
+    }
+    /// Every key sequence in the tree that ends in an action.
+    pub fn bindings(&self) -> Vec<(Vec<Key>, &Token)> {
+        let mut out = vec![];
+        for (key, child) in &self.children {
+            if let Some(action) = &child.action {
+                out.push((vec![*key], action));
+            }
+            for (mut keys, action) in child.bindings() {
+                keys.insert(0, *key);
+                out.push((keys, action));
+            }
+        }
+        out
+    }
 }
 pub trait KeymapProvider {
     fn define_keymap(&self) -> ActionNode;
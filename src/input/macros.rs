@@ -0,0 +1,95 @@
+use crate::commands::{Key, KeyCode, Modifiers, parse_key};
+
+/// Keys typed since `q` started recording, and the register they will go in.
+pub struct Recording {
+    pub register: char,
+    pub keys: Vec<Key>,
+}
+
+/// Writes keys as the text of a macro register: characters as themselves and other
+/// keys by name in angle brackets, e.g. `dw<esc>j` or `<ctrl+r>`; `<` is `<lt>`.
+pub fn keys_to_text(keys: &[Key]) -> String {
+    keys.iter()
+        .map(|key| match key.code {
+            KeyCode::Char('<') if key.modifiers.is_empty() => "<lt>".to_string(),
+            KeyCode::Char(c) if key.modifiers.is_empty() => c.to_string(),
+            _ => format!("<{}>", key),
+        })
+        .collect()
+}
+
+/// Reads the text of a macro register back into keys; a line break is Enter.
+pub fn text_to_keys(text: &str) -> Result<Vec<Key>, String> {
+    let plain = |code| Key {
+        code,
+        modifiers: Modifiers::empty(),
+    };
+    let mut keys = vec![];
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        let key = match c {
+            '\n' => plain(KeyCode::Enter),
+            '<' => {
+                let rest = chars.as_str();
+                let end = rest
+                    .find('>')
+                    .ok_or_else(|| format!("`<{}` is missing its `>`", rest))?;
+                let name = &rest[..end];
+                chars = rest[end + 1..].chars();
+                match name {
+                    "lt" => plain(KeyCode::Char('<')),
+                    _ => parse_key(name).map_err(|e| format!("in `<{}>`: {}", name, e))?,
+                }
+            }
+            c => plain(KeyCode::Char(c)),
+        };
+        keys.push(key);
+    }
+    Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_round_trip_through_text() {
+        for text in [
+            "dw<esc>j",
+            "0f,<ctrl+r>x",
+            "a<lt>b>",
+            "é <tab><alt+shift+x>",
+        ] {
+            let keys = text_to_keys(text).unwrap();
+            assert_eq!(keys_to_text(&keys), text);
+        }
+    }
+
+    #[test]
+    fn names_in_brackets_are_keys() {
+        let keys = text_to_keys("i<esc><lt>\n").unwrap();
+        let codes: Vec<KeyCode> = keys.iter().map(|k| k.code).collect();
+        assert_eq!(
+            codes,
+            [
+                KeyCode::Char('i'),
+                KeyCode::Esc,
+                KeyCode::Char('<'),
+                KeyCode::Enter
+            ]
+        );
+        assert_eq!(
+            text_to_keys("<ctrl+r>").unwrap()[0].modifiers,
+            Modifiers::CTRL
+        );
+        // a line break reads as Enter and is written back by name
+        assert_eq!(keys_to_text(&keys), "i<esc><lt><enter>");
+    }
+
+    #[test]
+    fn bad_names_are_errors() {
+        assert!(text_to_keys("a<esc").is_err());
+        assert!(text_to_keys("<nope>").is_err());
+        assert_eq!(text_to_keys("").unwrap(), []);
+    }
+}
@@ -1,7 +1,9 @@
 use crate::commands::command_dispatcher::CommandRequest;
 
+pub mod command_line;
 pub mod input_engine;
 pub mod keymaps;
+pub mod macros;
 pub type Operator = String;
 pub type Motion = String;
 
@@ -12,3 +14,14 @@ pub enum Token {
     Motion(Motion),
     Command(CommandRequest),
 }
+impl Token {
+    /// What a token other than a command does, e.g. `motion w`.
+    pub fn builtin_name(&self) -> Option<String> {
+        match self {
+            Token::Digit(_) => Some("count".to_string()),
+            Token::Operator(operator) => Some(format!("operator {}", operator)),
+            Token::Motion(motion) => Some(format!("motion {}", motion)),
+            Token::Command(_) => None,
+        }
+    }
+}
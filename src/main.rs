@@ -4,22 +4,24 @@ use std::{
     ffi::CString,
     fs::File,
     io::{Write, stdout},
-    path::PathBuf,
+    path::Path,
+    time::Duration,
 };
 
 use crossterm::{
     ExecutableCommand, QueueableCommand,
     cursor::{self, Hide, Show},
-    event::{DisableMouseCapture, EnableMouseCapture},
+    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
     terminal::{Clear, ClearType, disable_raw_mode, enable_raw_mode},
 };
 use log::LevelFilter;
 use pyo3::{
-    Py, Python,
+    Python,
     types::{PyAnyMethods, PyModuleMethods},
 };
 use simplelog::WriteLogger;
 pub mod api;
+pub mod cli;
 pub mod commands;
 pub mod config;
 pub mod engine;
@@ -27,27 +29,48 @@ pub mod input;
 pub mod render;
 
 use crate::{
-    commands::command_dispatcher::{
-        ApiContext, CommandDispatcher, CommandFunction, CommandRequest,
+    api::{API, rpc::RpcServer, stubs},
+    cli::Args,
+    commands::{
+        CommandRegistry,
+        command_dispatcher::{
+            CommandContext, CommandDispatcher, CommandFunction, CommandMeta, CommandRequest,
+            CommandResult,
+        },
+        editor::EditorCommands,
+        ex::ExCommands,
     },
     config::Config,
-    engine::{Engine, parse::parse_csv_to_doc},
+    engine::{
+        Engine, Input,
+        document::DocType,
+        messages::{Message, MessageLevel},
+        parse::parse_csv_to_doc,
+    },
     input::input_engine::InputEngine,
     render::UI,
 };
+/// How long the main loop waits for terminal input before servicing other sources.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
 fn main() -> Result<(), String> {
     init_logger();
-    let mut _args: Vec<String> = env::args().collect();
-    if _args.len() == 1 {
-        _args.push(String::new())
+    let args = Args::parse(env::args())?;
+    if let Some(path) = &args.gen_stubs {
+        return std::fs::write(path, stubs::generate(&API::new().describe()))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e));
+    }
+    if args.headless {
+        return run_headless(args);
     }
-    _args[1] = "./test.csv".to_string();
     enable_raw_mode().unwrap();
     stdout().execute(Hide).unwrap();
     stdout().execute(EnableMouseCapture).unwrap();
+    stdout().execute(EnableBracketedPaste).unwrap();
 
-    let res = main_loop(_args);
+    let res = main_loop(args);
 
+    stdout().execute(DisableBracketedPaste).unwrap();
     stdout().execute(DisableMouseCapture).unwrap();
     stdout().execute(Show).unwrap();
     disable_raw_mode().map_err(|e| e.to_string())?;
@@ -67,86 +90,261 @@ fn main() -> Result<(), String> {
     Ok(())
 }
 
-fn main_loop(args: Vec<String>) -> Result<(), String> {
+fn main_loop(args: Args) -> Result<(), String> {
+    let mut server = args.listen.as_deref().map(RpcServer::bind).transpose()?;
     let config = setup_config();
     let mut ui = setup_ui(&config);
     let mut input_engine = setup_input_engine(&config);
     let mut command_dispatcher = setup_command_dispatcher(&config);
-    let mut engine = setup_engine(config, args);
+    let mut engine = setup_engine(config, args)?;
     ui.handle_events(&mut engine);
     ui.draw(&mut engine, &input_engine);
     log::info!("Successfully created engines");
-    _ =command_dispatcher.dispatch(
-        &CommandRequest {
-            id: "init".to_string(),
-            args: vec![],
-        },
-        &mut engine,
-        &mut input_engine,
-        &mut ui,
-    );
+    let init = CommandRequest {
+        id: "init".to_string(),
+        args: vec![],
+    };
+    let res = command_dispatcher.dispatch(&init, &mut engine, &mut input_engine, &mut ui);
+    report_command_result(&init, res, &mut engine);
+    command_dispatcher.flush_events(&mut engine, &mut input_engine, &mut ui);
     // initial commands before awaiting an input;
     loop {
-        if let Some(key) = engine.process_input()?
-            && let Some(cmd) = input_engine.feed(key, &mut engine)?
-        {
-            let res = command_dispatcher.dispatch(&cmd, &mut engine, &mut input_engine, &mut ui);
-            match res {
-                Ok(_) => log::info!("OK running command {:?}", cmd.id),
-                Err(err) => log::warn!("failed running command {:?}: {:?}", cmd.id, err),
+        let mut dirty = false;
+        if engine.has_input(POLL_INTERVAL)? {
+            dirty = true;
+            match engine.process_input()? {
+                Some(Input::Key(key)) => {
+                    let cmd = input_engine.feed(key, &mut engine);
+                    run_input_command(
+                        cmd,
+                        &mut engine,
+                        &mut input_engine,
+                        &mut ui,
+                        &mut command_dispatcher,
+                    );
+                }
+                Some(Input::Mouse(event)) => ui.handle_mouse(&mut engine, &mut input_engine, event),
+                Some(Input::Paste(text)) => {
+                    let cmd = input_engine.paste(text, &mut engine);
+                    run_input_command(
+                        cmd,
+                        &mut engine,
+                        &mut input_engine,
+                        &mut ui,
+                        &mut command_dispatcher,
+                    );
+                }
+                None => {}
             }
         }
+        let cmd = input_engine.check_timeout(&mut engine);
+        dirty |= matches!(cmd, Ok(Some(_)));
+        run_input_command(
+            cmd,
+            &mut engine,
+            &mut input_engine,
+            &mut ui,
+            &mut command_dispatcher,
+        );
+        while let Some(key) = input_engine.take_replay() {
+            dirty = true;
+            let cmd = input_engine.feed_replayed(key, &mut engine);
+            run_input_command(
+                cmd,
+                &mut engine,
+                &mut input_engine,
+                &mut ui,
+                &mut command_dispatcher,
+            );
+        }
+        let doc_type = engine.get_current_window().1.doc_type.clone();
+        dirty |= input_engine.update_which_key(&mut engine, |id| {
+            command_dispatcher.title(id, &doc_type)
+        });
+        if let Some(server) = server.as_mut() {
+            dirty |= server.poll(
+                &mut engine,
+                &mut input_engine,
+                &mut ui,
+                &mut command_dispatcher,
+            );
+        }
+        dirty |= engine.messages.expire();
+        let events = command_dispatcher.flush_events(&mut engine, &mut input_engine, &mut ui);
+        if let Some(server) = server.as_mut() {
+            server.notify(&events);
+        }
+        engine.confirm_quit();
         if engine.should_quit {
             break;
         }
-        ui.handle_events(&mut engine);
-        ui.draw(&mut engine, &input_engine);
+        if dirty || !events.is_empty() {
+            ui.handle_events(&mut engine);
+            ui.draw(&mut engine, &input_engine);
+        }
     }
     Ok(())
 }
 
-fn setup_input_engine(_config: &Config) -> InputEngine {
-    InputEngine::new()
+/// Dispatches the command the input engine made of a key, if any.
+fn run_input_command(
+    cmd: Result<Option<CommandRequest>, String>,
+    engine: &mut Engine,
+    input_engine: &mut InputEngine,
+    ui: &mut UI,
+    command_dispatcher: &mut CommandDispatcher,
+) {
+    match cmd {
+        Ok(Some(cmd)) => {
+            let res = command_dispatcher.dispatch(&cmd, engine, input_engine, ui);
+            report_command_result(&cmd, res, engine);
+        }
+        Ok(None) => {}
+        Err(err) => engine.notify(Message::new(MessageLevel::Error, err)),
+    }
+}
+
+/// Logs a dispatched command's outcome and shows failures in the message area.
+fn report_command_result(cmd: &CommandRequest, res: CommandResult, engine: &mut Engine) {
+    match res {
+        Ok(_) => log::info!("OK running command {:?}", cmd.id),
+        Err(err) => {
+            log::warn!("failed running command {:?}: {}", cmd.id, err);
+            engine.notify(Message::new(
+                MessageLevel::Error,
+                format!("{}: {}", cmd.id, err),
+            ));
+        }
+    }
+}
+
+/// Runs `--script` against the document without a terminal; any error, including an
+/// uncaught Python exception, is returned so the process exits non-zero.
+fn run_headless(args: Args) -> Result<(), String> {
+    let script = args
+        .script
+        .clone()
+        .ok_or_else(|| "--headless requires --script <file>".to_string())?;
+    let config = setup_config();
+    let mut ui = UI::headless(&config);
+    let mut input_engine = setup_input_engine(&config);
+    let mut command_dispatcher = setup_command_dispatcher(&config);
+    let mut engine = setup_engine(config, args)?;
+    command_dispatcher.register(
+        "script",
+        None,
+        CommandFunction::Rust(Box::new(move |api, _params| run_python_file(api, &script))),
+        CommandMeta::new("Run Script", "Run the --script file."),
+    );
+    let res = command_dispatcher.dispatch(
+        &CommandRequest {
+            id: "script".to_string(),
+            args: vec![],
+        },
+        &mut engine,
+        &mut input_engine,
+        &mut ui,
+    );
+    command_dispatcher.flush_events(&mut engine, &mut input_engine, &mut ui);
+    // there is no footer to show messages in, so hand them to the caller
+    for message in engine.messages.history() {
+        eprintln!("[{}] {}", message.level.name(), message.text);
+    }
+    res.map(|_| ()).map_err(|e| e.to_string())
+}
+
+fn setup_input_engine(config: &Config) -> InputEngine {
+    let mut input_engine = InputEngine::new();
+    if let Some(ms) = config.settings.get("timeoutlen").and_then(|ms| ms.parse().ok()) {
+        input_engine.timeout = Duration::from_millis(ms);
+    }
+    if let Some(ms) = config.settings.get("whichkeydelay").and_then(|ms| ms.parse().ok()) {
+        input_engine.which_key_delay = Duration::from_millis(ms);
+    }
+    input_engine
 }
 fn setup_command_dispatcher(_config: &Config) -> CommandDispatcher {
     let mut cmd_disp = CommandDispatcher::new();
 
-    cmd_disp.register_global("kill", CommandFunction::Internal("kill".to_string(), None));
-    cmd_disp.register_global(
+    cmd_disp.register(
+        "kill",
+        None,
+        CommandFunction::Internal("kill".to_string(), None),
+        CommandMeta::new("Quit", "Quit the editor."),
+    );
+    cmd_disp.register(
+        "buffer.close",
+        None,
+        CommandFunction::Internal("doc.close".to_string(), None),
+        CommandMeta::new("Close Buffer", "Close the current document."),
+    );
+    cmd_disp.register(
+        "buffer.save",
+        None,
+        CommandFunction::Internal("doc.save".to_string(), None),
+        CommandMeta::new("Save Buffer", "Write the current document to its file."),
+    );
+    cmd_disp.register(
+        "palette.open",
+        None,
+        CommandFunction::Internal("palette.open".to_string(), None),
+        CommandMeta::new("Command Palette", "Search and run a command."),
+    );
+    cmd_disp.register(
+        "palette.accept",
+        Some(DocType::Palette),
+        CommandFunction::Internal("palette.accept".to_string(), None),
+        CommandMeta::new("Run Command", "Run the highlighted palette entry."),
+    );
+    cmd_disp.register(
+        "palette.close",
+        Some(DocType::Palette),
+        CommandFunction::Internal("palette.close".to_string(), None),
+        CommandMeta::new("Close Palette", "Close the palette without running anything."),
+    );
+    ExCommands::register_commands(&mut cmd_disp).expect("ex commands register");
+    EditorCommands::register_commands(&mut cmd_disp).expect("editor commands register");
+    cmd_disp.register(
         "init",
-        CommandFunction::Rust(Box::new(|api, params| {
-            Python::attach(|py| {
-                // Create the API object
-                let api = api.to_py_api()?;
-
-                // Get the __main__ module's globals
-                let main_module = py
-                    .import("__main__")
-                    .map_err(|e| format!("Failed to import __main__: {}", e))?;
-
-                let globals = main_module.dict();
-
-                // Add the API to globals as 'api'
-                globals
-                    .set_item("api", api)
-                    .map_err(|e| format!("Failed to set api in globals: {}", e))?;
-                let init_path = "./test/init.py";
-                // Read the init.py file
-                let code = std::fs::read_to_string(init_path)
-                    .map_err(|e| format!("Failed to read {}: {}", init_path, e))?;
-                let code_cstr = CString::new(code)
-                    .map_err(|e| format!("Failed to convert code to CString: {}", e))?;
-                // Execute the init.py file with the globals containing 'api'
-                py.run(code_cstr.as_c_str(), Some(&globals), None)
-                    .map_err(|e| format!("Failed to execute {}: {}", init_path, e))?;
-
-                Ok(None)
-            })
+        None,
+        CommandFunction::Rust(Box::new(|api, _params| {
+            run_python_file(api, Path::new("./test/init.py"))
         })),
+        CommandMeta::new("Run init.py", "Run the startup script again."),
     );
     cmd_disp
 }
 
+/// Runs a Python file in `__main__` with the API bound to the global `api`.
+fn run_python_file(api: &mut CommandContext, path: &Path) -> CommandResult {
+    Python::attach(|py| {
+        // Create the API object
+        let api = api.to_py_api()?;
+
+        // Get the __main__ module's globals
+        let main_module = py
+            .import("__main__")
+            .map_err(|e| format!("Failed to import __main__: {}", e))?;
+
+        let globals = main_module.dict();
+
+        // Add the API to globals as 'api'
+        globals
+            .set_item("api", api)
+            .map_err(|e| format!("Failed to set api in globals: {}", e))?;
+        // Read the file
+        let code = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let code_cstr =
+            CString::new(code).map_err(|e| format!("Failed to convert code to CString: {}", e))?;
+        // Execute the file with the globals containing 'api'
+        py.run(code_cstr.as_c_str(), Some(&globals), None)
+            .map_err(|e| format!("Failed to execute {}: {}", path.display(), e))?;
+
+        Ok(None)
+    })
+}
+
 fn setup_config() -> config::Config {
     let mut config = config::Config {
         init_location: None,
@@ -164,19 +362,26 @@ fn setup_config() -> config::Config {
     config
         .styles
         .insert("foreground".to_string(), "#F54927".to_string());
+    config
+        .styles
+        .insert("selection".to_string(), "#5C2A20".to_string());
+    config
+        .styles
+        .insert("warning".to_string(), "#F5A524".to_string());
+    config
+        .styles
+        .insert("error".to_string(), "#E5484D".to_string());
 
     config
 }
-fn setup_engine(config: Config, args: Vec<String>) -> Engine {
+fn setup_engine(config: Config, args: Args) -> Result<Engine, String> {
     let mut doc = None;
-    if !args.is_empty() && args[1].contains(".csv") {
-        doc = Some(
-            parse_csv_to_doc(PathBuf::from(args[1].clone()))
-                .map_err(|e| println!("{:?}", e.to_string()))
-                .unwrap(),
-        );
+    if let Some(file) = args.file
+        && file.extension().is_some_and(|ext| ext == "csv")
+    {
+        doc = Some(parse_csv_to_doc(file).map_err(|e| e.to_string())?);
     }
-    Engine::new(config, doc)
+    Ok(Engine::new(config, doc))
 }
 fn setup_ui(config: &Config) -> UI {
     UI::new(config)
@@ -1,9 +1,10 @@
 use crossterm::style::Attribute;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::render::{Rect, screen_buffer::ScreenBuffer};
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum BorderStyle {
     Single,
@@ -88,6 +89,11 @@ pub fn draw_border(
     buffer.cells[b][r].attrs = attrs.clone();
 
     // Return inner rect (inside border)
+    inner_rect(rect)
+}
+
+/// The part of `rect` inside the border `draw_border` draws around it.
+pub fn inner_rect(rect: &Rect) -> Rect {
     Rect {
         x: rect.x + 1,
         y: rect.y + 1,
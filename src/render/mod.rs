@@ -1,16 +1,33 @@
-use crossterm::{event::KeyEvent, style::Attribute};
-use std::{collections::HashMap, env, path::PathBuf};
+use crossterm::{
+    event::{KeyEvent, MouseEvent, MouseEventKind},
+    style::{Attribute, Color},
+};
+use std::{
+    collections::HashMap,
+    env,
+    io::{Write, stdout},
+    path::PathBuf,
+};
 
 use crate::{
     config::Config,
-    engine::{Engine, EngineEvent, WindowId, layout::LayoutNode, popup::RelativeTo},
+    engine::{
+        Engine, EngineEvent, WindowId, WindowState,
+        document::{Document, DocumentData},
+        layout::LayoutNode,
+        messages::{Message, MessageLevel},
+        motion::line_len,
+        popup::{PopupWindow, RelativeTo},
+    },
     input::input_engine::{InputEngine, ModeType},
     render::{
+        helpers::draw_border,
         screen_buffer::ScreenBuffer,
         styling::hex_to_color,
-        windows::{info::InfoWindow, table::TableWindow, text::TextWindow},
+        windows::{info::InfoWindow, palette::PaletteWindow, table::TableWindow, text::TextWindow},
     },
 };
+pub mod clipboard;
 pub mod helpers;
 pub mod screen_buffer;
 pub mod styling;
@@ -24,9 +41,15 @@ pub struct Rect {
     pub height: usize,
 }
 
+/// Columns and rows of the screen when there is no terminal, as under `--headless`.
+pub const HEADLESS_SIZE: (u16, u16) = (80, 24);
+
 pub struct UI {
     pub windows: HashMap<WindowId, Box<dyn Window>>,
     pub screen_buffer: ScreenBuffer,
+    headless: bool,
+    /// The window a mouse button went down in, which gets the drag until it is released.
+    mouse_window: Option<WindowId>,
 }
 impl UI {
     pub fn new(config: &Config) -> Self {
@@ -34,7 +57,31 @@ impl UI {
         Self {
             windows: HashMap::new(),
             screen_buffer: ScreenBuffer::new(size.0, size.1, config),
+            headless: false,
+            mouse_window: None,
+        }
+    }
+
+    /// A UI that is never drawn, for running without a terminal.
+    pub fn headless(config: &Config) -> Self {
+        Self {
+            windows: HashMap::new(),
+            screen_buffer: ScreenBuffer::new(HEADLESS_SIZE.0, HEADLESS_SIZE.1, config),
+            headless: true,
+            mouse_window: None,
+        }
+    }
+
+    /// Copies `text` to the system clipboard through the terminal.
+    pub fn set_clipboard(&self, text: &str) -> Result<(), String> {
+        if self.headless {
+            return Ok(());
         }
+        let mut stdout = stdout();
+        stdout
+            .write_all(clipboard::osc52(text).as_bytes())
+            .and_then(|_| stdout.flush())
+            .map_err(|e| e.to_string())
     }
 
     pub fn draw(&mut self, engine: &mut Engine, input_engine: &InputEngine) {
@@ -52,46 +99,205 @@ impl UI {
         self.draw_footer(engine, input_engine, &mut rect);
         self.draw_layout_node(engine, &rect, &layout.unwrap());
         _ = self.draw_popups(engine, &rect);
+        self.draw_notifications(engine, &rect);
+        self.draw_which_key(engine, input_engine, &rect);
         self.screen_buffer.flush();
     }
 
+    /// Hands a mouse event to the window under the pointer, or to the one a drag
+    /// started in, making a clicked window the active one. A selection dragged out
+    /// with the mouse starts visual mode, and a click that clears it leaves it.
+    pub fn handle_mouse(
+        &mut self,
+        engine: &mut Engine,
+        input_engine: &mut InputEngine,
+        event: MouseEvent,
+    ) {
+        let Some(layout) = &engine.layout else {
+            return;
+        };
+        let screen = engine.screen_rect();
+        let tiled = layout.get_rects(&screen);
+        let wins: HashMap<WindowId, Rect> = tiled.iter().cloned().collect();
+        // a popup is drawn over the layout, so its windows are hit first
+        let mut floating = vec![];
+        if let Some(mut popup) = engine.popups.clone()
+            && let Ok(rect) = popup_rect(engine, &mut popup, &screen, &wins)
+        {
+            floating = popup.layout.get_rects(&rect);
+        }
+        let (x, y) = (event.column as usize, event.row as usize);
+        let under = floating
+            .iter()
+            .chain(&tiled)
+            .find(|(_, r)| (r.x..r.x + r.width).contains(&x) && (r.y..r.y + r.height).contains(&y))
+            .map(|(win_id, _)| win_id.clone());
+        let rects: HashMap<WindowId, Rect> = tiled.into_iter().chain(floating).collect();
+        let target = match event.kind {
+            MouseEventKind::Drag(_) | MouseEventKind::Up(_) => {
+                self.mouse_window.clone().or(under)
+            }
+            _ => under,
+        };
+        let Some(win_id) = target else {
+            return;
+        };
+        match event.kind {
+            MouseEventKind::Down(_) => {
+                self.mouse_window = Some(win_id.clone());
+                engine.active_window = win_id.clone();
+                engine.emit(&EngineEvent::LayoutChange);
+            }
+            MouseEventKind::Up(_) => self.mouse_window = None,
+            _ => {}
+        }
+        if let (Some(window), Some(rect)) = (self.windows.get_mut(&win_id), rects.get(&win_id)) {
+            window.handle_mouse(rect, engine, event);
+        }
+        let (_, doc) = engine.get_current_window();
+        let selecting = doc.data.selection_mut().is_some_and(|s| s.is_some());
+        match (selecting, &input_engine.mode.mode) {
+            (true, ModeType::Normal | ModeType::Input) => {
+                input_engine.set_mode(ModeType::Visualize, engine)
+            }
+            (false, ModeType::Visualize) => input_engine.set_mode(ModeType::Normal, engine),
+            _ => {}
+        }
+    }
+
     pub fn draw_popups(&mut self, engine: &mut Engine, rect: &Rect) -> Result<(), String> {
         let layout = engine.layout.as_ref().ok_or("Engine layout not found")?;
         let wins: HashMap<String, Rect> = layout.get_rects(rect).into_iter().collect();
 
         if let Some(popup) = &mut engine.popups.clone() {
-            let relative_to = popup.relative_to.clone();
-
-            let rect = match relative_to {
-                RelativeTo::Editor => Ok(Rect {
-                    width: popup.width,
-                    height: popup.height,
-                    x: 0,
-                    y: 0,
-                }),
-                RelativeTo::Win(win_id) => wins
-                    .get(&win_id)
-                    .cloned()
-                    .ok_or_else(|| "window not found".to_string()),
-                RelativeTo::Cursor => {
-                    let win = engine.get_current_window().0;
-                    wins.get(&win.id)
-                        .map(|curr_win| Rect {
-                            width: popup.width,
-                            height: popup.height,
-                            x: curr_win.x + win.cursor_col,
-                            y: curr_win.y + win.cursor_row,
-                        })
-                        .ok_or_else(|| "failed to get cursor position".to_string())
-                }
-            }?;
-
-            let popup_rect = popup.get_rect(&rect)?;
+            let popup_rect = popup_rect(engine, popup, rect, &wins)?;
             self.draw_layout_node(engine, &popup_rect, &popup.layout);
         }
 
         Ok(())
     }
+    /// Stacks popup messages in boxes down the top-right corner, newest first.
+    pub fn draw_notifications(&mut self, engine: &mut Engine, rect: &Rect) {
+        let bg = engine.config.get_style_color("background", None);
+        let fg = engine.config.get_style_color("foreground", None);
+        let width = NOTIFICATION_WIDTH.min(rect.width / 2);
+        if width < 3 {
+            return;
+        }
+        let messages: Vec<Message> = engine.messages.popups().cloned().collect();
+        let mut y = rect.y;
+        for message in messages {
+            let lines: Vec<String> = message
+                .text
+                .lines()
+                .flat_map(|line| {
+                    let chars: Vec<char> = line.chars().collect();
+                    chars
+                        .chunks(width - 2)
+                        .map(|chunk| chunk.iter().collect())
+                        .collect::<Vec<String>>()
+                })
+                .take(NOTIFICATION_MAX_LINES)
+                .collect();
+            let height = lines.len() + 2;
+            if y + height > rect.y + rect.height {
+                break;
+            }
+            let color = level_color(&mut engine.config, message.level, fg);
+            let cell = screen_buffer::BufferCell {
+                ch: ' ',
+                fg: color,
+                bg,
+                attrs: vec![],
+            };
+            let outer = Rect {
+                x: rect.x + rect.width - width,
+                y,
+                width,
+                height,
+            };
+            for row in outer.y..outer.y + outer.height {
+                self.screen_buffer.write_section(
+                    row,
+                    outer.x,
+                    width,
+                    screen_buffer::Alignment::Left,
+                    "",
+                    cell.clone(),
+                );
+            }
+            let inner = draw_border(&String::new(), &outer, &mut self.screen_buffer, false, None);
+            for (i, line) in lines.iter().enumerate() {
+                self.screen_buffer.write_section(
+                    inner.y + i,
+                    inner.x,
+                    inner.width,
+                    screen_buffer::Alignment::Left,
+                    line,
+                    cell.clone(),
+                );
+            }
+            y += height;
+        }
+    }
+    /// Lists the keys that can follow a pending sequence in a box at the bottom right.
+    pub fn draw_which_key(&mut self, engine: &mut Engine, input_engine: &InputEngine, rect: &Rect) {
+        let Some(entries) = &input_engine.which_key else {
+            return;
+        };
+        let bg = engine.config.get_style_color("background", None);
+        let fg = engine.config.get_style_color("foreground", None);
+        let key_width = entries
+            .iter()
+            .map(|(key, _)| key.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let lines: Vec<String> = entries
+            .iter()
+            .map(|(key, label)| {
+                format!("{:<width$}  {}", key.to_string(), label, width = key_width)
+            })
+            .collect();
+        let longest = lines.iter().map(|line| line.chars().count()).max();
+        let width = (longest.unwrap_or(0) + 2).min(rect.width / 2);
+        let height = (lines.len() + 2).min(rect.height / 2);
+        if width < 3 || height < 3 {
+            return;
+        }
+        let cell = screen_buffer::BufferCell {
+            ch: ' ',
+            fg,
+            bg,
+            attrs: vec![],
+        };
+        let outer = Rect {
+            x: rect.x + rect.width - width,
+            y: rect.y + rect.height - height,
+            width,
+            height,
+        };
+        for row in outer.y..outer.y + outer.height {
+            self.screen_buffer.write_section(
+                row,
+                outer.x,
+                width,
+                screen_buffer::Alignment::Left,
+                "",
+                cell.clone(),
+            );
+        }
+        let inner = draw_border(&String::new(), &outer, &mut self.screen_buffer, false, None);
+        for (i, line) in lines.iter().take(inner.height).enumerate() {
+            self.screen_buffer.write_section(
+                inner.y + i,
+                inner.x,
+                inner.width,
+                screen_buffer::Alignment::Left,
+                line,
+                cell.clone(),
+            );
+        }
+    }
     pub fn draw_layout_node(&mut self, engine: &mut Engine, rect: &Rect, node: &LayoutNode) {
         let node_rects = node.get_rects(rect);
 
@@ -123,17 +329,25 @@ impl UI {
             None => "---".to_string(),
         };
         let modestr = match input_engine.mode.mode {
-            ModeType::Input => "I",
-            ModeType::Normal => "N",
-            ModeType::Visualize => "V"
+            ModeType::Input => "I".to_string(),
+            ModeType::Normal => "N".to_string(),
+            ModeType::Visualize => match selection_size(w, d) {
+                Some(size) => format!("V {}", size),
+                None => "V".to_string(),
+            },
+            ModeType::Command => "C".to_string(),
+        };
+        let modestr = match &input_engine.recording {
+            Some(recording) => format!("{} rec @{}", modestr, recording.register),
+            None => modestr,
         };
 
         let mode_end = self.screen_buffer.write_section(
             rect.height - 1,
             0,
-            3,
+            modestr.chars().count() + 2,
             screen_buffer::Alignment::Center,
-            modestr,
+            &modestr,
             screen_buffer::BufferCell {
                 ch: ' ',
                 fg,
@@ -142,6 +356,11 @@ impl UI {
             },
         );
         self.screen_buffer.cells[mode_end.0][mode_end.1].ch = '|';
+        if let ModeType::Command = input_engine.mode.mode {
+            self.draw_command_line(input_engine, mode_end.1 + 1, rect, fg, bg);
+            rect.height -= 1;
+            return;
+        }
         let path_end = self.screen_buffer.write_section(
             mode_end.0,
             mode_end.1 + 1,
@@ -156,13 +375,30 @@ impl UI {
             },
         );
         self.screen_buffer.cells[path_end.0][path_end.1].ch = '|';
+        if let Some(message) = engine.messages.current() {
+            let color = level_color(&mut engine.config, message.level, fg);
+            let text = message.text.lines().next().unwrap_or_default();
+            self.screen_buffer.write_section(
+                path_end.0,
+                path_end.1 + 2,
+                rect.width.saturating_sub(path_end.1 + 23),
+                screen_buffer::Alignment::Left,
+                text,
+                screen_buffer::BufferCell {
+                    ch: ' ',
+                    fg: color,
+                    bg,
+                    attrs: vec![],
+                },
+            );
+        }
 
         let start = self.screen_buffer.write_section(
             rect.height - 1,
             rect.width - 20,
             20,
             screen_buffer::Alignment::Right,
-            &doc_type.as_str(),
+            doc_type.as_str(),
             screen_buffer::BufferCell {
                 ch: ' ',
                 fg,
@@ -174,6 +410,37 @@ impl UI {
         rect.height -= 1;
     }
 
+    /// Draws `:` and the command being typed over the rest of the footer.
+    fn draw_command_line(
+        &mut self,
+        input_engine: &InputEngine,
+        col: usize,
+        rect: &Rect,
+        fg: Color,
+        bg: Color,
+    ) {
+        let line = &input_engine.command_line;
+        let row = rect.height - 1;
+        let width = rect.width.saturating_sub(col);
+        let text = format!(":{}", line.text);
+        self.screen_buffer.write_section(
+            row,
+            col,
+            width,
+            screen_buffer::Alignment::Left,
+            &text,
+            screen_buffer::BufferCell {
+                ch: ' ',
+                fg,
+                bg,
+                attrs: vec![],
+            },
+        );
+        if let Some(cell) = self.screen_buffer.get_cell_mut(row, col + 1 + line.cursor) {
+            std::mem::swap(&mut cell.fg, &mut cell.bg);
+        }
+    }
+
     pub fn handle_events(&mut self, engine: &mut Engine) {
         let events: Vec<EngineEvent> = engine.events.drain(..).collect();
         for event in events {
@@ -183,22 +450,30 @@ impl UI {
 
     fn handle_event(&mut self, engine: &mut Engine, event: EngineEvent) {
         match event {
-            EngineEvent::WindowCreate(win_id) => {
-                let doc = &engine.docs[&engine.windows[&win_id].doc_id];
+            EngineEvent::WindowCreate(win_id) | EngineEvent::WindowDocChange(win_id, _) => {
+                let Some(doc) = engine
+                    .windows
+                    .get(&win_id)
+                    .and_then(|win| engine.docs.get(&win.doc_id))
+                else {
+                    return;
+                };
                 let window: Box<dyn Window> = match doc.doc_type {
-                    crate::engine::document::DocType::SpreadSheet => Box::new(TableWindow {
-                        window_id: win_id.clone(),
-                    }),
+                    crate::engine::document::DocType::SpreadSheet => {
+                        Box::new(TableWindow::new(win_id.clone()))
+                    }
                     crate::engine::document::DocType::Info => Box::new(InfoWindow {
                         window_id: win_id.clone(),
                     }),
                     crate::engine::document::DocType::Text => Box::new(TextWindow {
                         window_id: win_id.clone(),
                     }),
+                    crate::engine::document::DocType::Palette => Box::new(PaletteWindow {
+                        window_id: win_id.clone(),
+                    }),
                 };
                 self.windows.insert(win_id, window);
             }
-            EngineEvent::WindowDocChange(_window_id, _doc_id) => {}
             EngineEvent::WindowClose(window_id) => {
                 self.windows.remove(&window_id);
             }
@@ -207,6 +482,64 @@ impl UI {
     }
 }
 
+const NOTIFICATION_WIDTH: usize = 40;
+const NOTIFICATION_MAX_LINES: usize = 4;
+
+/// Where a popup is drawn on `screen`, given the rects of the layout's windows.
+fn popup_rect(
+    engine: &mut Engine,
+    popup: &mut PopupWindow,
+    screen: &Rect,
+    wins: &HashMap<WindowId, Rect>,
+) -> Result<Rect, String> {
+    let rect = match popup.relative_to.clone() {
+        RelativeTo::Editor => Ok(*screen),
+        RelativeTo::Win(win_id) => wins
+            .get(&win_id)
+            .cloned()
+            .ok_or_else(|| "window not found".to_string()),
+        RelativeTo::Cursor => {
+            let win = engine.get_current_window().0;
+            wins.get(&win.id)
+                .map(|curr_win| Rect {
+                    width: popup.width,
+                    height: popup.height,
+                    x: curr_win.x + win.cursor_col,
+                    y: curr_win.y + win.cursor_row,
+                })
+                .ok_or_else(|| "failed to get cursor position".to_string())
+        }
+    }?;
+    popup.get_rect(&rect)
+}
+
+/// The style colour for a message level, falling back to `fg` for plain info.
+fn level_color(config: &mut Config, level: MessageLevel, fg: Color) -> Color {
+    match level {
+        MessageLevel::Info => fg,
+        MessageLevel::Warn => config.get_style_color("warning", Some(fg)),
+        MessageLevel::Error => config.get_style_color("error", Some(fg)),
+    }
+}
+
+/// The size of the window's visual selection for the footer, such as `3R x 2C`.
+fn selection_size(win: &WindowState, doc: &Document) -> Option<String> {
+    match &doc.data {
+        DocumentData::Text(data) => data.selected.map(|selection| {
+            selection.describe((win.cursor_row, win.cursor_col), |row| {
+                line_len(&data.data, row)
+            })
+        }),
+        DocumentData::SpreadSheet(data) => data
+            .selected
+            .map(|selection| selection.describe(data.selected_cell, |_| 0)),
+        _ => None,
+    }
+}
+
 pub trait Window {
     fn draw(&self, rect: &Rect, engine: &mut Engine, buffer: &mut ScreenBuffer);
+    /// Handles a mouse event over the window drawn in `rect`, or one continuing a
+    /// drag that started in it. Windows ignore the mouse unless they say otherwise.
+    fn handle_mouse(&mut self, _rect: &Rect, _engine: &mut Engine, _event: MouseEvent) {}
 }
@@ -44,8 +44,8 @@ impl ScreenBuffer {
         content: &str,
         template_cell: BufferCell,
     ) -> (usize, usize) {
-        let size = content.len();
         let chars: Vec<char> = content.chars().collect();
+        let size = chars.len();
 
         for buf_idx in start_col..start_col + size {
             let mut val = template_cell.clone();
@@ -1,3 +1,4 @@
 pub mod info;
+pub mod palette;
 pub mod table;
 pub mod text;
@@ -0,0 +1,97 @@
+use crate::{
+    engine::{Engine, WindowId, document::DocumentData},
+    render::{
+        Rect, ScreenBuffer, Window,
+        helpers::draw_border,
+        screen_buffer::{Alignment, BufferCell},
+    },
+};
+
+pub struct PaletteWindow {
+    pub window_id: WindowId,
+}
+
+impl Window for PaletteWindow {
+    fn draw(&self, rect: &Rect, engine: &mut Engine, buffer: &mut ScreenBuffer) {
+        let fg = engine.config.get_style_color("foreground", None);
+        let bg = engine.config.get_style_color("background", None);
+        let focused = engine.active_window == self.window_id;
+        let (win, doc) = engine.get_window(&self.window_id);
+        let inner = draw_border(&self.window_id, rect, buffer, focused, win.border_style);
+        let DocumentData::Palette(palette) = &doc.data else {
+            return;
+        };
+        if inner.width == 0 || inner.height == 0 {
+            return;
+        }
+        let cell = BufferCell {
+            ch: ' ',
+            fg,
+            bg,
+            attrs: vec![],
+        };
+        let selected = BufferCell {
+            fg: bg,
+            bg: fg,
+            ..cell.clone()
+        };
+
+        // query line, with the cursor after the typed text
+        let prompt = format!("> {}", palette.query);
+        buffer.write_section(
+            inner.y,
+            inner.x,
+            inner.width,
+            Alignment::Left,
+            &prompt,
+            cell.clone(),
+        );
+        let cursor = prompt.chars().count();
+        if focused && cursor < inner.width {
+            buffer.cells[inner.y][inner.x + cursor] = BufferCell {
+                ch: ' ',
+                ..selected.clone()
+            };
+        }
+
+        // keep the selection on screen
+        let rows = inner.height - 1;
+        let scroll = (palette.selected + 1).saturating_sub(rows);
+        for row in 0..rows {
+            let y = inner.y + 1 + row;
+            let Some(&index) = palette.matches.get(row + scroll) else {
+                buffer.write_section(y, inner.x, inner.width, Alignment::Left, "", cell.clone());
+                continue;
+            };
+            let entry = &palette.entries[index];
+            let template = if row + scroll == palette.selected {
+                selected.clone()
+            } else {
+                cell.clone()
+            };
+            let keys = entry.keys.join(", ");
+            let keys_width = keys.chars().count().min(inner.width / 3);
+            let text_width = inner.width - keys_width;
+            let text = match &entry.description {
+                Some(description) => format!(" {}  {}", entry.label(), description),
+                None => format!(" {}", entry.label()),
+            };
+            buffer.write_section(
+                y,
+                inner.x,
+                text_width,
+                Alignment::Left,
+                &text,
+                template.clone(),
+            );
+            buffer.write_section(
+                y,
+                inner.x + text_width,
+                keys_width,
+                Alignment::Right,
+                &keys,
+                template,
+            );
+        }
+    }
+}
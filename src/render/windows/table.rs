@@ -4,16 +4,47 @@ use std::{
     hash::Hash,
 };
 
-use crossterm::event::KeyEvent;
+use crossterm::event::{KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 
 use crate::{
-    engine::{Engine, WindowId, document::DocumentData},
-    render::{Rect, ScreenBuffer, Window, helpers::draw_border, screen_buffer::Alignment, styling::hex_to_color},
+    engine::{
+        Engine, EngineEvent, WindowId, WindowState,
+        document::DocumentData,
+        documents::spreadsheet::{MIN_COLUMN_WIDTH, SpreadSheetDocumentData},
+        selection::{Selection, VisualKind},
+    },
+    render::{
+        Rect, ScreenBuffer, Window,
+        helpers::{draw_border, inner_rect},
+        screen_buffer::Alignment,
+        styling::hex_to_color,
+    },
 };
 
+/// How many rows one turn of the mouse wheel scrolls.
+const WHEEL_ROWS: usize = 3;
+
 pub struct TableWindow {
     pub window_id: WindowId,
+    /// What the left button is dragging since it went down in the window.
+    drag: Option<Drag>,
+}
+impl TableWindow {
+    pub fn new(window_id: WindowId) -> Self {
+        Self {
+            window_id,
+            drag: None,
+        }
+    }
+}
+
+enum Drag {
+    /// A selection from the cell the button went down on.
+    Cells((usize, usize)),
+    /// The right border of a column, which starts at the given terminal column.
+    Border { col: usize, start: usize },
 }
+
 impl Window for TableWindow {
     fn draw(&self, rect: &Rect, engine: &mut Engine, buffer: &mut ScreenBuffer) {
         let fg = hex_to_color(engine.config.styles.get("foreground").unwrap().as_str()).unwrap();
@@ -27,6 +58,7 @@ impl Window for TableWindow {
                 .as_str(),
         )
         .unwrap();
+        let selection_bg = engine.config.get_style_color("selection", Some(bg_secondary));
         let (win, doc) = engine.get_window(&self.window_id);
         let rect = draw_border(
             &self.window_id,
@@ -38,23 +70,16 @@ impl Window for TableWindow {
 
         if let DocumentData::SpreadSheet(data) = &doc.data {
             let (selected_row, selected_col) = data.selected_cell;
-            let max_rows = data.cells.keys().max().copied().unwrap_or(0);
-            let max_cols = data
-                .cells
-                .values()
-                .flat_map(|r| r.keys())
-                .max()
-                .copied()
-                .unwrap_or(0);
-
-            let mut col_widths: HashMap<usize, usize> = HashMap::new();
-            for col in 0..=max_cols {
-                let max_width = (0..max_rows)
-                    .filter_map(|row| data.cells.get(&row)?.get(&col).map(|s| s.raw.len()))
-                    .max()
-                    .unwrap_or(3);
-                col_widths.insert(col, vec![10, max_width + 4].into_iter().max().unwrap());
-            }
+            let (scroll_rows, scroll_cols) = (win.scroll_rows, win.scroll_cols);
+            let (rows, cols) = data.extent();
+            let max_rows = rows.saturating_sub(1);
+            let max_cols = cols.saturating_sub(1);
+            let right = rect.x + rect.width;
+            let bottom = rect.y + rect.height;
+
+            let col_widths: HashMap<usize, usize> = (0..=max_cols)
+                .map(|col| (col, data.column_width(col)))
+                .collect();
 
             // render col ids
             let mut loc: usize = rect.x;
@@ -68,11 +93,14 @@ impl Window for TableWindow {
                 .push(crossterm::style::Attribute::Bold);
 
             loc += 1;
-            for col in 0..=max_cols {
+            for col in scroll_cols..=max_cols {
+                if loc >= right {
+                    break;
+                }
                 let size = col_widths[&col];
                 let id = column_num_to_id(col);
                 let chars = ScreenBuffer::format_cell(id.as_str(), size, Alignment::Center);
-                for buf_idx in loc..loc + size {
+                for buf_idx in loc..(loc + size).min(right) {
                     let cell = &mut buffer.cells[rect.y][buf_idx];
                     cell.ch = chars[buf_idx - loc];
                     cell.bg = fg;
@@ -88,14 +116,20 @@ impl Window for TableWindow {
                 loc += size;
             }
 
-            for row in 0..=max_rows {
-                let buf_y = row + rect.y + 1;
+            for row in scroll_rows..=max_rows {
+                let buf_y = row - scroll_rows + rect.y + 1;
+                if buf_y >= bottom {
+                    break;
+                }
                 let color = if row % 2 == 0 { bg } else { bg_secondary };
                 let mut loc: usize = rect.x;
                 buffer.cells[buf_y][loc].ch = ' ';
                 buffer.cells[buf_y][loc].bg = fg;
                 loc += 1;
-                for col in 0..=max_cols {
+                for col in scroll_cols..=max_cols {
+                    if loc >= right {
+                        break;
+                    }
                     let size = col_widths[&col];
 
                     let mut raw: String = String::new();
@@ -105,8 +139,14 @@ impl Window for TableWindow {
                         raw = col.raw.clone();
                     }
 
+                    let color = match data.selected {
+                        Some(selection) if selection.contains(data.selected_cell, (row, col)) => {
+                            selection_bg
+                        }
+                        _ => color,
+                    };
                     let chars = ScreenBuffer::format_cell(raw.as_str(), size, Alignment::Center);
-                    for buf_idx in loc..loc + size {
+                    for buf_idx in loc..(loc + size).min(right) {
                         let cell = &mut buffer.cells[buf_y][buf_idx];
                         cell.ch = chars[buf_idx - loc];
                         cell.bg = color;
@@ -127,6 +167,141 @@ impl Window for TableWindow {
             }
         }
     }
+
+    fn handle_mouse(&mut self, rect: &Rect, engine: &mut Engine, event: MouseEvent) {
+        let (win, doc) = engine.get_window(&self.window_id);
+        let DocumentData::SpreadSheet(data) = &mut doc.data else {
+            return;
+        };
+        let rect = inner_rect(rect);
+        let (x, y) = (event.column as usize, event.row as usize);
+        let (cursor, selection) = (data.selected_cell, data.selected);
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.drag = if y == rect.y {
+                    border_at(data, win, &rect, x).map(|(col, start)| Drag::Border { col, start })
+                } else {
+                    cell_at(data, win, &rect, (x, y), false).map(|cell| {
+                        data.selected_cell = cell;
+                        data.selected = None;
+                        Drag::Cells(cell)
+                    })
+                };
+            }
+            MouseEventKind::Drag(MouseButton::Left) => match self.drag {
+                Some(Drag::Border { col, start }) => {
+                    let width = x.saturating_sub(start).max(MIN_COLUMN_WIDTH);
+                    data.column_widths.insert(col, width);
+                }
+                Some(Drag::Cells(from)) => {
+                    if let Some(cell) = cell_at(data, win, &rect, (x, y), true) {
+                        data.selected_cell = cell;
+                        data.selected =
+                            (cell != from).then(|| Selection::new(from, VisualKind::Block));
+                    }
+                }
+                None => {}
+            },
+            MouseEventKind::Up(_) => self.drag = None,
+            MouseEventKind::ScrollDown | MouseEventKind::ScrollUp
+                if event.modifiers.contains(KeyModifiers::SHIFT) =>
+            {
+                scroll(data, win, 0, event.kind == MouseEventKind::ScrollDown, 1)
+            }
+            MouseEventKind::ScrollDown => scroll(data, win, WHEEL_ROWS, true, 0),
+            MouseEventKind::ScrollUp => scroll(data, win, WHEEL_ROWS, false, 0),
+            MouseEventKind::ScrollRight => scroll(data, win, 0, true, 1),
+            MouseEventKind::ScrollLeft => scroll(data, win, 0, false, 1),
+            _ => {}
+        }
+        let moved = (data.selected_cell != cursor).then_some(data.selected_cell);
+        let selection_changed =
+            data.selected.map(|s| (s.anchor, s.kind)) != selection.map(|s| (s.anchor, s.kind));
+        let (win_id, doc_id) = (win.id.clone(), doc.id.clone());
+        if let Some((row, col)) = moved {
+            engine.emit(&EngineEvent::CursorMoved(win_id, row, col));
+        }
+        if selection_changed || (moved.is_some() && selection.is_some()) {
+            engine.emit(&EngineEvent::SelectionChanged(doc_id));
+        }
+    }
+}
+
+/// The cell drawn at `(x, y)` in a table inside `rect`. Points past the cells find
+/// nothing, or with `clamp` the nearest cell in view, so a drag can leave the table.
+fn cell_at(
+    data: &SpreadSheetDocumentData,
+    win: &WindowState,
+    rect: &Rect,
+    (x, y): (usize, usize),
+    clamp: bool,
+) -> Option<(usize, usize)> {
+    let (rows, cols) = data.extent();
+    let (last_row, last_col) = (rows.saturating_sub(1), cols.saturating_sub(1));
+    // the header takes the first row and the row gutter the first column
+    let (left, top) = (rect.x + 1, rect.y + 1);
+    let (x, y) = match clamp {
+        true => (
+            x.clamp(left, (rect.x + rect.width).saturating_sub(1).max(left)),
+            y.clamp(top, (rect.y + rect.height).saturating_sub(1).max(top)),
+        ),
+        false if x < left || y < top => return None,
+        false => (x, y),
+    };
+    let row = match win.scroll_rows + y - top {
+        row if row <= last_row => row,
+        _ if clamp => last_row,
+        _ => return None,
+    };
+    let mut loc = left;
+    for col in win.scroll_cols..=last_col {
+        loc += data.column_width(col);
+        if x < loc {
+            return Some((row, col));
+        }
+    }
+    clamp.then_some((row, last_col))
+}
+
+/// The column whose right border the header draws at `x`, and where the column starts.
+fn border_at(
+    data: &SpreadSheetDocumentData,
+    win: &WindowState,
+    rect: &Rect,
+    x: usize,
+) -> Option<(usize, usize)> {
+    let (_, cols) = data.extent();
+    let mut loc = rect.x + 1;
+    for col in win.scroll_cols..cols {
+        let start = loc;
+        loc += data.column_width(col);
+        if x == loc {
+            return Some((col, start));
+        }
+    }
+    None
+}
+
+/// Scrolls the table by `rows` or `cols` down or right if `forward`, without going
+/// past its last row or column.
+fn scroll(
+    data: &SpreadSheetDocumentData,
+    win: &mut WindowState,
+    rows: usize,
+    forward: bool,
+    cols: usize,
+) {
+    let (last_row, last_col) = {
+        let (rows, cols) = data.extent();
+        (rows.saturating_sub(1), cols.saturating_sub(1))
+    };
+    if forward {
+        win.scroll_rows = (win.scroll_rows + rows).min(last_row);
+        win.scroll_cols = (win.scroll_cols + cols).min(last_col);
+    } else {
+        win.scroll_rows = win.scroll_rows.saturating_sub(rows);
+        win.scroll_cols = win.scroll_cols.saturating_sub(cols);
+    }
 }
 
 fn column_num_to_id(mut col: usize) -> String {
@@ -142,5 +317,3 @@ fn column_num_to_id(mut col: usize) -> String {
 
     result.chars().rev().collect()
 }
-
-
@@ -1,7 +1,9 @@
 use crossterm::event::{KeyCode, KeyEvent};
 
 use crate::{
-    engine::{Engine, WindowId, document::DocumentData},
+    engine::{
+        Engine, WindowId, document::DocumentData, motion::line_len, selection::VisualKind,
+    },
     render::{Rect, ScreenBuffer, Window, helpers::draw_border},
 };
 
@@ -14,6 +16,7 @@ impl Window for TextWindow {
         // Draw the border first
         let bg = engine.config.get_style_color("background", None);
         let fg = engine.config.get_style_color("foreground", None);
+        let selection_bg = engine.config.get_style_color("selection", Some(fg));
         // Get window state and document
         let (window, doc) = engine.get_window(&self.window_id);
         let focussed = self.window_id == window.id;
@@ -22,6 +25,8 @@ impl Window for TextWindow {
         if let DocumentData::Text(lines) = &doc.data {
             let cursor_row = window.cursor_row; // for border
             let cursor_col = window.cursor_col;
+            let (scroll_rows, scroll_cols) = (window.scroll_rows, window.scroll_cols);
+            let selection = lines.selected;
 
             // Iterate over each cell in the window rect
 
@@ -35,15 +40,28 @@ impl Window for TextWindow {
                     cell.bg = bg;
                     cell.fg = fg;
 
-                    if let Some(line) = lines.data.get(row) {
-                        let chars: Vec<char> = line.chars().collect();
-                        cell.ch = chars.get(col).copied().unwrap_or(' ');
+                    if let Some(line) = lines.data.get(row + scroll_rows) {
+                        cell.ch = line.chars().nth(col + scroll_cols).unwrap_or(' ');
                     } else {
                         cell.ch = ' ';
                     }
 
+                    // Selection highlight: a block covers its whole rectangle, otherwise
+                    // only the text and one cell for an empty line
+                    let pos = (row + scroll_rows, col + scroll_cols);
+                    if let Some(selection) = selection
+                        && selection.contains((cursor_row, cursor_col), pos)
+                        && (selection.kind == VisualKind::Block
+                            || pos.1 < line_len(&lines.data, pos.0).max(1))
+                    {
+                        cell.bg = selection_bg;
+                    }
+
                     // Cursor highlight
-                    if row == cursor_row && col_idx == rect.x as usize + cursor_col && focussed {
+                    if row + scroll_rows == cursor_row
+                        && col + scroll_cols == cursor_col
+                        && focussed
+                    {
                         std::mem::swap(&mut cell.bg, &mut cell.fg);
                     }
                 }
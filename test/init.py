@@ -1,3 +1,5 @@
+from __future__ import annotations
+
 from typing import TYPE_CHECKING
 
 if TYPE_CHECKING:
@@ -8,25 +10,25 @@ print(api.call)
 
 def my_func(api: API, args):
     print("called from python")
-    api.call("test")
+    api.call("ui.notify", {"level": "info", "text": "Hello from python"})
 
 
 def change_mode(api: API, args):
     api.call("doc.changeMode", {"mode":"insert"})
 
-api.call("command.register_command", {
+api.call("command.register", {
     "id": "say_hello",
     "function": my_func})
-api.call("command.register_command", {
+api.call("command.register", {
     "id": "change_mode",
     "function": change_mode})
 
-api.call("command.register_keybind", {
+api.call("keybind.register", {
     "keys": ["ctrl+h"],
     "command_id": "say_hello"
 })
 
-api.call("command.register_keybind", {
+api.call("keybind.register", {
     "keys": ["ctrl+i"],
     "command_id": "change_mode"
 })
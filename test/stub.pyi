@@ -0,0 +1,602 @@
+# Generated by `neocel --gen-stubs`, do not edit.
+"""Type stubs for the neocel plugin API."""
+
+from typing import Any, Callable, Dict, List, Literal, NotRequired, Optional, Tuple, TypedDict, Union, overload
+
+class NeocelError(RuntimeError):
+    """Base class of every error raised by `api.call`."""
+
+class UnknownMethodError(NeocelError):
+    """No API method has the given name."""
+
+class InvalidParamsError(NeocelError):
+    """The params do not match what the method expects."""
+
+class NotFoundError(NeocelError):
+    """A window, document, command or subscription does not exist."""
+
+class WrongDocumentTypeError(NeocelError):
+    """The method does not support the document's type."""
+
+class PluginError(NeocelError):
+    """A Python command or event handler raised."""
+
+class NeocelIOError(NeocelError):
+    """Reading or writing a file failed."""
+
+APIDescription = TypedDict("APIDescription", {"$defs": Dict[str, Any], "methods": List[APIMethodInfo]})
+
+class APIMethodInfo(TypedDict):
+    description: str
+    name: str
+    params: NotRequired[Any]
+    params_optional: bool
+    returns: NotRequired[Any]
+
+BorderStyle = Literal["single", "double", "rounded", "shadow", "none"]
+
+class Cell(TypedDict):
+    ast: NotRequired[Optional[Expr]]
+    dependencies: List[str]
+    raw: str
+    used_by: List[str]
+    value: CellValue
+
+CellValue = Union[Literal["Empty"], CellValueNumber, CellValueText, CellValueError]
+
+class CellValueError(TypedDict):
+    Error: str
+
+class CellValueNumber(TypedDict):
+    Number: float
+
+class CellValueText(TypedDict):
+    Text: str
+
+class ChangeModeParams(TypedDict):
+    mode: ModeType
+
+class CommandInfo(TypedDict):
+    """A registered command as listed by `command.list`."""
+    args: NotRequired[Any]
+    description: NotRequired[Optional[str]]
+    doc_type: NotRequired[Optional[DocType]]
+    id: str
+    plugin: NotRequired[Optional[str]]
+    source: CommandSource
+    title: NotRequired[Optional[str]]
+
+class CommandRequest(TypedDict):
+    args: List[Any]
+    id: str
+
+CommandSource = Literal["rust", "python", "internal"]
+
+CreateWindowParams = Union[CreateWindowParamsSplit, CreateWindowParamsFloating]
+
+class CreateWindowParamsFloating(TypedDict):
+    Floating: CreateWindowParamsFloatingFields
+
+class CreateWindowParamsFloatingFields(TypedDict):
+    border: NotRequired[Optional[BorderStyle]]
+    col: NotRequired[Optional[int]]
+    doc: str
+    enter: bool
+    focusable: NotRequired[Optional[bool]]
+    height: int
+    position: PopupPosition
+    relative: RelativeTo
+    row: NotRequired[Optional[int]]
+    style: NotRequired[Optional[str]]
+    width: int
+    win: NotRequired[Optional[str]]
+    zindex: NotRequired[Optional[int]]
+
+class CreateWindowParamsSplit(TypedDict):
+    Split: CreateWindowParamsSplitFields
+
+class CreateWindowParamsSplitFields(TypedDict):
+    border: NotRequired[Optional[BorderStyle]]
+    direction: SplitDirection
+    doc: str
+    enter: bool
+    ratio: NotRequired[Optional[float]]
+    src_win: str
+
+class CursorResult(TypedDict):
+    col: int
+    row: int
+
+class DimensionsResult(TypedDict):
+    col: int
+    height: int
+    row: int
+    width: int
+
+class DocIdParams(TypedDict):
+    doc_id: NotRequired[Optional[str]]
+
+class DocIdResult(TypedDict):
+    doc_id: str
+
+DocType = Literal["spread_sheet", "info", "text", "palette"]
+
+class Document(TypedDict):
+    data: DocumentData
+    doc_type: DocType
+    id: str
+    path: NotRequired[Optional[str]]
+
+DocumentData = Union[DocumentDataSpreadSheet, DocumentDataText, DocumentDataHelp, DocumentDataConfig, DocumentDataPalette]
+
+class DocumentDataConfig(TypedDict):
+    Config: str
+
+class DocumentDataHelp(TypedDict):
+    Help: str
+
+class DocumentDataPalette(TypedDict):
+    Palette: PaletteDocumentData
+
+class DocumentDataSpreadSheet(TypedDict):
+    SpreadSheet: SpreadSheetDocumentData
+
+class DocumentDataText(TypedDict):
+    Text: TextDocumentData
+
+EngineEventKind = Literal["window_create", "window_close", "window_doc_change", "layout_change", "document_create", "document_changed", "document_saved", "document_closed", "cursor_moved", "selection_changed", "mode_changed", "before_quit", "message", "input_event"]
+
+Expr = Dict[str, Any]
+
+class KeybindInfo(TypedDict):
+    """A key binding as listed by `keybind.list`."""
+    builtin: NotRequired[Optional[str]]
+    command: NotRequired[Optional[CommandRequest]]
+    keys: List[str]
+    scope: KeybindScope
+
+KeybindScope = Union[Literal["global"], KeybindScopeDocType, KeybindScopeWindow, KeybindScopeBuffer, KeybindScopeMode]
+
+class KeybindScopeBuffer(TypedDict):
+    """One document by its id."""
+    buffer: str
+
+class KeybindScopeDocType(TypedDict):
+    """Every document of a type, including ones opened later."""
+    doc_type: DocType
+
+class KeybindScopeMode(TypedDict):
+    mode: ModeType
+
+class KeybindScopeWindow(TypedDict):
+    """One window by its id."""
+    window: str
+
+class ListKeybinds(TypedDict):
+    scope: NotRequired[Optional[KeybindScope]]
+
+class Message(TypedDict):
+    """A line for the footer's message area, and optionally a notification popup."""
+    level: MessageLevel
+    popup: bool
+    text: str
+    time: int
+
+MessageLevel = Literal["info", "warn", "error"]
+
+ModeType = Union[Literal["Input", "Visualize", "Normal"], Literal["Command"]]
+
+class NotifyParams(TypedDict):
+    level: MessageLevel
+    popup: NotRequired[bool]
+    text: str
+    timeout_ms: NotRequired[Optional[int]]
+
+class OpenParams(TypedDict):
+    path: str
+    win_id: NotRequired[Optional[str]]
+
+class OperatorParams(TypedDict):
+    char: NotRequired[Optional[str]]
+    count: NotRequired[Optional[int]]
+    modifier: NotRequired[Optional[str]]
+    motion: NotRequired[Optional[str]]
+    operator: NotRequired[Optional[str]]
+    register: NotRequired[Optional[str]]
+    selection: NotRequired[Optional[SelectionSize]]
+
+class PaletteDocumentData(TypedDict):
+    """The query line and filtered command list of a command palette."""
+    entries: List[PaletteEntry]
+    matches: List[int]
+    origin: str
+    origin_mode: ModeType
+    query: str
+    selected: int
+
+class PaletteEntry(TypedDict):
+    """A command offered by the palette."""
+    description: NotRequired[Optional[str]]
+    id: str
+    keys: List[str]
+    title: NotRequired[Optional[str]]
+
+class PasteParams(TypedDict):
+    text: str
+
+class PlayParams(TypedDict):
+    count: NotRequired[Optional[int]]
+    register: str
+
+PopupPosition = Literal["top_right", "top_left", "bottom_right", "botton_left", "center", "absolute"]
+
+class PutParams(TypedDict):
+    before: NotRequired[bool]
+    count: NotRequired[Optional[int]]
+    register: NotRequired[Optional[str]]
+    transpose: NotRequired[bool]
+
+class RecordParams(TypedDict):
+    register: str
+
+class Register(TypedDict):
+    """Text yanked or deleted by an operator, ready to be put back."""
+    kind: NotRequired[RegisterKind]
+    text: List[str]
+
+class RegisterCommandParams(TypedDict):
+    """What the command palette, help and completion show about a command."""
+    args: NotRequired[Any]
+    description: NotRequired[Optional[str]]
+    doc_type: NotRequired[Optional[DocType]]
+    function: Callable[..., Any]
+    id: str
+    plugin: NotRequired[Optional[str]]
+    title: NotRequired[Optional[str]]
+
+class RegisterKeybind(TypedDict):
+    command_id: NotRequired[Optional[str]]
+    keys: List[str]
+    mode: NotRequired[Optional[ModeType]]
+    params: NotRequired[Optional[List[Any]]]
+    scope: NotRequired[Optional[KeybindScope]]
+
+RegisterKind = Union[Literal["chars"], Literal["lines"], Literal["cells"], Literal["block"]]
+
+RelativeTo = Union[Literal["editor", "cursor"], RelativeToWin]
+
+class RelativeToWin(TypedDict):
+    win: str
+
+class RepeatParams(TypedDict):
+    count: NotRequired[Optional[int]]
+
+class RunCommandParams(TypedDict):
+    command: CommandRequest
+
+class SaveParams(TypedDict):
+    doc_id: NotRequired[Optional[str]]
+    path: NotRequired[Optional[str]]
+
+class SaveResult(TypedDict):
+    path: str
+
+class Selection(TypedDict):
+    """A visual selection: it runs from where visual mode started to the cursor, or to
+the selected cell of a spreadsheet, and grows and shrinks as that moves."""
+    anchor: Tuple[int, int]
+    kind: VisualKind
+
+class SelectionSize(TypedDict):
+    """The size of a selection without where it is, as `.` repeats an operator used in
+visual mode on as much from the cursor."""
+    cols: int
+    kind: VisualKind
+    rows: int
+
+class SetRegisterParams(TypedDict):
+    name: str
+    register: Register
+
+class SetTimeoutParams(TypedDict):
+    ms: int
+
+class SortParams(TypedDict):
+    column: NotRequired[Optional[int]]
+    descending: NotRequired[bool]
+
+SplitDirection = Literal["up", "down", "left", "right"]
+
+class SpreadSheetDocumentData(TypedDict):
+    cells: Dict[str, Dict[str, Cell]]
+    column_widths: Dict[str, int]
+    selected: NotRequired[Optional[Selection]]
+    selected_cell: Tuple[int, int]
+
+class SubscribeParams(TypedDict):
+    callback: Callable[..., Any]
+    event: EngineEventKind
+
+class SubscriptionParams(TypedDict):
+    subscription_id: str
+
+class TextDocumentData(TypedDict):
+    data: List[str]
+    selected: NotRequired[Optional[Selection]]
+
+class UnregisterKeybind(TypedDict):
+    keys: List[str]
+    mode: NotRequired[Optional[ModeType]]
+    scope: NotRequired[Optional[KeybindScope]]
+
+class ViewResult(TypedDict):
+    scroll_cols: int
+    scroll_rows: int
+
+VisualKind = Union[Literal["char"], Literal["line"], Literal["block"]]
+
+class VisualParams(TypedDict):
+    kind: NotRequired[Optional[VisualKind]]
+
+class WindowCursorParams(TypedDict):
+    col: int
+    row: int
+    win_id: str
+
+class WindowIdParams(TypedDict):
+    win_id: str
+
+class WindowInfo(TypedDict):
+    document: Document
+    window: WindowState
+
+class WindowMoveParams(TypedDict):
+    dir: SplitDirection
+
+class WindowOptionParams(TypedDict):
+    option: str
+    value: NotRequired[Any]
+    win_id: str
+
+class WindowResizeParams(TypedDict):
+    cells: NotRequired[Optional[int]]
+    dir: SplitDirection
+    win_id: NotRequired[Optional[str]]
+
+class WindowRotateParams(TypedDict):
+    reverse: NotRequired[bool]
+    win_id: NotRequired[Optional[str]]
+
+class WindowState(TypedDict):
+    border_style: NotRequired[Optional[BorderStyle]]
+    cursor_col: int
+    cursor_row: int
+    doc_id: str
+    id: str
+    scroll_cols: int
+    scroll_rows: int
+
+class WindowSwapParams(TypedDict):
+    dir: SplitDirection
+    win_id: NotRequired[Optional[str]]
+
+class WindowViewParams(TypedDict):
+    scroll_cols: int
+    scroll_rows: int
+    win_id: str
+
+class WindowZoomParams(TypedDict):
+    win_id: NotRequired[Optional[str]]
+
+class API:
+    """The editor API, passed to commands and event handlers and bound to `api` in scripts"""
+
+    @overload
+    def call(self, id: Literal["api.list"]) -> APIDescription:
+        """Lists every API method with JSON schemas for its params and result."""
+
+    @overload
+    def call(self, id: Literal["cmdline.complete"]) -> None:
+        """Completes the command id or file path before the command-line cursor."""
+
+    @overload
+    def call(self, id: Literal["command.list"]) -> List[CommandInfo]:
+        """Lists global and per-document commands with their metadata."""
+
+    @overload
+    def call(self, id: Literal["command.register"], params: RegisterCommandParams) -> None:
+        """Registers a Python function as a command, optionally for one document type."""
+
+    @overload
+    def call(self, id: Literal["command.run"], params: RunCommandParams) -> Any:
+        """Runs a command and returns its result."""
+
+    @overload
+    def call(self, id: Literal["command.test"]) -> None:
+        """Shows a test message."""
+
+    @overload
+    def call(self, id: Literal["doc.changeMode"], params: ChangeModeParams) -> None:
+        """Switches the input mode."""
+
+    @overload
+    def call(self, id: Literal["doc.close"], params: Optional[DocIdParams] = None) -> None:
+        """Closes a document, by default the current one, and the windows showing it."""
+
+    @overload
+    def call(self, id: Literal["doc.open"], params: OpenParams) -> DocIdResult:
+        """Opens a file in a window, by default the current one."""
+
+    @overload
+    def call(self, id: Literal["doc.save"], params: Optional[SaveParams] = None) -> SaveResult:
+        """Writes a document, by default the current one, to its path or to `path`."""
+
+    @overload
+    def call(self, id: Literal["doc.sort"], params: Optional[SortParams] = None) -> None:
+        """Sorts the current sheet's rows by a column, or the current text's lines."""
+
+    @overload
+    def call(self, id: Literal["editor.operator"], params: OperatorParams) -> None:
+        """Moves the cursor by a normal mode motion, or applies an operator over it."""
+
+    @overload
+    def call(self, id: Literal["editor.paste"], params: PasteParams) -> None:
+        """Inserts pasted text at the cursor, or spreads it over the cells of a sheet."""
+
+    @overload
+    def call(self, id: Literal["editor.put"], params: Optional[PutParams] = None) -> None:
+        """Puts a register after the cursor, or at the selected cell of a sheet."""
+
+    @overload
+    def call(self, id: Literal["editor.repeat"], params: Optional[RepeatParams] = None) -> None:
+        """Repeats the last change made in normal mode, like `.`."""
+
+    @overload
+    def call(self, id: Literal["editor.undo"]) -> None:
+        """Reverts the last change on the current document's undo stack, like `u`."""
+
+    @overload
+    def call(self, id: Literal["editor.visual"], params: Optional[VisualParams] = None) -> None:
+        """Starts, switches or ends visual mode with a selection anchored at the cursor."""
+
+    @overload
+    def call(self, id: Literal["events.subscribe"], params: SubscribeParams) -> SubscriptionParams:
+        """Calls `callback(api, event)` whenever an event of the given kind is emitted."""
+
+    @overload
+    def call(self, id: Literal["events.unsubscribe"], params: SubscriptionParams) -> None:
+        """Removes a subscription."""
+
+    @overload
+    def call(self, id: Literal["keybind.list"], params: Optional[ListKeybinds] = None) -> List[KeybindInfo]:
+        """Lists every key binding, or those in one keymap, with the scope it is in."""
+
+    @overload
+    def call(self, id: Literal["keybind.register"], params: RegisterKeybind) -> None:
+        """Binds a key sequence to a command."""
+
+    @overload
+    def call(self, id: Literal["keybind.set_timeout"], params: SetTimeoutParams) -> None:
+        """Sets how long a key sequence waits for its next key."""
+
+    @overload
+    def call(self, id: Literal["keybind.unregister"], params: UnregisterKeybind) -> None:
+        """Removes the binding of a key sequence from one keymap."""
+
+    @overload
+    def call(self, id: Literal["kill"]) -> None:
+        """Asks to quit; `before_quit` handlers may refuse."""
+
+    @overload
+    def call(self, id: Literal["macro.play"], params: PlayParams) -> None:
+        """Feeds the keys in a register as if typed."""
+
+    @overload
+    def call(self, id: Literal["macro.record"], params: RecordParams) -> None:
+        """Starts recording typed keys into a register."""
+
+    @overload
+    def call(self, id: Literal["macro.stop"]) -> None:
+        """Stops recording and stores the keys in the register as text."""
+
+    @overload
+    def call(self, id: Literal["palette.accept"]) -> None:
+        """Closes the palette and runs the highlighted command."""
+
+    @overload
+    def call(self, id: Literal["palette.close"]) -> None:
+        """Closes the palette without running anything."""
+
+    @overload
+    def call(self, id: Literal["palette.open"]) -> None:
+        """Opens the command palette over the current window."""
+
+    @overload
+    def call(self, id: Literal["registers.list"]) -> Dict[str, Register]:
+        """Returns every register that holds something, by name."""
+
+    @overload
+    def call(self, id: Literal["registers.set"], params: SetRegisterParams) -> None:
+        """Fills a register; `+` also copies to the system clipboard."""
+
+    @overload
+    def call(self, id: Literal["ui.messages"]) -> List[Message]:
+        """Lists the messages shown so far, oldest first."""
+
+    @overload
+    def call(self, id: Literal["ui.notify"], params: NotifyParams) -> None:
+        """Shows a message in the footer, and as a popup if `popup` is set."""
+
+    @overload
+    def call(self, id: Literal["ui.show_messages"]) -> None:
+        """Opens the message history in a split below the current window."""
+
+    @overload
+    def call(self, id: Literal["window.close"], params: WindowIdParams) -> None:
+        """Closes a window."""
+
+    @overload
+    def call(self, id: Literal["window.create"], params: CreateWindowParams) -> None:
+        """Opens a split or floating window on a document."""
+
+    @overload
+    def call(self, id: Literal["window.equalize"]) -> None:
+        """Gives the windows in each row and column of the layout equal space."""
+
+    @overload
+    def call(self, id: Literal["window.get_current"]) -> WindowInfo:
+        """Returns the focused window and its document."""
+
+    @overload
+    def call(self, id: Literal["window.get_cursor"], params: WindowIdParams) -> CursorResult:
+        """Returns the cursor, or the selected cell of a spreadsheet."""
+
+    @overload
+    def call(self, id: Literal["window.get_dimensions"], params: WindowIdParams) -> DimensionsResult:
+        """Returns where a window is drawn on screen."""
+
+    @overload
+    def call(self, id: Literal["window.get_option"], params: WindowOptionParams) -> Any:
+        """Reads a window option such as `border_style`."""
+
+    @overload
+    def call(self, id: Literal["window.get_view"], params: WindowIdParams) -> ViewResult:
+        """Returns the first row and column shown in a window."""
+
+    @overload
+    def call(self, id: Literal["window.get_window"], params: WindowIdParams) -> WindowInfo:
+        """Returns a window and its document."""
+
+    @overload
+    def call(self, id: Literal["window.move"], params: WindowMoveParams) -> Optional[WindowIdParams]:
+        """Focuses the neighbouring window in a direction, returning it if there is one."""
+
+    @overload
+    def call(self, id: Literal["window.resize"], params: WindowResizeParams) -> None:
+        """Moves a window's edge on one side by some rows or columns, out or in."""
+
+    @overload
+    def call(self, id: Literal["window.rotate"], params: Optional[WindowRotateParams] = None) -> None:
+        """Moves each window in a window's row or column one place along."""
+
+    @overload
+    def call(self, id: Literal["window.set_cursor"], params: WindowCursorParams) -> None:
+        """Moves the cursor, or selects a cell of a spreadsheet."""
+
+    @overload
+    def call(self, id: Literal["window.set_option"], params: WindowOptionParams) -> None:
+        """Sets a window option such as `border_style`."""
+
+    @overload
+    def call(self, id: Literal["window.set_view"], params: WindowViewParams) -> None:
+        """Scrolls a window so the given row and column come first."""
+
+    @overload
+    def call(self, id: Literal["window.swap"], params: WindowSwapParams) -> Optional[WindowIdParams]:
+        """Exchanges a window with its neighbour in a direction, returning it if any."""
+
+    @overload
+    def call(self, id: Literal["window.zoom"], params: Optional[WindowZoomParams] = None) -> None:
+        """Zooms a window to fill the screen, or restores the layout if one is zoomed."""
+
+api: API